@@ -0,0 +1,389 @@
+// Configuration comes from a build plan, usually the project's config.dog. It holds one or more
+// config declarations, and a config may extend another one:
+//
+//   config debug {
+//       version: '1.0'
+//       database: { host: 'localhost', port: 5432 }
+//   }
+//   config release: debug {
+//       database: { host: 'db.example.com' }
+//   }
+//
+// A config that extends another starts with all of the values of the one it extends and then
+// sets its own over them. Maps are merged key by key, all the way down, so release above still
+// has the port. Anything else, lists included, is replaced.
+//
+// A few keys mean something to the compiler and are checked: version, libraries, log_level and
+// target_triple. Every key, known or not, can be read from dog code as a constant, like
+// `config.database.port`.
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::lex::number_literal::NumberValue;
+use crate::source::Span;
+use crate::transform::ast::{ConfigDecl, ConfigEntry, ConfigValue, ConfigValueKind, Literal};
+
+pub const LOG_LEVELS: [&str; 6] = ["none", "error", "warning", "info", "debug", "trace"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    pub message: String,
+    pub file_name: String,
+    pub span: Span,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}:{}:{}", self.message, self.file_name, self.span.line_number, self.span.line_offset)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Value {
+    pub kind: ValueKind,
+    // where the value was set, in whichever config set it
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueKind {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Null,
+    Map(Vec<Entry>),
+    List(Vec<Value>),
+}
+
+impl ValueKind {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            ValueKind::String(_) => "a string",
+            ValueKind::Integer(_) => "an int",
+            ValueKind::Float(_) => "a float",
+            ValueKind::Boolean(_) => "a bool",
+            ValueKind::Null => "null",
+            ValueKind::Map(_) => "a map",
+            ValueKind::List(_) => "a list",
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub value: Value,
+}
+
+// A config with the values of every config it extends merged in, and the keys that the compiler
+// knows about read out of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub name: String,
+    pub entries: Vec<Entry>,
+    pub version: Option<String>,
+    // linked into every executable, by name like `m` or by path
+    pub libraries: Vec<String>,
+    pub log_level: String,
+    // what to generate code for, the machine the compiler runs on when there's none
+    pub target_triple: Option<String>,
+}
+
+// the config for a file that's compiled on its own, nothing is set
+impl Default for Config {
+    fn default() -> Config {
+        return Config {
+            name: "default".to_string(),
+            entries: vec![],
+            version: None,
+            libraries: vec![],
+            log_level: "info".to_string(),
+            target_triple: None,
+        };
+    }
+}
+
+impl Config {
+    // the value at a path of keys, like ["database", "port"] for config.database.port
+    pub fn get(&self, path: &[&str]) -> Option<&Value> {
+        let (first, rest) = path.split_first()?;
+        let mut value = &self.entries.iter().find(|entry| entry.name == *first)?.value;
+        for key in rest {
+            value = match &value.kind {
+                ValueKind::Map(entries) => &entries.iter().find(|entry| entry.name == *key)?.value,
+                _ => return None,
+            };
+        }
+        return Some(value);
+    }
+}
+
+struct Resolver<'a> {
+    decls: &'a [ConfigDecl],
+    file_name: &'a str,
+}
+
+// Resolves the config with this name, or an error naming the config that extends one that isn't
+// declared or that extends itself by way of others, or a known key with a value that can't be.
+pub fn resolve(decls: &[ConfigDecl], name: &str, file_name: &str) -> Result<Config, ConfigError> {
+    let resolver = Resolver { decls, file_name };
+    let chain = resolver.chain(name)?;
+    let mut entries: Vec<Entry> = vec![];
+    for decl in chain.iter().rev() {
+        let own = resolver.map(&decl.entries)?;
+        merge(&mut entries, own);
+    }
+    let mut result = Config {
+        name: name.to_string(),
+        entries,
+        ..Config::default()
+    };
+    resolver.read_known_keys(&mut result)?;
+    return Ok(result);
+}
+
+// the child's entries over the parent's, maps merged key by key
+fn merge(into: &mut Vec<Entry>, from: Vec<Entry>) {
+    for entry in from {
+        let existing = into.iter_mut().find(|existing| existing.name == entry.name);
+        match existing {
+            Some(existing) => {
+                if let (ValueKind::Map(existing_entries), ValueKind::Map(entries)) = (&mut existing.value.kind, &entry.value.kind) {
+                    merge(existing_entries, entries.clone());
+                    existing.value.span = entry.value.span;
+                } else {
+                    *existing = entry;
+                }
+            }
+            None => into.push(entry),
+        }
+    }
+}
+
+impl<'a> Resolver<'a> {
+    fn error(&self, span: Span, message: String) -> ConfigError {
+        return ConfigError {
+            message,
+            file_name: self.file_name.to_string(),
+            span,
+        };
+    }
+
+    // from the config asked for up to the one that doesn't extend anything
+    fn chain(&self, name: &str) -> Result<Vec<&'a ConfigDecl>, ConfigError> {
+        let mut decl = match self.decls.iter().find(|decl| decl.name.text == name) {
+            Some(decl) => decl,
+            None => {
+                let span = self.decls.first().map(|decl| decl.span).unwrap_or(Span {
+                    file_id: None,
+                    start: 0,
+                    end: 0,
+                    line_number: 1,
+                    line_offset: 1,
+                });
+                return Err(self.error(span, format!("no config named {}", name)));
+            }
+        };
+        let mut result = vec![decl];
+        while let Some(extends) = &decl.extends {
+            decl = match self.decls.iter().find(|parent| parent.name.text == extends.text) {
+                Some(parent) => parent,
+                None => return Err(self.error(extends.span, format!("config {} extends {}, which isn't declared", decl.name.text, extends.text))),
+            };
+            // the configs before the cycle aren't part of it, they only lead into it
+            if let Some(start) = result.iter().position(|seen| seen.name.text == decl.name.text) {
+                let names: Vec<&str> = result[start..].iter().map(|seen| seen.name.text.as_str()).collect();
+                return Err(self.error(extends.span, format!("config {} extends itself: {} -> {}", decl.name.text, names.join(" -> "), decl.name.text)));
+            }
+            result.push(decl);
+        }
+        return Ok(result);
+    }
+
+    fn map(&self, config_entries: &[ConfigEntry]) -> Result<Vec<Entry>, ConfigError> {
+        let mut result: Vec<Entry> = vec![];
+        for config_entry in config_entries {
+            if result.iter().any(|entry| entry.name == config_entry.name.text) {
+                return Err(self.error(config_entry.name.span, format!("{} is set more than once", config_entry.name.text)));
+            }
+            result.push(Entry {
+                name: config_entry.name.text.clone(),
+                value: self.value(&config_entry.value)?,
+            });
+        }
+        return Ok(result);
+    }
+
+    fn value(&self, config_value: &ConfigValue) -> Result<Value, ConfigError> {
+        let kind = match &config_value.kind {
+            ConfigValueKind::Literal(Literal::String(text)) => ValueKind::String(text.clone()),
+            ConfigValueKind::Literal(Literal::Number(number)) => match number.value {
+                NumberValue::Float(float) => ValueKind::Float(float),
+                NumberValue::Integer(integer) if number.suffix.is_some_and(|suffix| suffix.is_float()) => ValueKind::Float(integer as f64),
+                NumberValue::Integer(integer) => match i64::try_from(integer) {
+                    Ok(integer) => ValueKind::Integer(integer),
+                    Err(_) => return Err(self.error(config_value.span, format!("{} is too big for an int", integer))),
+                },
+            },
+            ConfigValueKind::Literal(Literal::Boolean(value)) => ValueKind::Boolean(*value),
+            ConfigValueKind::Literal(Literal::Null) => ValueKind::Null,
+            ConfigValueKind::Map(entries) => ValueKind::Map(self.map(entries)?),
+            ConfigValueKind::List(values) => {
+                let mut result = vec![];
+                for value in values {
+                    result.push(self.value(value)?);
+                }
+                ValueKind::List(result)
+            }
+        };
+        return Ok(Value {
+            kind,
+            span: config_value.span,
+        });
+    }
+
+    fn read_known_keys(&self, config: &mut Config) -> Result<(), ConfigError> {
+        if let Some(value) = config.get(&["version"]) {
+            let version = self.string(value, "version")?;
+            let numbers_only = version.split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+            if !numbers_only {
+                return Err(self.error(value.span, format!("version must be numbers separated by periods, like 1.0.2, found '{}'", version)));
+            }
+            config.version = Some(version);
+        }
+        if let Some(value) = config.get(&["libraries"]) {
+            let values = match &value.kind {
+                ValueKind::List(values) => values,
+                other => return Err(self.error(value.span, format!("libraries must be a list, found {}", other.name()))),
+            };
+            let mut libraries = vec![];
+            for library in values {
+                let library_name = self.string(library, "a library")?;
+                if library_name.trim().is_empty() {
+                    return Err(self.error(library.span, "a library must have a name".to_string()));
+                }
+                libraries.push(library_name);
+            }
+            config.libraries = libraries;
+        }
+        if let Some(value) = config.get(&["log_level"]) {
+            let log_level = self.string(value, "log_level")?;
+            if !LOG_LEVELS.contains(&log_level.as_str()) {
+                return Err(self.error(value.span, format!("log_level must be one of {}, found '{}'", LOG_LEVELS.join(", "), log_level)));
+            }
+            config.log_level = log_level;
+        }
+        if let Some(value) = config.get(&["target_triple"]) {
+            let target_triple = self.string(value, "target_triple")?;
+            let parts: Vec<&str> = target_triple.split('-').collect();
+            if parts.len() < 3 || parts.iter().any(|part| part.is_empty()) {
+                return Err(self.error(value.span, format!("target_triple must be like x86_64-unknown-linux-gnu, found '{}'", target_triple)));
+            }
+            config.target_triple = Some(target_triple);
+        }
+        return Ok(());
+    }
+
+    fn string(&self, value: &Value, what: &str) -> Result<String, ConfigError> {
+        return match &value.kind {
+            ValueKind::String(text) => Ok(text.clone()),
+            other => Err(self.error(value.span, format!("{} must be a string, found {}", what, other.name()))),
+        };
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use crate::config::{resolve, ValueKind};
+    use crate::source::SourceMap;
+    use crate::transform::ast::ConfigDecl;
+    use crate::transform::transform_config;
+
+    fn config_decls(code: &str) -> Vec<ConfigDecl> {
+        return transform_config(&mut SourceMap::new(), code);
+    }
+
+    fn value(decls: &[ConfigDecl], config: &str, path: &[&str]) -> ValueKind {
+        let config = resolve(decls, config, "config.dog").unwrap();
+        return config.get(path).unwrap().kind.clone();
+    }
+
+    fn error(code: &str, config: &str) -> String {
+        return resolve(&config_decls(code), config, "config.dog").unwrap_err().to_string();
+    }
+
+    #[test]
+    fn resolve_extended_configs() {
+        let decls = config_decls("\
+config base {
+    version: '1.0'
+    name: 'base'
+    database: {
+        host: 'localhost'
+        port: 5432
+        options: { timeout: 1.5, retry: true }
+    }
+    libraries: ['m', 'z']
+}
+config debug: base {
+    name: 'debug'
+    database: {
+        host: 'db.example.com'
+        options: { retry: false }
+    }
+    libraries: ['m']
+}
+config local: debug {
+    log_level: 'trace'
+}
+");
+        assert_eq!(ValueKind::String("base".to_string()), value(&decls, "base", &["name"]));
+        assert_eq!(ValueKind::String("debug".to_string()), value(&decls, "local", &["name"]));
+
+        // maps are merged all the way down, lists are replaced
+        assert_eq!(ValueKind::String("db.example.com".to_string()), value(&decls, "local", &["database", "host"]));
+        assert_eq!(ValueKind::Integer(5432), value(&decls, "local", &["database", "port"]));
+        assert_eq!(ValueKind::Float(1.5), value(&decls, "local", &["database", "options", "timeout"]));
+        assert_eq!(ValueKind::Boolean(false), value(&decls, "local", &["database", "options", "retry"]));
+
+        let local = resolve(&decls, "local", "config.dog").unwrap();
+        let keys: Vec<&str> = local.entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(vec!["version", "name", "database", "libraries", "log_level"], keys);
+        assert_eq!(Some("1.0".to_string()), local.version);
+        assert_eq!(vec!["m".to_string()], local.libraries);
+        assert_eq!("trace", local.log_level);
+        assert_eq!(None, local.target_triple);
+        assert_eq!("info", resolve(&decls, "base", "config.dog").unwrap().log_level);
+    }
+
+    #[test]
+    fn reject_bad_extensions() {
+        assert_eq!("config debug extends base, which isn't declared at config.dog:1:15", error("config debug: base {\n}\n", "debug"));
+        assert_eq!("no config named release at config.dog:1:1", error("config debug: base {\n}\n", "release"));
+        assert_eq!("config b extends itself: b -> a -> c -> b at config.dog:5:11",
+                   error("config a: c {\n}\nconfig b: a {\n}\nconfig c: b {\n}\n", "b"));
+        assert_eq!("config b extends itself: b -> c -> b at config.dog:5:11",
+                   error("config a: b {\n}\nconfig b: c {\n}\nconfig c: b {\n}\n", "a"));
+        assert_eq!("config a extends itself: a -> a at config.dog:1:11", error("config a: a {\n}\n", "a"));
+    }
+
+    #[test]
+    fn validate_known_keys() {
+        assert_eq!("version must be numbers separated by periods, like 1.0.2, found '1.x' at config.dog:2:14",
+                   error("config a {\n    version: '1.x'\n}\n", "a"));
+        assert_eq!("version must be a string, found a float at config.dog:2:14", error("config a {\n    version: 1.5\n}\n", "a"));
+        assert_eq!("libraries must be a list, found a string at config.dog:2:16", error("config a {\n    libraries: 'm'\n}\n", "a"));
+        assert_eq!("a library must be a string, found an int at config.dog:2:17", error("config a {\n    libraries: [1]\n}\n", "a"));
+        assert_eq!("log_level must be one of none, error, warning, info, debug, trace, found 'loud' at config.dog:2:16",
+                   error("config a {\n    log_level: 'loud'\n}\n", "a"));
+        assert_eq!("target_triple must be like x86_64-unknown-linux-gnu, found 'x86_64' at config.dog:2:20",
+                   error("config a {\n    target_triple: 'x86_64'\n}\n", "a"));
+        assert_eq!("name is set more than once at config.dog:3:5", error("config a {\n    name: 'a'\n    name: 'b'\n}\n", "a"));
+
+        // a parent's mistake is found in the configs that extend it
+        assert_eq!("log_level must be a string, found a bool at config.dog:2:16",
+                   error("config a {\n    log_level: true\n}\nconfig b: a {\n}\n", "b"));
+    }
+}
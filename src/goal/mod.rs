@@ -3,7 +3,7 @@ extern crate clap;
 use std::env::Args;
 use std::fmt::Debug;
 
-use clap::{App, AppSettings, Arg, SubCommand};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -36,6 +36,36 @@ pub struct UserGoal {
     pub arg_project_type: ProjectType,
     pub arg_build_plan: Option<String>,
     pub arg_configuration: Option<String>,
+    pub arg_output: Option<String>,
+    // for test, the tests to run by part of their name, and where to write a json report
+    pub arg_test_filters: Vec<String>,
+    pub arg_test_json: Option<String>,
+    // --trace-parse, with the format and the rules to trace
+    pub arg_trace_parse: bool,
+    pub arg_trace_format: Option<String>,
+    pub arg_trace_rules: Vec<String>,
+}
+
+// for the commands that parse a file
+fn trace_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    return vec![
+        Arg::with_name("trace-parse")
+            .long("trace-parse")
+            .help("prints every rule the parser tries to stderr"),
+        Arg::with_name("trace-format")
+            .long("trace-format")
+            .takes_value(true)
+            .possible_values(&["text", "json"])
+            .requires("trace-parse")
+            .help("an indented trace, or a json object for each line"),
+        Arg::with_name("trace-rule")
+            .long("trace-rule")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .requires("trace-parse")
+            .help("only traces this rule and the rules it tries, can be given more than once"),
+    ];
 }
 
 pub fn parse(args: Args) -> Box<UserGoal> {
@@ -44,13 +74,23 @@ pub fn parse(args: Args) -> Box<UserGoal> {
         .about("A programming language for people who work with data.")
         .setting(AppSettings::SubcommandRequired)
         .subcommand(SubCommand::with_name("clean")
-            .about("cleans output folder"))
+            .about("cleans output folder")
+            .arg(Arg::with_name("build-plan")
+                .index(1)
+                .help("specifies the file that defines the build plan.")))
         .subcommand(SubCommand::with_name("compile")
             .about("compiles a single file")
-            .arg(Arg::with_name("file").required(true)))
+            .arg(Arg::with_name("file").required(true))
+            .arg(Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .help("the executable to write, or the folder to write them to when the file has more than one app fn"))
+            .args(&trace_args()))
         .subcommand(SubCommand::with_name("check")
             .about("syntax check on a single file")
-            .arg(Arg::with_name("file").required(true)))
+            .arg(Arg::with_name("file").required(true))
+            .args(&trace_args()))
         .subcommand(SubCommand::with_name("format")
             .about("format a single file")
             .arg(Arg::with_name("file").required(true)))
@@ -71,7 +111,7 @@ pub fn parse(args: Args) -> Box<UserGoal> {
                 .index(2)
                 .help("specifies while configuration to use within the build plan.")))
         .subcommand(SubCommand::with_name("release")
-            .about("cleans and builds a dog project")
+            .about("cleans and builds an optimized dog project without debug info")
             .arg(Arg::with_name("build-plan")
                 .index(1)
                 .help("specifies the file that defines the build plan."))
@@ -85,7 +125,18 @@ pub fn parse(args: Args) -> Box<UserGoal> {
                 .help("specifies the file that defines the build plan."))
             .arg(Arg::with_name("configuration")
                 .index(2)
-                .help("specifies while configuration to use within the build plan.")))
+                .help("specifies while configuration to use within the build plan."))
+            .arg(Arg::with_name("filter")
+                .short("f")
+                .long("filter")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .help("only runs the tests with this in their name, can be given more than once"))
+            .arg(Arg::with_name("json")
+                .long("json")
+                .takes_value(true)
+                .help("also writes the results to this file as json")))
         .subcommand(SubCommand::with_name("generate")
             .about("generates a dog project")
             .arg(Arg::with_name("project-name")
@@ -106,48 +157,63 @@ pub fn parse(args: Args) -> Box<UserGoal> {
         arg_project_type: ProjectType::App,
         arg_build_plan: None,
         arg_configuration: None,
+        arg_output: None,
+        arg_test_filters: vec![],
+        arg_test_json: None,
+        arg_trace_parse: false,
+        arg_trace_format: None,
+        arg_trace_rules: vec![],
     };
 
     if let Some(sub_args) = parsed_args.subcommand_matches("compile") {
         let compile_file = sub_args.value_of("file").unwrap_or_default();
         result.arg_command = Command::Compile;
         result.arg_file = Some(String::from(compile_file));
+        result.arg_output = sub_args.value_of("output").map(String::from);
+        read_trace_args(sub_args, &mut result);
         println!("Compiling {}", compile_file);
     } else if let Some(sub_args) = parsed_args.subcommand_matches("check") {
         let compile_file = sub_args.value_of("file").unwrap_or_default();
         result.arg_command = Command::Check;
         result.arg_file = Some(String::from(compile_file));
+        read_trace_args(sub_args, &mut result);
         println!("Checking {}", compile_file);
-    } else if let Some(_sub_args) = parsed_args.subcommand_matches("clean") {
+    } else if let Some(sub_args) = parsed_args.subcommand_matches("clean") {
         result.arg_command = Command::Clean;
+        result.arg_build_plan = sub_args.value_of("build-plan").map(String::from);
         println!("Cleaning");
     } else if let Some(sub_args) = parsed_args.subcommand_matches("build") {
         let build_plan = sub_args.value_of("build-plan").unwrap_or_default();
         let configuration = sub_args.value_of("configuration").unwrap_or_default();
         result.arg_command = Command::Build;
-        result.arg_build_plan = Some(String::from(build_plan));
-        result.arg_configuration = Some(String::from(configuration));
+        // the build plan defaults to config.dog and every configuration is built, see target::build
+        result.arg_build_plan = sub_args.value_of("build-plan").map(String::from);
+        result.arg_configuration = sub_args.value_of("configuration").map(String::from);
         println!("Building {} {}", build_plan, configuration);
     } else if let Some(sub_args) = parsed_args.subcommand_matches("rebuild") {
         let build_plan = sub_args.value_of("build-plan").unwrap_or_default();
         let configuration = sub_args.value_of("configuration").unwrap_or_default();
         result.arg_command = Command::Rebuild;
-        result.arg_build_plan = Some(String::from(build_plan));
-        result.arg_configuration = Some(String::from(configuration));
+        result.arg_build_plan = sub_args.value_of("build-plan").map(String::from);
+        result.arg_configuration = sub_args.value_of("configuration").map(String::from);
         println!("Rebuilding {} {}", build_plan, configuration);
     } else if let Some(sub_args) = parsed_args.subcommand_matches("test") {
         let build_plan = sub_args.value_of("build-plan").unwrap_or_default();
         let configuration = sub_args.value_of("configuration").unwrap_or_default();
         result.arg_command = Command::Test;
-        result.arg_build_plan = Some(String::from(build_plan));
-        result.arg_configuration = Some(String::from(configuration));
+        result.arg_build_plan = sub_args.value_of("build-plan").map(String::from);
+        result.arg_configuration = sub_args.value_of("configuration").map(String::from);
+        if let Some(filters) = sub_args.values_of("filter") {
+            result.arg_test_filters = filters.map(String::from).collect();
+        }
+        result.arg_test_json = sub_args.value_of("json").map(String::from);
         println!("Testing {} {}", build_plan, configuration);
     } else if let Some(sub_args) = parsed_args.subcommand_matches("release") {
         let build_plan = sub_args.value_of("build-plan").unwrap_or_default();
         let configuration = sub_args.value_of("configuration").unwrap_or_default();
         result.arg_command = Command::Release;
-        result.arg_build_plan = Some(String::from(build_plan));
-        result.arg_configuration = Some(String::from(configuration));
+        result.arg_build_plan = sub_args.value_of("build-plan").map(String::from);
+        result.arg_configuration = sub_args.value_of("configuration").map(String::from);
         println!("Releasing {} {}", build_plan, configuration);
     } else if let Some(sub_args) = parsed_args.subcommand_matches("generate") {
         let project_name = sub_args.value_of("project-name").unwrap_or_default();
@@ -166,4 +232,12 @@ pub fn parse(args: Args) -> Box<UserGoal> {
     }
 
     return Box::new(result);
-}
\ No newline at end of file
+}
+
+fn read_trace_args(sub_args: &ArgMatches, result: &mut UserGoal) {
+    result.arg_trace_parse = sub_args.is_present("trace-parse");
+    result.arg_trace_format = sub_args.value_of("trace-format").map(String::from);
+    if let Some(rules) = sub_args.values_of("trace-rule") {
+        result.arg_trace_rules = rules.map(String::from).collect();
+    }
+}
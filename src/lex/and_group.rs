@@ -1,5 +1,6 @@
 use core::option::Option;
 
+use crate::lex::dfa::{CharRanges, Nfa};
 use crate::lex::group::Group;
 
 pub struct AndGroup {
@@ -116,4 +117,37 @@ impl Group for AndGroup {
         result += ")";
         return result;
     }
+
+    fn build_nfa(&self, nfa: &mut Nfa) -> Option<(usize, usize)> {
+        if self.groups.is_empty() {
+            return None;
+        }
+        let start = nfa.add_state();
+        let mut end = start;
+        let last_group = self.groups.len() - 1;
+        for (index, group) in self.groups.iter().enumerate() {
+            let first_state = nfa.size();
+            let (group_start, group_end) = group.build_nfa(nfa)?;
+            if group.contains_any() && index < last_group {
+                // `match_with` stops a match-any group at the first place the next group matches.
+                // We can only express that when the next group is a single character: the
+                // match-any group isn't allowed to consume that character.
+                let next_group = &self.groups[index + 1];
+                if next_group.contains_any() {
+                    return None;
+                }
+                nfa.exclude_from(first_state, &next_group.single_char_ranges()?);
+            }
+            nfa.add_epsilon(end, group_start);
+            end = group_end;
+        }
+        return Some((start, end));
+    }
+
+    fn single_char_ranges(&self) -> Option<CharRanges> {
+        if self.groups.len() == 1 {
+            return self.groups[0].single_char_ranges();
+        }
+        return None;
+    }
 }
\ No newline at end of file
@@ -1,3 +1,4 @@
+use crate::lex::dfa::{CharRanges, MAX_CHAR, Nfa};
 use crate::lex::group::Group;
 
 #[derive(Debug)]
@@ -25,4 +26,15 @@ impl Group for AnyChar {
     fn render(&self) -> String {
         return ".".to_string();
     }
+
+    fn build_nfa(&self, nfa: &mut Nfa) -> Option<(usize, usize)> {
+        let start = nfa.add_state();
+        let end = nfa.add_state();
+        nfa.add_transition(start, 0, MAX_CHAR, end);
+        return Some((start, end));
+    }
+
+    fn single_char_ranges(&self) -> Option<CharRanges> {
+        return Some(vec![(0, MAX_CHAR)]);
+    }
 }
@@ -0,0 +1,184 @@
+// Matches one character from a unicode class, like the characters that may start an identifier
+// or every uppercase letter. The classes are too large to list as character ranges by hand, so
+// the ranges are worked out once from the unicode tables when the group is created.
+
+use unicode_general_category::{get_general_category, GeneralCategory};
+use unicode_normalization::UnicodeNormalization;
+use unicode_xid::UnicodeXID;
+
+use crate::lex::dfa::{CharRanges, Nfa};
+use crate::lex::group::Group;
+
+// The same name can be written with different characters: é is either one character or an e
+// followed by a combining accent. Identifiers are compared in their composed (NFC) form, so both
+// spellings name the same thing.
+pub fn normalize_identifier(name: &str) -> String {
+    return name.nfc().collect();
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CharacterClass {
+    // characters that can start an identifier
+    XidStart,
+    // characters that can follow the first character of an identifier
+    XidContinue,
+    // characters in any of the general categories
+    #[allow(dead_code)]
+    GeneralCategory(Vec<GeneralCategory>),
+}
+
+impl CharacterClass {
+    pub fn contains(&self, c: char) -> bool {
+        return match self {
+            CharacterClass::XidStart => c.is_xid_start(),
+            CharacterClass::XidContinue => c.is_xid_continue(),
+            CharacterClass::GeneralCategory(categories) => categories.contains(&get_general_category(c)),
+        };
+    }
+
+    #[allow(dead_code)]
+    pub fn render(&self) -> String {
+        return match self {
+            CharacterClass::XidStart => "\\p{XID_Start}".to_string(),
+            CharacterClass::XidContinue => "\\p{XID_Continue}".to_string(),
+            CharacterClass::GeneralCategory(categories) => {
+                let names: Vec<&str> = categories.iter().map(|category| category.abbreviation()).collect();
+                format!("\\p{{{}}}", names.join("|"))
+            }
+        };
+    }
+
+    fn ranges(&self) -> CharRanges {
+        let mut result: CharRanges = vec![];
+        for c in (0..=char::MAX as u32).filter_map(char::from_u32) {
+            if !self.contains(c) {
+                continue;
+            }
+            let code = c as u32;
+            match result.last_mut() {
+                // the surrogates aren't characters, so ranges continue across them
+                Some(last) if last.1 + 1 == code || (last.1 == 0xD7FF && code == 0xE000) => last.1 = code,
+                _ => result.push((code, code)),
+            }
+        }
+        return result;
+    }
+}
+
+#[derive(Debug)]
+pub struct CharacterClassGroup {
+    pub class: CharacterClass,
+    ranges: CharRanges,
+}
+
+impl CharacterClassGroup {
+    pub fn new(class: CharacterClass) -> CharacterClassGroup {
+        let ranges = class.ranges();
+        return CharacterClassGroup {
+            class,
+            ranges,
+        };
+    }
+}
+
+impl Group for CharacterClassGroup {
+    fn match_with(&self, text: &[char], offset: usize) -> Option<u32> {
+        if offset < text.len() && self.class.contains(text[offset]) {
+            return Some(1);
+        }
+        return None;
+    }
+
+    fn contains_any(&self) -> bool {
+        return false;
+    }
+
+    fn name(&self) -> String {
+        return "CharacterClassGroup".to_string();
+    }
+
+    fn min_matches(&self) -> usize {
+        return 1;
+    }
+
+    fn render(&self) -> String {
+        return self.class.render();
+    }
+
+    fn build_nfa(&self, nfa: &mut Nfa) -> Option<(usize, usize)> {
+        let start = nfa.add_state();
+        let end = nfa.add_state();
+        for (range_start, range_end) in &self.ranges {
+            nfa.add_transition(start, *range_start, *range_end, end);
+        }
+        return Some((start, end));
+    }
+
+    fn single_char_ranges(&self) -> Option<CharRanges> {
+        return Some(self.ranges.clone());
+    }
+}
+
+#[cfg(test)]
+mod character_class_tests {
+    use unicode_general_category::GeneralCategory;
+
+    use crate::lex::character_class::{CharacterClass, CharacterClassGroup, normalize_identifier};
+    use crate::lex::group::Group;
+
+    fn matches(group: &CharacterClassGroup, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        return group.match_with(&chars, 0) == Some(1);
+    }
+
+    #[test]
+    fn match_identifier_classes() {
+        let start = CharacterClassGroup::new(CharacterClass::XidStart);
+        let body = CharacterClassGroup::new(CharacterClass::XidContinue);
+        for c in ["a", "Z", "ö", "名", "ß", "λ"] {
+            assert!(matches(&start, c), "{} should start an identifier", c);
+        }
+        for c in ["1", "_", "$", " ", "€", "\u{301}"] {
+            assert!(!matches(&start, c), "{} shouldn't start an identifier", c);
+        }
+        for c in ["1", "_", "\u{301}", "前"] {
+            assert!(matches(&body, c), "{} should continue an identifier", c);
+        }
+        assert!(!matches(&body, "-"));
+        assert!(!matches(&body, ""));
+        assert_eq!("\\p{XID_Start}", start.render());
+    }
+
+    #[test]
+    fn match_general_categories() {
+        let group = CharacterClassGroup::new(CharacterClass::GeneralCategory(vec![
+            GeneralCategory::UppercaseLetter,
+            GeneralCategory::CurrencySymbol,
+        ]));
+        assert!(matches(&group, "A"));
+        assert!(matches(&group, "Ö"));
+        assert!(matches(&group, "€"));
+        assert!(!matches(&group, "a"));
+        assert_eq!("\\p{Lu|Sc}", group.render());
+    }
+
+    #[test]
+    fn ranges_agree_with_contains() {
+        let class = CharacterClass::XidContinue;
+        let ranges = class.ranges();
+        for window in ranges.windows(2) {
+            assert!(window[0].1 + 1 < window[1].0, "ranges {:?} should have been merged", window);
+        }
+        for c in (0..=0x3000).filter_map(char::from_u32) {
+            let in_ranges = ranges.iter().any(|(start, end)| *start <= c as u32 && c as u32 <= *end);
+            assert_eq!(class.contains(c), in_ranges, "{:?}", c);
+        }
+    }
+
+    #[test]
+    fn normalize_identifiers() {
+        assert_eq!("caf\u{e9}", normalize_identifier("cafe\u{301}"));
+        assert_eq!(normalize_identifier("gr\u{f6}\u{df}e"), normalize_identifier("gro\u{308}\u{df}e"));
+        assert_eq!("名前", normalize_identifier("名前"));
+    }
+}
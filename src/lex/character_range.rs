@@ -1,3 +1,4 @@
+use crate::lex::dfa::{CharRanges, Nfa};
 use crate::lex::group::Group;
 use crate::lex::render_char;
 
@@ -9,6 +10,9 @@ pub struct CharacterRange {
 
 impl Group for CharacterRange {
     fn match_with(&self, text: &[char], offset: usize) -> Option<u32> {
+        if offset >= text.len() {
+            return None;
+        }
         let target_char = text[offset];
         if target_char >= self.match_start_char && target_char <= self.match_end_char {
             return Some(1);
@@ -40,5 +44,16 @@ impl Group for CharacterRange {
         result += "]";
         return result;
     }
+
+    fn build_nfa(&self, nfa: &mut Nfa) -> Option<(usize, usize)> {
+        let start = nfa.add_state();
+        let end = nfa.add_state();
+        nfa.add_transition(start, self.match_start_char as u32, self.match_end_char as u32, end);
+        return Some((start, end));
+    }
+
+    fn single_char_ranges(&self) -> Option<CharRanges> {
+        return Some(vec![(self.match_start_char as u32, self.match_end_char as u32)]);
+    }
 }
 
@@ -0,0 +1,417 @@
+// Trying every pattern at every offset is simple, but it is slow on large files. Instead, we
+// compile the pattern trees once into a single deterministic automaton and walk it once per
+// token. The pattern trees stay around as the reference implementation (see `find_longest_match`),
+// and they are still used directly for any pattern that can't be expressed as an automaton.
+//
+// The compile happens in two steps:
+//   1. every pattern is turned into a small nondeterministic automaton (a thompson construction)
+//      that all hang off of one shared start state.
+//   2. the combined nfa is turned into a dfa with the subset construction.
+//
+// Characters are handled as ranges of u32 code points so that things like `AnyChar` don't
+// explode into a million transitions.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::OnceLock;
+
+use crate::lex::lexer_mode::LexerMode;
+use crate::lex::pattern::Pattern;
+
+pub const MAX_CHAR: u32 = char::MAX as u32;
+
+pub type CharRanges = Vec<(u32, u32)>;
+
+struct NfaState {
+    epsilon: Vec<usize>,
+    transitions: Vec<(u32, u32, usize)>,
+}
+
+pub struct Nfa {
+    states: Vec<NfaState>,
+}
+
+impl Nfa {
+    pub fn new() -> Nfa {
+        return Nfa {
+            states: vec![],
+        };
+    }
+
+    pub fn add_state(&mut self) -> usize {
+        self.states.push(NfaState {
+            epsilon: vec![],
+            transitions: vec![],
+        });
+        return self.states.len() - 1;
+    }
+
+    pub fn size(&self) -> usize {
+        return self.states.len();
+    }
+
+    pub fn add_epsilon(&mut self, from: usize, to: usize) {
+        self.states[from].epsilon.push(to);
+    }
+
+    pub fn add_transition(&mut self, from: usize, start: u32, end: u32, to: usize) {
+        self.states[from].transitions.push((start, end, to));
+    }
+
+    // Removes the excluded characters from every transition of the states starting at
+    // `first_state`. This is how a lazy "match anything until X" gets expressed: the anything
+    // part is simply not allowed to consume the first character of X.
+    pub fn exclude_from(&mut self, first_state: usize, excluded: &CharRanges) {
+        for state in &mut self.states[first_state..] {
+            let mut transitions = vec![];
+            for (start, end, to) in &state.transitions {
+                for (remaining_start, remaining_end) in subtract_ranges(*start, *end, excluded) {
+                    transitions.push((remaining_start, remaining_end, *to));
+                }
+            }
+            state.transitions = transitions;
+        }
+    }
+
+    fn epsilon_closure(&self, states: &mut BTreeSet<usize>) {
+        let mut pending: Vec<usize> = states.iter().cloned().collect();
+        while let Some(next) = pending.pop() {
+            for target in &self.states[next].epsilon {
+                if states.insert(*target) {
+                    pending.push(*target);
+                }
+            }
+        }
+    }
+}
+
+// returns the parts of [start, end] that are not covered by any of the excluded ranges
+fn subtract_ranges(start: u32, end: u32, excluded: &CharRanges) -> CharRanges {
+    let mut result = vec![(start, end)];
+    for (excluded_start, excluded_end) in excluded {
+        let mut next_result = vec![];
+        for (range_start, range_end) in result {
+            if *excluded_end < range_start || *excluded_start > range_end {
+                next_result.push((range_start, range_end));
+                continue;
+            }
+            if *excluded_start > range_start {
+                next_result.push((range_start, excluded_start - 1));
+            }
+            if *excluded_end < range_end {
+                next_result.push((excluded_end + 1, range_end));
+            }
+        }
+        result = next_result;
+    }
+    return result;
+}
+
+pub fn complement_ranges(ranges: &CharRanges) -> CharRanges {
+    return subtract_ranges(0, MAX_CHAR, ranges);
+}
+
+struct DfaState {
+    // sorted, non-overlapping ranges
+    transitions: Vec<(u32, u32, usize)>,
+    // the index of the highest precedence pattern that accepts in this state
+    accept: Option<usize>,
+}
+
+pub struct Dfa {
+    states: Vec<DfaState>,
+    // patterns that can't be compiled into the automaton, by index into the pattern list.
+    // these are matched with the reference matcher.
+    fallback_patterns: Vec<usize>,
+}
+
+impl Dfa {
+    pub fn compile(patterns: &[Pattern]) -> Dfa {
+        let mut nfa = Nfa::new();
+        let start = nfa.add_state();
+        let mut accepting_states: HashMap<usize, usize> = HashMap::new();
+        let mut fallback_patterns = vec![];
+        for (index, pattern) in patterns.iter().enumerate() {
+            let first_state = nfa.size();
+            if let Some((pattern_start, pattern_end)) = pattern.pattern_group.build_nfa(&mut nfa) {
+                nfa.add_epsilon(start, pattern_start);
+                accepting_states.insert(pattern_end, index);
+            } else {
+                // whatever was partially built is unreachable, so it is harmless to leave it.
+                nfa.states.truncate(first_state);
+                fallback_patterns.push(index);
+            }
+        }
+
+        // every boundary where the set of possible transitions could change
+        let mut boundaries: BTreeSet<u32> = BTreeSet::new();
+        for state in &nfa.states {
+            for (range_start, range_end, _) in &state.transitions {
+                boundaries.insert(*range_start);
+                boundaries.insert(*range_end + 1);
+            }
+        }
+        let boundaries: Vec<u32> = boundaries.into_iter().collect();
+
+        let mut start_set = BTreeSet::new();
+        start_set.insert(start);
+        nfa.epsilon_closure(&mut start_set);
+
+        let mut dfa_states: Vec<DfaState> = vec![];
+        let mut known_sets: HashMap<BTreeSet<usize>, usize> = HashMap::new();
+        let mut pending: Vec<BTreeSet<usize>> = vec![];
+        known_sets.insert(start_set.clone(), 0);
+        dfa_states.push(DfaState {
+            transitions: vec![],
+            accept: find_accept(&start_set, &accepting_states),
+        });
+        pending.push(start_set);
+
+        while let Some(current_set) = pending.pop() {
+            let current_index = known_sets[&current_set];
+            // targets by interval index. interval i is [boundaries[i], boundaries[i + 1] - 1]
+            let mut moves: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); boundaries.len()];
+            for nfa_state in &current_set {
+                for (range_start, range_end, target) in &nfa.states[*nfa_state].transitions {
+                    let mut interval = boundaries.binary_search(range_start).unwrap();
+                    while interval + 1 < boundaries.len() && boundaries[interval + 1] <= range_end + 1 {
+                        moves[interval].insert(*target);
+                        interval += 1;
+                    }
+                }
+            }
+
+            let mut transitions: Vec<(u32, u32, usize)> = vec![];
+            // character classes split the characters into thousands of intervals, and neighbouring
+            // intervals usually move to the same states, so the target of the last one is reused.
+            let mut previous_move: Option<(BTreeSet<usize>, usize)> = None;
+            for (interval, move_set) in moves.into_iter().enumerate() {
+                if move_set.is_empty() {
+                    continue;
+                }
+                let target_index = match &previous_move {
+                    Some((previous_set, previous_index)) if *previous_set == move_set => *previous_index,
+                    _ => {
+                        let mut target_set = move_set.clone();
+                        nfa.epsilon_closure(&mut target_set);
+                        let target_index = if let Some(existing) = known_sets.get(&target_set) {
+                            *existing
+                        } else {
+                            let new_index = dfa_states.len();
+                            dfa_states.push(DfaState {
+                                transitions: vec![],
+                                accept: find_accept(&target_set, &accepting_states),
+                            });
+                            known_sets.insert(target_set.clone(), new_index);
+                            pending.push(target_set);
+                            new_index
+                        };
+                        previous_move = Some((move_set, target_index));
+                        target_index
+                    }
+                };
+                let range_start = boundaries[interval];
+                let range_end = boundaries[interval + 1] - 1;
+                if let Some(last) = transitions.last_mut() {
+                    if last.2 == target_index && last.1 + 1 == range_start {
+                        last.1 = range_end;
+                        continue;
+                    }
+                }
+                transitions.push((range_start, range_end, target_index));
+            }
+            dfa_states[current_index].transitions = transitions;
+        }
+
+        return Dfa {
+            states: dfa_states,
+            fallback_patterns,
+        };
+    }
+
+    // Finds the longest match starting at offset. When more than one pattern matches the same
+    // length, the pattern that was defined first wins.
+    // Returns the index of the pattern and the length of the match.
+    pub fn longest_match(&self, patterns: &[Pattern], text: &[char], offset: usize) -> Option<(usize, usize)> {
+        let mut result: Option<(usize, usize)> = None;
+        let mut state = 0;
+        let mut length = 0;
+        while offset + length < text.len() {
+            let c = text[offset + length] as u32;
+            let transitions = &self.states[state].transitions;
+            let found = transitions.binary_search_by(|(range_start, range_end, _)| {
+                if *range_end < c {
+                    std::cmp::Ordering::Less
+                } else if *range_start > c {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            });
+            if let Ok(transition_index) = found {
+                state = transitions[transition_index].2;
+                length += 1;
+                if let Some(pattern_index) = self.states[state].accept {
+                    result = Some((pattern_index, length));
+                }
+            } else {
+                break;
+            }
+        }
+
+        for pattern_index in &self.fallback_patterns {
+            if let Some(fallback_length) = patterns[*pattern_index].pattern_group.match_with(text, offset) {
+                let fallback_length = fallback_length as usize;
+                if fallback_length == 0 {
+                    continue;
+                }
+                if let Some((best_index, best_length)) = result {
+                    if fallback_length < best_length || (fallback_length == best_length && best_index < *pattern_index) {
+                        continue;
+                    }
+                }
+                result = Some((*pattern_index, fallback_length));
+            }
+        }
+        return result;
+    }
+
+    #[allow(dead_code)]
+    pub fn size(&self) -> usize {
+        return self.states.len();
+    }
+}
+
+fn find_accept(states: &BTreeSet<usize>, accepting_states: &HashMap<usize, usize>) -> Option<usize> {
+    return states.iter()
+        .filter_map(|state| accepting_states.get(state))
+        .min()
+        .cloned();
+}
+
+static DOG_DFA: OnceLock<Dfa> = OnceLock::new();
+static SQL_DFA: OnceLock<Dfa> = OnceLock::new();
+
+pub fn get_dfa(mode: LexerMode) -> &'static Dfa {
+    let dfa = match mode {
+        LexerMode::Dog => &DOG_DFA,
+        LexerMode::Sql => &SQL_DFA,
+    };
+    return dfa.get_or_init(|| Dfa::compile(mode.patterns()));
+}
+
+#[cfg(test)]
+mod dfa_tests {
+    use crate::lex::{lex, reference_lex, test_random};
+    use crate::source::SourceMap;
+    use crate::lex::dfa::get_dfa;
+    use crate::lex::lexer_mode::LexerMode;
+    use crate::lex::pattern_init::get_patterns;
+
+    const SAMPLE: &str = "mod my_mod\n\
+        use other::thing as alias\n\
+        // simple function that adds two numbers and returns the result\n\
+        pub struct MyStruct {\n\
+            pub prop1: mut int\n\
+            pub prop2: [i64]\n\
+        }\n\
+        #attr_tag{name: \"value\", other: 'x'}\n\
+        app fn main(args: [string]) {\n\
+            let apple: f32 = 1.5 + 0x1F * 42 / (app - lib) - 2.5e-3f32 * 0b1010_0101u8\n\
+            let query = `select * from table where a = :b`\n\
+            if apple >= 10 { log(\"big \\\"number\\\"\") } otherwise fail(r\"C:\\nope\")\n\
+            log(\"{apple} is {if apple > 2 { 'big' } otherwise { \"small\" }}\")\n\
+            for x in [1..=10] { continue }\n\
+            while true { break }\n\
+            return x_1::y.z?|!\n\
+        }\n";
+
+    fn assert_same(code: &str) {
+        let mut source_map = SourceMap::new();
+        let file_id = source_map.add_file(Some("test.dog"), None, code.to_string());
+        let expected = reference_lex(&source_map, file_id);
+        let actual = lex(&source_map, file_id);
+        assert_eq!(expected, actual, "lexing differed for: {:?}", code);
+    }
+
+    #[test]
+    fn only_recursive_patterns_fall_back() {
+        let dfa = get_dfa(LexerMode::Dog);
+        let patterns = get_patterns();
+        let fallback: Vec<String> = dfa.fallback_patterns.iter()
+            .map(|index| patterns[*index].pattern_group.name())
+            .collect();
+        assert_eq!(vec!["NestedGroup", "StringLiteralGroup"], fallback);
+        assert!(dfa.size() > patterns.len());
+        assert!(get_dfa(LexerMode::Sql).fallback_patterns.is_empty());
+    }
+
+    #[test]
+    fn matches_reference_on_sample() {
+        let mut source_map = SourceMap::new();
+        let file_id = source_map.add_file(None, None, SAMPLE.to_string());
+        let expected = reference_lex(&source_map, file_id);
+        assert!(expected.is_ok());
+        assert_eq!(expected, lex(&source_map, file_id));
+    }
+
+    #[test]
+    fn matches_reference_on_keywords_and_symbols() {
+        let mut code = String::new();
+        for pattern in get_patterns() {
+            // every keyword, alone and as the prefix of an identifier
+            let rendered = pattern.pattern_group.render();
+            if rendered.starts_with('\'') && rendered.ends_with('\'') && rendered.len() > 2 {
+                let text = &rendered[1..rendered.len() - 1];
+                code += text;
+                code += " ";
+                code += text;
+                code += "x1\n";
+            }
+        }
+        assert_same(&code);
+    }
+
+    #[test]
+    fn matches_reference_on_edge_cases() {
+        assert_same("");
+        assert_same("app");
+        assert_same("apple");
+        assert_same("0x");
+        assert_same("1.");
+        assert_same("1.5.6");
+        assert_same("0x_ff_u8 0b1010 0o17 1_000 1.5e-3f32 2E+10 10u8 1.5f32 1f64 1i128 0b102 1e 1ef32 0x1f32");
+        assert_same("// comment without newline");
+        assert_same("/// doc\n//! inner doc\n//// not a doc\n///");
+        assert_same("/* outer /* inner */ still outer */ x");
+        assert_same("/* never closed");
+        assert_same("\"unterminated string");
+        assert_same("'single' \"double\" ''");
+        assert_same("\"\"\"triple \"quoted\"\"\"\" r'raw\\' r\"\"\"\"\"\"");
+        assert_same("\"a {b} c {\"d {e}\"}\" '{}' \"\\q\"");
+        assert_same("``");
+        assert_same("`unterminated sql");
+        assert_same("`select \"a\", 'b''c', 1.5 FROM t where x <> :y -- comment` `'''` `\"` `$`");
+        assert_same("\t\r\n  \n");
+        assert_same("a;b");
+        assert_same("größe");
+    }
+
+    #[test]
+    fn matches_reference_on_generated_code() {
+        let fragments = [
+            "app", "fn", "let", "x", "abc_1", "Z", "0", "42", "0x", "ff", "1.5", ".", "..", "::",
+            "\"", "'", "\\", "`", "//", "\n", " ", "\t", "\r", "{", "}", "(", ")", "[", "]", "=", "!",
+            "+", "-", "*", "/", "#", "?", ":", "|", "<", ">", ",", "u8", "i64", "as", "mod", "é",
+            "/*", "*/", "///", "//!", "0b", "0o", "e", "E", "_", "f32", "1e", "i16",
+        ];
+        let mut next = test_random(12345);
+        for _ in 0..500 {
+            let mut code = String::new();
+            let length = next(40);
+            for _ in 0..length {
+                code += fragments[next(fragments.len())];
+            }
+            assert_same(&code);
+        }
+    }
+}
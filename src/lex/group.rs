@@ -1,7 +1,19 @@
-pub trait Group {
+use crate::lex::dfa::{CharRanges, Nfa};
+
+// patterns are built once and shared by every lexer, see sql_pattern_init.rs
+pub trait Group: Send + Sync {
     fn match_with(&self, text: &[char], offset: usize) -> Option<u32>;
     fn contains_any(&self) -> bool;
     fn name(&self) -> String;
     fn min_matches(&self) -> usize;
     fn render(&self) -> String;
+    // adds this group to the nfa and returns the start and end states of what was added.
+    // returns None if the group can't be expressed as an automaton.
+    fn build_nfa(&self, nfa: &mut Nfa) -> Option<(usize, usize)>;
+    // if this group always matches exactly one character, the characters it matches.
+    fn single_char_ranges(&self) -> Option<CharRanges>;
+    // if this group only ever matches one piece of text, that text.
+    fn fixed_text(&self) -> Option<String> {
+        return None;
+    }
 }
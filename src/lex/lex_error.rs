@@ -0,0 +1,27 @@
+use crate::lex::render_string;
+use crate::source::{FileId, SourceMap};
+
+// Describes text that none of the patterns could match, or a token whose contents are invalid,
+// like a string literal with an unknown escape sequence. The file's name is looked up in the
+// source map when the error is rendered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub message: String,
+    pub file_id: FileId,
+    // byte offsets into the source of the text that is wrong
+    pub start: usize,
+    pub end: usize,
+    // both start at 1
+    pub line_number: usize,
+    pub line_offset: usize,
+    // the text around the failure, up to the next whitespace
+    pub snippet: String,
+}
+
+impl LexError {
+    // message at file_name:line:offset: [snippet]
+    pub fn render(&self, source_map: &SourceMap) -> String {
+        return format!("{} at {}:{}:{}: [{}]",
+                       self.message, source_map.get(self.file_id).name, self.line_number, self.line_offset, render_string(self.snippet.clone()));
+    }
+}
@@ -0,0 +1,50 @@
+// The lexer understands more than one language. Files are lexed as dog, but some dog tokens hold
+// text in another language, like the sql between backticks. The body of such a token is lexed
+// again in that language's mode, with its own patterns and automaton.
+
+use crate::lex::dfa::{Dfa, get_dfa};
+use crate::lex::pattern::Pattern;
+use crate::lex::pattern_init::get_patterns;
+use crate::lex::sql_pattern_init::{get_sql_patterns, SQL, SQL_END, SQL_START};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LexerMode {
+    Dog,
+    Sql,
+}
+
+// How a token that holds another language is split up: its opening delimiter becomes a token
+// labeled `start_label`, the body is lexed in `mode`, and the closing delimiter becomes a token
+// labeled `end_label`.
+pub struct EmbeddedLanguage {
+    pub mode: LexerMode,
+    pub start_label: &'static str,
+    pub end_label: &'static str,
+    pub delimiter_length: usize,
+}
+
+impl LexerMode {
+    pub fn patterns(&self) -> &'static Vec<Pattern> {
+        return match self {
+            LexerMode::Dog => get_patterns(),
+            LexerMode::Sql => get_sql_patterns(),
+        };
+    }
+
+    pub fn dfa(&self) -> &'static Dfa {
+        return get_dfa(*self);
+    }
+
+    // the language held by tokens with this label, if they hold one
+    pub fn embedded_language(&self, label: &str) -> Option<EmbeddedLanguage> {
+        if *self == LexerMode::Dog && label == SQL {
+            return Some(EmbeddedLanguage {
+                mode: LexerMode::Sql,
+                start_label: SQL_START,
+                end_label: SQL_END,
+                delimiter_length: 1,
+            });
+        }
+        return None;
+    }
+}
@@ -1,3 +1,4 @@
+use crate::lex::dfa::{CharRanges, Nfa};
 use crate::lex::group::Group;
 
 #[derive(PartialEq, Eq, Debug)]
@@ -91,4 +92,29 @@ impl Group for GroupRepeats {
         }
         return result;
     }
+
+    fn build_nfa(&self, nfa: &mut Nfa) -> Option<(usize, usize)> {
+        let (group_start, group_end) = self.group.build_nfa(nfa)?;
+        if self.match_repeats == MatchRepeats::Once {
+            return Some((group_start, group_end));
+        }
+        let start = nfa.add_state();
+        let end = nfa.add_state();
+        nfa.add_epsilon(start, group_start);
+        nfa.add_epsilon(group_end, end);
+        if self.match_repeats == MatchRepeats::ZeroOrMore || self.match_repeats == MatchRepeats::OneOrMore {
+            nfa.add_epsilon(group_end, group_start);
+        }
+        if self.match_repeats == MatchRepeats::ZeroOrMore || self.match_repeats == MatchRepeats::ZeroOrOne {
+            nfa.add_epsilon(start, end);
+        }
+        return Some((start, end));
+    }
+
+    fn single_char_ranges(&self) -> Option<CharRanges> {
+        if self.match_repeats == MatchRepeats::Once {
+            return self.group.single_char_ranges();
+        }
+        return None;
+    }
 }
\ No newline at end of file
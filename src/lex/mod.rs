@@ -8,95 +8,330 @@
 
 use std::cmp::min;
 use std::iter::FromIterator;
+use std::ops::Range;
 
 use pattern::PatternMatcher;
 use token_stream::TokenStream;
 
-use crate::lex::pattern_init::get_patterns;
+use crate::lex::character_class::normalize_identifier;
+use crate::lex::lex_error::LexError;
+use crate::lex::lexer_mode::LexerMode;
+use crate::lex::number_literal::{NUMBER_LITERAL, parse_number_literal};
+use crate::lex::pattern_init::WORD;
+use crate::lex::string_literal::{decode_string_token, INTERPOLATED_STRING_END, INTERPOLATED_STRING_MIDDLE, INTERPOLATED_STRING_START, scan_string_literal, STRING_LITERAL};
 use crate::lex::token_stream::Match;
-use crate::UNKNOWN;
+use crate::source::{FileId, SourceMap};
+
+const MAX_SNIPPET_LENGTH: usize = 20;
 
 pub(crate) mod token_stream;
 mod pattern;
 mod or_group;
 mod text_pattern;
 mod match_repeats;
-mod pattern_init;
+pub(crate) mod pattern_init;
 mod and_group;
 mod not_group;
 mod character_range;
+pub(crate) mod character_class;
 mod any_char;
 mod group;
+mod dfa;
+mod nested_group;
+mod lexer_mode;
+mod sql_pattern_init;
+pub(crate) mod relex;
+pub(crate) mod number_literal;
+pub(crate) mod string_literal;
+pub(crate) mod lex_error;
+
+#[allow(dead_code)]
+pub fn lex(source_map: &SourceMap, file_id: FileId) -> Result<Box<TokenStream>, LexError> {
+    let (token_stream, mut errors) = lex_with(source_map, file_id, find_longest_match_with_dfa, false, false);
+    if errors.is_empty() {
+        return Ok(token_stream);
+    }
+    return Err(errors.remove(0));
+}
 
-// todo: right now, this is only using the dog grammar, but we need to add a function or enum
-// that supports the SQL grammar.
-pub fn lex(dog_code: &str, file_name: Option<&str>, file_path: Option<&str>) -> Option<Box<TokenStream>> {
-    let real_file_name = String::from(file_name.unwrap_or(UNKNOWN));
-    let real_file_path = String::from(file_path.unwrap_or(UNKNOWN));
-    println!("Lexing: {} ({})", real_file_name, real_file_path);
+// Keeps the whitespace, line ends and comments in the token stream, marked as skipped, so that the
+// parser can attach them to the tokens around them and the file can be written back out exactly
+// as it was. See parse_lossless.
+#[allow(dead_code)]
+pub fn lex_lossless(source_map: &SourceMap, file_id: FileId) -> Result<Box<TokenStream>, LexError> {
+    let (token_stream, mut errors) = lex_with(source_map, file_id, find_longest_match_with_dfa, false, true);
+    if errors.is_empty() {
+        return Ok(token_stream);
+    }
+    return Err(errors.remove(0));
+}
 
-    let text: Vec<char> = dog_code.chars().collect();
+// Rather than stopping at the first character that can't be matched, this skips it, records the
+// error, and keeps going, so that a single run can report every problem in the file.
+pub fn lex_with_recovery(source_map: &SourceMap, file_id: FileId) -> (Box<TokenStream>, Vec<LexError>) {
+    return lex_with(source_map, file_id, find_longest_match_with_dfa, true, false);
+}
 
-    let mut line_number: usize = 1;
-    let mut line_offset: usize = 1;
-    let mut matches = vec![];
-    let mut offset = 0;
-    while offset < text.len() {
-        //println!("{} {}", offset, text[offset]);
-        //println!("Text is: [{}]", render_string(String::from_iter(text[offset..min(text.len(), offset + 5)].iter())));
-        if let Some(mut longest_match) = find_longest_match(&text, offset) {
-            longest_match.line_number = line_number;
-            longest_match.line_offset = line_offset;
-            longest_match.file_path = Some(real_file_path.clone());
-            longest_match.file_name = Some(real_file_name.clone());
-            //println!("Found pattern: {:?}", &longest_match);
-            //println!();
-
-            if longest_match.value.contains("\n") {
-                line_number += 1;
-                line_offset = 1;
-            } else {
-                line_offset += longest_match.length;
-            }
-            offset += longest_match.length;
-            if !longest_match.skip {
-                matches.push(longest_match);
-            }
-        } else {
-            // I'm about 99% sure there must be a more elegant way of doing this.
-            println!("Text is: [{}]", render_string(String::from_iter(text[offset..min(text.len(), offset + 5)].iter())));
-            println!("Currently at: {} out of {} which has char [{}]", offset, text.len(), text[offset] as u16);
-            let mut val = &text[offset..];
-            let mut val_as_string = String::from_iter(val.iter());
-            let i = val_as_string.find(' ').unwrap_or(val.len() - 1);
-            let j = val_as_string.find('\n').unwrap_or(val.len() - 1);
-
-            if i < j {
-                val = &val[..i];
+// Lexes by trying every pattern tree at every offset. This is much slower than `lex`, but it is
+// the definition of what the compiled automaton must produce.
+#[allow(dead_code)]
+pub fn reference_lex(source_map: &SourceMap, file_id: FileId) -> Result<Box<TokenStream>, LexError> {
+    let (token_stream, mut errors) = lex_with(source_map, file_id, find_longest_match, false, false);
+    if errors.is_empty() {
+        return Ok(token_stream);
+    }
+    return Err(errors.remove(0));
+}
+
+// Where the lexer is in the text. Both line values start at 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Position {
+    pub line_number: usize,
+    pub line_offset: usize,
+    pub byte_offset: usize,
+}
+
+impl Position {
+    fn start() -> Position {
+        return Position {
+            line_number: 1,
+            line_offset: 1,
+            byte_offset: 0,
+        };
+    }
+
+    // Moves the position past the text. Tokens like comments and multiline strings can span
+    // several lines.
+    fn advance(&self, text: &str) -> Position {
+        let mut result = *self;
+        for c in text.chars() {
+            if c == '\n' {
+                result.line_number += 1;
+                result.line_offset = 1;
             } else {
-                val = &val[..j];
+                result.line_offset += 1;
             }
-
-            val_as_string = String::from_iter(val.iter());
-            println!("Unable to match text at [line: {}: character: {}]: [{}] [{}]", line_number, line_offset, val[0], val_as_string);
-            return None;
         }
+        result.byte_offset += text.len();
+        return result;
     }
+}
+
+// Everything about a run of the lexer that doesn't change as it moves through the text.
+struct LexContext {
+    file_id: FileId,
+    find_match: fn(LexerMode, &[char], usize) -> Option<Box<Match>>,
+    recover: bool,
+    // keep the tokens that the parser skips
+    keep_trivia: bool,
+}
 
-    return Some(Box::new(TokenStream {
+impl LexContext {
+    fn error(&self, message: &str, position: Position, bad_text: &[char], snippet: &[char]) -> LexError {
+        return LexError {
+            message: message.to_string(),
+            file_id: self.file_id,
+            start: position.byte_offset,
+            end: position.byte_offset + bad_text.iter().map(|c| c.len_utf8()).sum::<usize>(),
+            line_number: position.line_number,
+            line_offset: position.line_offset,
+            snippet: String::from_iter(snippet[..min(snippet.len(), MAX_SNIPPET_LENGTH)].iter()),
+        };
+    }
+}
+
+fn lex_with(source_map: &SourceMap, file_id: FileId,
+            find_match: fn(LexerMode, &[char], usize) -> Option<Box<Match>>,
+            recover: bool, keep_trivia: bool) -> (Box<TokenStream>, Vec<LexError>) {
+    let source_file = source_map.get(file_id);
+    let context = LexContext {
+        file_id,
+        find_match,
+        recover,
+        keep_trivia,
+    };
+    let text: Vec<char> = source_file.contents.chars().collect();
+    let mut matches = vec![];
+    let mut errors = vec![];
+    lex_text(&context, LexerMode::Dog, &text, Position::start(), &mut matches, &mut errors);
+
+    let token_stream = Box::new(TokenStream {
         matches,
         offset: 0,
         fresh: true,
         last_consumed_offset: None,
-    }));
+    });
+    return (token_stream, errors);
+}
+
+// Lexes text that begins at the given position of the file. This is the whole file, or a part
+// of it, like the expression inside of an interpolated string or the body of a sql string.
+fn lex_text(context: &LexContext, mode: LexerMode, text: &[char], start: Position,
+            matches: &mut Vec<Box<Match>>, errors: &mut Vec<LexError>) {
+    let mut position = start;
+    let mut offset = 0;
+    while offset < text.len() {
+        if !context.recover && !errors.is_empty() {
+            return;
+        }
+        let (next_offset, next_position) = lex_next(context, mode, text, offset, position, matches, errors);
+        offset = next_offset;
+        position = next_position;
+    }
+}
+
+// Lexes the token at offset, or records an error for the character there if nothing matches.
+// Returns the offset and position just past what was consumed.
+fn lex_next(context: &LexContext, mode: LexerMode, text: &[char], offset: usize, position: Position,
+            matches: &mut Vec<Box<Match>>, errors: &mut Vec<LexError>) -> (usize, Position) {
+    if let Some(mut longest_match) = (context.find_match)(mode, text, offset) {
+        let end = position.advance(&longest_match.value);
+        set_position(&mut longest_match, context.file_id, position, end);
+        let next_offset = offset + longest_match.length;
+        if !longest_match.skip {
+            push_match(context, mode, text, offset, longest_match, matches, errors);
+        } else if context.keep_trivia {
+            matches.push(longest_match);
+        }
+        return (next_offset, end);
+    }
+    let snippet_end = text[offset + 1..].iter()
+        .position(|c| c.is_whitespace())
+        .map(|position| offset + 1 + position)
+        .unwrap_or(text.len());
+    errors.push(context.error("Unable to match text", position,
+                              &text[offset..offset + 1], &text[offset..snippet_end]));
+    // skip the character and hope the rest of the file makes sense
+    return (offset + 1, position.advance(&text[offset].to_string()));
+}
+
+fn set_position(token: &mut Match, file_id: FileId, start: Position, end: Position) {
+    token.file_id = Some(file_id);
+    token.line_number = start.line_number;
+    token.line_offset = start.line_offset;
+    token.start_byte_offset = start.byte_offset;
+    token.end_line_number = end.line_number;
+    token.end_line_offset = end.line_offset;
+    token.end_byte_offset = end.byte_offset;
+}
+
+// String and number literals are checked here, so that the user hears about bad escape sequences
+// and numbers that don't fit their type alongside every other lexing problem. Names are normalized
+// so that they compare equal however they were written. Interpolated strings
+// are split into pieces, with the tokens of each expression in between, and tokens that hold
+// another language are replaced by the tokens of their body.
+fn push_match(context: &LexContext, mode: LexerMode, text: &[char], offset: usize, mut token: Box<Match>,
+              matches: &mut Vec<Box<Match>>, errors: &mut Vec<LexError>) {
+    if let Some(language) = mode.embedded_language(&token.label) {
+        let literal = &text[offset..offset + token.length];
+        let start = Position {
+            line_number: token.line_number,
+            line_offset: token.line_offset,
+            byte_offset: token.start_byte_offset,
+        };
+        let body_start = language.delimiter_length;
+        let body_end = literal.len() - language.delimiter_length;
+        matches.push(build_token(context, language.start_label, &literal[..body_start], start));
+        let body_position = advance_chars(start, &literal[..body_start]);
+        lex_text(context, language.mode, &literal[body_start..body_end], body_position, matches, errors);
+        matches.push(build_token(context, language.end_label, &literal[body_end..],
+                                 advance_chars(start, &literal[..body_end])));
+        return;
+    }
+    if token.label == NUMBER_LITERAL {
+        if let Err(error) = parse_number_literal(&token.value) {
+            let start = Position {
+                line_number: token.line_number,
+                line_offset: token.line_offset,
+                byte_offset: token.start_byte_offset,
+            };
+            let literal = &text[offset..offset + token.length];
+            let bad_text = &literal[error.offset..error.offset + error.length];
+            errors.push(context.error(&error.message, advance_chars(start, &literal[..error.offset]), bad_text, bad_text));
+        }
+    }
+    if token.label == WORD {
+        // the positions and length still describe the text as it is in the file
+        token.value = normalize_identifier(&token.value);
+    }
+    if token.label != STRING_LITERAL {
+        matches.push(token);
+        return;
+    }
+    let literal = &text[offset..offset + token.length];
+    let start = Position {
+        line_number: token.line_number,
+        line_offset: token.line_offset,
+        byte_offset: token.start_byte_offset,
+    };
+    let (_, expressions) = scan_string_literal(literal, 0).unwrap();
+    if expressions.is_empty() {
+        push_string_piece(context, literal, start, STRING_LITERAL, 0..literal.len(), matches, errors);
+        return;
+    }
+
+    let mut piece_start = 0;
+    for (index, (expression_start, expression_end)) in expressions.into_iter().enumerate() {
+        let label = if index == 0 { INTERPOLATED_STRING_START } else { INTERPOLATED_STRING_MIDDLE };
+        push_string_piece(context, literal, start, label, piece_start..expression_start, matches, errors);
+
+        let expression = &literal[expression_start..expression_end];
+        let expression_position = advance_chars(start, &literal[..expression_start]);
+        let token_count = matches.len();
+        let error_count = errors.len();
+        lex_text(context, mode, expression, expression_position, matches, errors);
+        if matches.len() == token_count && errors.len() == error_count {
+            let braces = &literal[expression_start - 1..expression_end + 1];
+            errors.push(context.error("Expected an expression between `{` and `}`",
+                                      advance_chars(start, &literal[..expression_start - 1]), braces, braces));
+        }
+        piece_start = expression_end;
+    }
+    push_string_piece(context, literal, start, INTERPOLATED_STRING_END, piece_start..literal.len(), matches, errors);
 }
 
-fn find_longest_match(text: &Vec<char>, offset: usize) -> Option<Box<Match>> {
+fn push_string_piece(context: &LexContext, literal: &[char], start: Position, label: &str, piece_range: Range<usize>,
+                     matches: &mut Vec<Box<Match>>, errors: &mut Vec<LexError>) {
+    let piece = &literal[piece_range.clone()];
+    let value = String::from_iter(piece.iter());
+    let piece_position = advance_chars(start, &literal[..piece_range.start]);
+    if let Err(error) = decode_string_token(label, &value) {
+        let bad_text = &piece[error.offset..error.offset + error.length];
+        errors.push(context.error(&error.message, advance_chars(piece_position, &piece[..error.offset]), bad_text, bad_text));
+    }
+    matches.push(build_token(context, label, piece, piece_position));
+}
+
+// builds a token for text that the lexer split out of a larger match
+fn build_token(context: &LexContext, label: &str, text: &[char], start: Position) -> Box<Match> {
+    let mut token = Box::new(Match {
+        length: text.len(),
+        label: label.to_string(),
+        value: String::from_iter(text.iter()),
+        skip: false,
+        line_number: 0,
+        line_offset: 0,
+        end_line_number: 0,
+        end_line_offset: 0,
+        start_byte_offset: 0,
+        end_byte_offset: 0,
+        file_id: None,
+    });
+    let end = start.advance(&token.value);
+    set_position(&mut token, context.file_id, start, end);
+    return token;
+}
+
+fn advance_chars(position: Position, text: &[char]) -> Position {
+    return position.advance(&String::from_iter(text.iter()));
+}
+
+fn find_longest_match(mode: LexerMode, text: &[char], offset: usize) -> Option<Box<Match>> {
     // This is stupid that I need two variables to detect whether we found a match.
     // However, when I try to use one, it gets complicated. More research needed.
     let mut longest_match: Option<Match> = None;
     let mut longest_match_len: usize = 0;
-    for pattern in get_patterns() {
+    for pattern in mode.patterns() {
         let next = pattern.match_with(text, offset);
         if let Some(next) = next {
             if longest_match.is_none() || next.length > longest_match_len {
@@ -112,6 +347,42 @@ fn find_longest_match(text: &Vec<char>, offset: usize) -> Option<Box<Match>> {
     return Some(Box::new(longest_match.unwrap()));
 }
 
+// The text of the tokens with this label, if they can only ever be one piece of text, like `{` or
+// `let`.
+pub fn token_text(label: &str) -> Option<String> {
+    return LexerMode::Dog.patterns().iter()
+        .find(|pattern| pattern.label == label)
+        .and_then(|pattern| pattern.pattern_group.fixed_text());
+}
+
+// lexes a snippet of code that doesn't belong to any real file
+#[cfg(test)]
+pub fn lex_code(dog_code: &str) -> Result<Box<TokenStream>, LexError> {
+    let mut source_map = SourceMap::new();
+    let file_id = source_map.add_file(None, None, dog_code.to_string());
+    return lex(&source_map, file_id);
+}
+
+// numbers below the limit that each call is given, the same ones for the same seed, for tests that
+// put code together from random fragments. a small linear congruential generator keeps the tests
+// deterministic.
+#[cfg(test)]
+pub fn test_random(seed: u64) -> impl FnMut(usize) -> usize {
+    let mut seed = seed;
+    return move |limit| {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        return ((seed >> 33) % limit as u64) as usize;
+    };
+}
+
+fn find_longest_match_with_dfa(mode: LexerMode, text: &[char], offset: usize) -> Option<Box<Match>> {
+    let patterns = mode.patterns();
+    if let Some((pattern_index, length)) = mode.dfa().longest_match(patterns, text, offset) {
+        return Some(Box::new(patterns[pattern_index].build_match(text, offset, length)));
+    }
+    return None;
+}
+
 fn render_string(s: String) -> String {
     let mut result = s.replace("\n", "\\n");
     result = result.replace("\r", "\\r");
@@ -153,7 +424,7 @@ mod lex_tests {
     fn lex_simple() {
         let text: Vec<char> = "app main() {}".chars().collect();
 
-        let option = find_longest_match(&text, 0);
+        let option = find_longest_match(LexerMode::Dog, &text, 0);
         assert_eq!(true, option.is_some());
         let match_obj = option.unwrap();
         let match_value = match_obj.value;
@@ -161,6 +432,186 @@ mod lex_tests {
         assert_eq!("app", match_value);
     }
 
+    #[test]
+    fn lex_error() {
+        let mut source_map = SourceMap::new();
+        let file_id = source_map.add_file(Some("main.dog"), Some("src/main.dog"), "let x = 1\nlet y@ = 2".to_string());
+        let error = lex(&source_map, file_id).unwrap_err();
+        assert_eq!(file_id, error.file_id);
+        assert_eq!(2, error.line_number);
+        assert_eq!(6, error.line_offset);
+        assert_eq!(15, error.start);
+        assert_eq!(16, error.end);
+        assert_eq!("@", error.snippet);
+        assert_eq!("Unable to match text at main.dog:2:6: [@]", error.render(&source_map));
+    }
+
+    #[test]
+    fn lex_error_recovery() {
+        let mut source_map = SourceMap::new();
+        let file_id = source_map.add_file(None, None, "a @ b\n$c €".to_string());
+        let (token_stream, errors) = lex_with_recovery(&source_map, file_id);
+        assert_eq!(3, errors.len());
+        assert_eq!((1, 3), (errors[0].line_number, errors[0].line_offset));
+        assert_eq!((2, 1), (errors[1].line_number, errors[1].line_offset));
+        assert_eq!("$c", errors[1].snippet);
+        assert_eq!((2, 4), (errors[2].line_number, errors[2].line_offset));
+        assert_eq!((9, 12), (errors[2].start, errors[2].end));
+        let values: Vec<&str> = token_stream.matches.iter().map(|m| m.value.as_str()).collect();
+        assert_eq!(vec!["a", "b", "c"], values);
+    }
+
+    #[test]
+    fn lex_positions() {
+        let token_stream = lex_code("let s = \"a\n\nbé\" // note\n  x").unwrap();
+        let matches = token_stream.matches();
+        assert_eq!(5, matches.len());
+
+        let string_literal = matches[3];
+        assert_eq!("_string_literal", string_literal.label);
+        assert_eq!((1, 9), (string_literal.line_number, string_literal.line_offset));
+        assert_eq!((3, 4), (string_literal.end_line_number, string_literal.end_line_offset));
+        assert_eq!((8, 16), (string_literal.start_byte_offset, string_literal.end_byte_offset));
+
+        // the comment is skipped, but it still moves us to the next line
+        let x = matches[4];
+        assert_eq!((4, 3), (x.line_number, x.line_offset));
+        assert_eq!((4, 4), (x.end_line_number, x.end_line_offset));
+        assert_eq!((27, 28), (x.start_byte_offset, x.end_byte_offset));
+    }
+
+    #[test]
+    fn lex_comments() {
+        let token_stream = lex_code("/* a /* nested */ comment */ x // to the end\ny // no newline").unwrap();
+        let values: Vec<&str> = token_stream.matches.iter().map(|m| m.value.as_str()).collect();
+        assert_eq!(vec!["x", "y"], values);
+        assert_eq!(2, token_stream.matches[1].line_number);
+    }
+
+    #[test]
+    fn lex_doc_comments() {
+        let token_stream = lex_code("//! module docs\n/// about x\n//// just a comment\nx").unwrap();
+        let matches = token_stream.matches();
+        let labels: Vec<&str> = matches.iter().map(|m| m.label.as_str()).collect();
+        assert_eq!(vec!["_doc_comment", "_doc_comment", "_word"], labels);
+        assert_eq!("/// about x\n", matches[1].value);
+        assert_eq!(4, matches[2].line_number);
+    }
+
+    #[test]
+    fn lex_unicode_identifiers() {
+        // the second größe is written with a combining diaeresis, but it's the same name
+        let token_stream = lex_code("let größe = 名前 + gro\u{308}ße_2").unwrap();
+        let matches = token_stream.matches();
+        let tokens: Vec<(&str, &str)> = matches.iter().map(|m| (m.label.as_str(), m.value.as_str())).collect();
+        assert_eq!(vec![
+            ("_let", "let"),
+            ("_word", "größe"),
+            ("_equal", "="),
+            ("_word", "名前"),
+            ("_plus", "+"),
+            ("_word", "größe_2"),
+        ], tokens);
+        // positions still describe the text in the file
+        let last = matches[5];
+        assert_eq!(8, last.length);
+        assert_eq!((1, 18), (last.line_number, last.line_offset));
+        assert_eq!((1, 26), (last.end_line_number, last.end_line_offset));
+        assert_eq!((23, 33), (last.start_byte_offset, last.end_byte_offset));
+    }
+
+    #[test]
+    fn lex_interpolated_string() {
+        let token_stream = lex_code("x = \"sum: {a + b}, {f(\"{c}\")}!\"").unwrap();
+        let matches = token_stream.matches();
+        let tokens: Vec<(&str, &str)> = matches.iter().map(|m| (m.label.as_str(), m.value.as_str())).collect();
+        assert_eq!(vec![
+            ("_word", "x"),
+            ("_equal", "="),
+            ("_interpolated_string_start", "\"sum: {"),
+            ("_word", "a"),
+            ("_plus", "+"),
+            ("_word", "b"),
+            ("_interpolated_string_middle", "}, {"),
+            ("_word", "f"),
+            ("_open_paren", "("),
+            ("_interpolated_string_start", "\"{"),
+            ("_word", "c"),
+            ("_interpolated_string_end", "}\""),
+            ("_close_paren", ")"),
+            ("_interpolated_string_end", "}!\""),
+        ], tokens);
+        let a = matches[3];
+        assert_eq!((1, 12), (a.line_number, a.line_offset));
+        assert_eq!((11, 12), (a.start_byte_offset, a.end_byte_offset));
+        let middle = matches[6];
+        assert_eq!((1, 17), (middle.line_number, middle.line_offset));
+        assert_eq!((1, 21), (middle.end_line_number, middle.end_line_offset));
+    }
+
+    #[test]
+    fn lex_string_literal_errors() {
+        let mut source_map = SourceMap::new();
+        let file_id = source_map.add_file(Some("main.dog"), None, "a = 'ok\\n'\nb = \"x\\q {y $} \\u{d800}\" '{ }'".to_string());
+        let (token_stream, errors) = lex_with_recovery(&source_map, file_id);
+        let messages: Vec<String> = errors.iter().map(|error| error.render(&source_map)).collect();
+        assert_eq!(vec![
+            "Unknown escape sequence `\\q` at main.dog:2:7: [\\q]",
+            "Unable to match text at main.dog:2:13: [$]",
+            "Invalid unicode escape `\\u{d800}`: not a unicode scalar value at main.dog:2:16: [\\u{d800}]",
+            "Expected an expression between `{` and `}` at main.dog:2:27: [{ }]",
+        ], messages);
+        assert_eq!((17, 19), (errors[0].start, errors[0].end));
+        assert_eq!(10, token_stream.matches.len());
+    }
+
+    #[test]
+    fn lex_number_literals() {
+        let token_stream = lex_code("0xFF_u8 0o17 0b1010 1_000 2.5e-3f32 10u8 1..=10").unwrap();
+        let values: Vec<&str> = token_stream.matches.iter().map(|m| m.value.as_str()).collect();
+        assert_eq!(vec!["0xFF_u8", "0o17", "0b1010", "1_000", "2.5e-3f32", "10u8", "1", ".", ".", "=", "10"], values);
+
+        let mut source_map = SourceMap::new();
+        let file_id = source_map.add_file(Some("main.dog"), None, "x = 256u8 + 1.5i32".to_string());
+        let (_, errors) = lex_with_recovery(&source_map, file_id);
+        let messages: Vec<String> = errors.iter().map(|error| error.render(&source_map)).collect();
+        assert_eq!(vec![
+            "Integer literal is too large for u8 at main.dog:1:5: [256u8]",
+            "Float literal can't have the integer suffix `i32` at main.dog:1:16: [i32]",
+        ], messages);
+    }
+
+    #[test]
+    fn lex_sql() {
+        let token_stream = lex_code("let q = `SELECT name, \"order\" FROM t\n  WHERE id >= :id -- note\n  AND s = 'it''s'`").unwrap();
+        let matches = token_stream.matches();
+        let tokens: Vec<(&str, &str)> = matches.iter().skip(3).map(|m| (m.label.as_str(), m.value.as_str())).collect();
+        assert_eq!(vec![
+            ("_sql_start", "`"),
+            ("_sql_keyword", "SELECT"),
+            ("_sql_identifier", "name"),
+            ("_sql_comma", ","),
+            ("_sql_identifier", "\"order\""),
+            ("_sql_keyword", "FROM"),
+            ("_sql_identifier", "t"),
+            ("_sql_keyword", "WHERE"),
+            ("_sql_identifier", "id"),
+            ("_sql_operator", ">="),
+            ("_sql_variable", ":id"),
+            ("_sql_keyword", "AND"),
+            ("_sql_identifier", "s"),
+            ("_sql_operator", "="),
+            ("_sql_string_literal", "'it''s'"),
+            ("_sql_end", "`"),
+        ], tokens);
+        // positions are in the dog file, not the sql
+        let variable = matches[13];
+        assert_eq!((2, 15), (variable.line_number, variable.line_offset));
+        assert_eq!((51, 54), (variable.start_byte_offset, variable.end_byte_offset));
+        let end = matches[18];
+        assert_eq!((3, 18), (end.line_number, end.line_offset));
+    }
+
     #[test]
     fn text_match_with_and() {
         let mut alpha_patterns: Vec<Box<dyn Group>> = vec![];
@@ -0,0 +1,76 @@
+use crate::lex::dfa::{CharRanges, Nfa};
+use crate::lex::group::Group;
+
+// Matches text between an open and close marker where the markers can nest, like /* /* */ */.
+// Nesting can't be expressed with the other groups (or with an automaton), so this is always
+// matched directly.
+pub struct NestedGroup {
+    pub open_text: String,
+    pub close_text: String,
+}
+
+fn starts_with(text: &[char], offset: usize, target: &str) -> Option<usize> {
+    let mut length = 0;
+    for c in target.chars() {
+        if offset + length >= text.len() || text[offset + length] != c {
+            return None;
+        }
+        length += 1;
+    }
+    return Some(length);
+}
+
+impl Group for NestedGroup {
+    fn match_with(&self, text: &[char], offset: usize) -> Option<u32> {
+        let mut depth = 0;
+        let mut current_offset = offset;
+        loop {
+            if let Some(length) = starts_with(text, current_offset, &self.open_text) {
+                depth += 1;
+                current_offset += length;
+            } else if current_offset == offset {
+                return None;
+            } else if let Some(length) = starts_with(text, current_offset, &self.close_text) {
+                depth -= 1;
+                current_offset += length;
+                if depth == 0 {
+                    return Some((current_offset - offset) as u32);
+                }
+            } else if current_offset < text.len() {
+                current_offset += 1;
+            } else {
+                // never closed
+                return None;
+            }
+        }
+    }
+
+    fn contains_any(&self) -> bool {
+        return false;
+    }
+
+    fn name(&self) -> String {
+        return "NestedGroup".to_string();
+    }
+
+    fn min_matches(&self) -> usize {
+        return self.open_text.chars().count() + self.close_text.chars().count();
+    }
+
+    fn render(&self) -> String {
+        let mut result: String = String::from("nested('");
+        result += &*self.open_text.replace("'", "\\'");
+        result += "', '";
+        result += &*self.close_text.replace("'", "\\'");
+        result += "')";
+        return result;
+    }
+
+    fn build_nfa(&self, _nfa: &mut Nfa) -> Option<(usize, usize)> {
+        return None;
+    }
+
+    fn single_char_ranges(&self) -> Option<CharRanges> {
+        return None;
+    }
+}
@@ -1,3 +1,4 @@
+use crate::lex::dfa::{CharRanges, complement_ranges, Nfa};
 use crate::lex::group::Group;
 
 pub struct NotGroup {
@@ -7,6 +8,9 @@ pub struct NotGroup {
 
 impl Group for NotGroup {
     fn match_with(&self, text: &[char], offset: usize) -> Option<u32> {
+        if offset + self.length as usize > text.len() {
+            return None;
+        }
         if self.group.match_with(text, offset).is_some() {
             return None;
         }
@@ -34,4 +38,22 @@ impl Group for NotGroup {
         result += &*self.group.render();
         return result;
     }
+
+    fn build_nfa(&self, nfa: &mut Nfa) -> Option<(usize, usize)> {
+        // only the single character form is a regular language that's easy to express
+        let ranges = self.single_char_ranges()?;
+        let start = nfa.add_state();
+        let end = nfa.add_state();
+        for (range_start, range_end) in ranges {
+            nfa.add_transition(start, range_start, range_end, end);
+        }
+        return Some((start, end));
+    }
+
+    fn single_char_ranges(&self) -> Option<CharRanges> {
+        if self.length != 1 {
+            return None;
+        }
+        return Some(complement_ranges(&self.group.single_char_ranges()?));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,269 @@
+// Number literals are lexed as a single `_number_literal` token whose value is the original
+// source text, like `0xFF_u8` or `2.5e-3f32`. This turns that text into the number it represents
+// and checks that it fits the type named by its suffix.
+//
+// Without a suffix, an integer must fit in a u64 and a float in an f64. The minus sign is a
+// separate token, so signed suffixes accept the magnitude of the type's minimum value (128i8);
+// whether the literal is actually negated is checked once expressions are known.
+
+use crate::lex::string_literal::LiteralError;
+
+pub const NUMBER_LITERAL: &str = "_number_literal";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberSuffix {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+}
+
+impl NumberSuffix {
+    pub fn all() -> Vec<NumberSuffix> {
+        return vec![NumberSuffix::I8, NumberSuffix::I16, NumberSuffix::I32, NumberSuffix::I64,
+                    NumberSuffix::U8, NumberSuffix::U16, NumberSuffix::U32, NumberSuffix::U64,
+                    NumberSuffix::F32, NumberSuffix::F64];
+    }
+
+    pub fn from_name(name: &str) -> Option<NumberSuffix> {
+        return NumberSuffix::all().into_iter().find(|suffix| suffix.name() == name);
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            NumberSuffix::I8 => "i8",
+            NumberSuffix::I16 => "i16",
+            NumberSuffix::I32 => "i32",
+            NumberSuffix::I64 => "i64",
+            NumberSuffix::U8 => "u8",
+            NumberSuffix::U16 => "u16",
+            NumberSuffix::U32 => "u32",
+            NumberSuffix::U64 => "u64",
+            NumberSuffix::F32 => "f32",
+            NumberSuffix::F64 => "f64",
+        };
+    }
+
+    pub fn is_float(&self) -> bool {
+        return *self == NumberSuffix::F32 || *self == NumberSuffix::F64;
+    }
+
+    // the largest magnitude an integer literal with this suffix may have
+    fn max_magnitude(&self) -> u64 {
+        return match self {
+            NumberSuffix::I8 => 1 << 7,
+            NumberSuffix::I16 => 1 << 15,
+            NumberSuffix::I32 => 1 << 31,
+            NumberSuffix::I64 => 1 << 63,
+            NumberSuffix::U8 => u8::MAX as u64,
+            NumberSuffix::U16 => u16::MAX as u64,
+            NumberSuffix::U32 => u32::MAX as u64,
+            NumberSuffix::U64 | NumberSuffix::F32 | NumberSuffix::F64 => u64::MAX,
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumberValue {
+    Integer(u64),
+    Float(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberLiteral {
+    // 2, 8, 10 or 16
+    pub radix: u32,
+    pub suffix: Option<NumberSuffix>,
+    pub value: NumberValue,
+}
+
+fn radix_name(radix: u32) -> &'static str {
+    return match radix {
+        2 => "Binary",
+        8 => "Octal",
+        16 => "Hexadecimal",
+        _ => "Decimal",
+    };
+}
+
+pub fn parse_number_literal(text: &str) -> Result<NumberLiteral, LiteralError> {
+    let chars: Vec<char> = text.chars().collect();
+    let whole_literal = |message: String| LiteralError {
+        message,
+        offset: 0,
+        length: chars.len(),
+    };
+
+    let (radix, digits_start) = match text.get(0..2) {
+        Some("0x") => (16, 2),
+        Some("0o") => (8, 2),
+        Some("0b") => (2, 2),
+        _ => (10, 0),
+    };
+
+    // digits never contain i or u, and only hexadecimal digits contain f
+    let suffix_start = chars[digits_start..].iter()
+        .position(|c| *c == 'i' || *c == 'u' || (radix != 16 && *c == 'f'))
+        .map(|position| digits_start + position)
+        .unwrap_or(chars.len());
+    let mut suffix = None;
+    if suffix_start < chars.len() {
+        let suffix_text: String = chars[suffix_start..].iter().collect();
+        suffix = NumberSuffix::from_name(&suffix_text);
+        if suffix.is_none() {
+            return Err(LiteralError {
+                message: format!("Unknown number suffix `{}`", suffix_text),
+                offset: suffix_start,
+                length: chars.len() - suffix_start,
+            });
+        }
+        if radix != 10 && suffix.unwrap().is_float() {
+            return Err(LiteralError {
+                message: format!("{} literal can't have the float suffix `{}`", radix_name(radix), suffix_text),
+                offset: suffix_start,
+                length: chars.len() - suffix_start,
+            });
+        }
+    }
+
+    let mut digits = String::new();
+    for (index, c) in chars[digits_start..suffix_start].iter().enumerate() {
+        let is_float_part = radix == 10 && (*c == '.' || *c == 'e' || *c == 'E' || *c == '+' || *c == '-');
+        if *c != '_' && !is_float_part && !c.is_digit(radix) {
+            return Err(LiteralError {
+                message: format!("Invalid digit `{}` in {} literal", c, radix_name(radix).to_lowercase()),
+                offset: digits_start + index,
+                length: 1,
+            });
+        }
+        if *c != '_' {
+            digits.push(*c);
+        }
+    }
+    if digits.is_empty() {
+        return Err(whole_literal(format!("{} literal has no digits", radix_name(radix))));
+    }
+
+    // e is a digit in hexadecimal, and there are no hexadecimal floats
+    let is_float = radix == 10 && digits.contains(['.', 'e', 'E']);
+    if is_float {
+        if let Some(suffix) = suffix {
+            if !suffix.is_float() {
+                return Err(LiteralError {
+                    message: format!("Float literal can't have the integer suffix `{}`", suffix.name()),
+                    offset: suffix_start,
+                    length: chars.len() - suffix_start,
+                });
+            }
+        }
+        if digits.ends_with(['e', 'E', '+', '-']) {
+            return Err(whole_literal("Exponent has no digits".to_string()));
+        }
+    }
+
+    if is_float || suffix.is_some_and(|suffix| suffix.is_float()) {
+        let value: f64 = digits.parse()
+            .map_err(|_| whole_literal("Float literal is not valid".to_string()))?;
+        let suffix_name = suffix.map_or("f64", |suffix| suffix.name());
+        let limit = if suffix == Some(NumberSuffix::F32) { f32::MAX as f64 } else { f64::MAX };
+        if !value.is_finite() || value > limit {
+            return Err(whole_literal(format!("Float literal is out of range for {}", suffix_name)));
+        }
+        return Ok(NumberLiteral {
+            radix,
+            suffix,
+            value: NumberValue::Float(value),
+        });
+    }
+
+    let suffix_name = suffix.map_or("u64", |suffix| suffix.name());
+    let too_large = || whole_literal(format!("Integer literal is too large for {}", suffix_name));
+    let value = u64::from_str_radix(&digits, radix).map_err(|_| too_large())?;
+    if value > suffix.map_or(u64::MAX, |suffix| suffix.max_magnitude()) {
+        return Err(too_large());
+    }
+    return Ok(NumberLiteral {
+        radix,
+        suffix,
+        value: NumberValue::Integer(value),
+    });
+}
+
+#[cfg(test)]
+mod number_literal_tests {
+    use crate::lex::number_literal::*;
+
+    fn value(text: &str) -> NumberValue {
+        return parse_number_literal(text).unwrap().value;
+    }
+
+    fn error(text: &str) -> String {
+        return parse_number_literal(text).unwrap_err().message;
+    }
+
+    #[test]
+    fn parse_radixes_and_separators() {
+        assert_eq!(NumberLiteral {
+            radix: 16,
+            suffix: Some(NumberSuffix::U8),
+            value: NumberValue::Integer(255),
+        }, parse_number_literal("0xF_fu8").unwrap());
+        assert_eq!(NumberValue::Integer(15), value("0o17"));
+        assert_eq!(NumberValue::Integer(10), value("0b1010"));
+        assert_eq!(NumberValue::Integer(1_000_000), value("1_000_000"));
+        assert_eq!(NumberValue::Integer(u64::MAX), value("18446744073709551615"));
+        assert_eq!(10, parse_number_literal("0").unwrap().radix);
+        // e is a hexadecimal digit, not an exponent
+        assert_eq!(NumberValue::Integer(0xFE), value("0xFE"));
+        assert_eq!(NumberValue::Integer(0xDEADBEEF), value("0xDEADBEEF"));
+        assert_eq!(NumberValue::Integer(0x1e), value("0x1e_u8"));
+        assert_eq!(NumberValue::Integer(0xe5), value("0xe5"));
+    }
+
+    #[test]
+    fn parse_floats() {
+        assert_eq!(NumberValue::Float(1.5), value("1.5"));
+        assert_eq!(NumberValue::Float(0.0025), value("2.5e-3"));
+        assert_eq!(NumberValue::Float(1000.0), value("1E3"));
+        assert_eq!(NumberLiteral {
+            radix: 10,
+            suffix: Some(NumberSuffix::F32),
+            value: NumberValue::Float(1.0),
+        }, parse_number_literal("1f32").unwrap());
+    }
+
+    #[test]
+    fn check_suffix_range() {
+        assert_eq!(NumberValue::Integer(128), value("128i8"));
+        assert_eq!("Integer literal is too large for i8", error("129i8"));
+        assert_eq!("Integer literal is too large for u8", error("256u8"));
+        assert_eq!(NumberValue::Integer(u32::MAX as u64), value("0xFFFF_FFFFu32"));
+        assert_eq!("Integer literal is too large for u64", error("18446744073709551616"));
+        assert_eq!("Float literal is out of range for f32", error("1e39f32"));
+        assert_eq!("Float literal is out of range for f64", error("1e309"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Err(LiteralError {
+            message: "Float literal can't have the integer suffix `u8`".to_string(),
+            offset: 3,
+            length: 2,
+        }), parse_number_literal("1.5u8"));
+        assert_eq!(Err(LiteralError {
+            message: "Invalid digit `8` in octal literal".to_string(),
+            offset: 3,
+            length: 1,
+        }), parse_number_literal("0o78"));
+        assert_eq!("Hexadecimal literal has no digits", error("0x__"));
+        assert_eq!("Exponent has no digits", error("1e_"));
+        assert_eq!("Unknown number suffix `u7`", error("1u7"));
+        assert_eq!("Binary literal can't have the float suffix `f32`", error("0b1f32"));
+    }
+}
@@ -1,5 +1,6 @@
 use core::option::Option;
 
+use crate::lex::dfa::{CharRanges, Nfa};
 use crate::lex::group::Group;
 
 pub struct OrGroup {
@@ -69,4 +70,23 @@ impl Group for OrGroup {
         result += ")";
         return result;
     }
+
+    fn build_nfa(&self, nfa: &mut Nfa) -> Option<(usize, usize)> {
+        let start = nfa.add_state();
+        let end = nfa.add_state();
+        for group in &self.groups {
+            let (group_start, group_end) = group.build_nfa(nfa)?;
+            nfa.add_epsilon(start, group_start);
+            nfa.add_epsilon(group_end, end);
+        }
+        return Some((start, end));
+    }
+
+    fn single_char_ranges(&self) -> Option<CharRanges> {
+        let mut result = vec![];
+        for group in &self.groups {
+            result.append(&mut group.single_char_ranges()?);
+        }
+        return Some(result);
+    }
 }
@@ -17,22 +17,31 @@ impl PatternMatcher for Pattern {
     fn match_with(&self, text: &[char], offset: usize) -> Option<Match> {
         //println!("Testing group: {} {} {}", self.label, self.pattern_group.name(), self.pattern_group.render());
         if let Some(len) = self.pattern_group.match_with(text, offset) {
-            let end_offset = offset + (len as usize);
-            let matched_text = text[offset..end_offset].iter().collect();
-            return Some(Match {
-                length: len as usize,
-                label: self.label.clone(),
-                value: matched_text,
-                skip: self.skip,
-                line_number: 0,
-                line_offset: 0,
-                file_name: None,
-                file_path: None,
-            });
+            return Some(self.build_match(text, offset, len as usize));
         }
         return None;
     }
 }
 
+impl Pattern {
+    pub fn build_match(&self, text: &[char], offset: usize, length: usize) -> Match {
+        let end_offset = offset + length;
+        let matched_text = text[offset..end_offset].iter().collect();
+        return Match {
+            length,
+            label: self.label.clone(),
+            value: matched_text,
+            skip: self.skip,
+            line_number: 0,
+            line_offset: 0,
+            end_line_number: 0,
+            end_line_offset: 0,
+            start_byte_offset: 0,
+            end_byte_offset: 0,
+            file_id: None,
+        };
+    }
+}
+
 
 
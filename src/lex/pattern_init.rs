@@ -2,13 +2,21 @@ use std::sync::Once;
 
 use crate::lex::and_group::AndGroup;
 use crate::lex::any_char::AnyChar;
+use crate::lex::character_class::{CharacterClass, CharacterClassGroup};
 use crate::lex::character_range::CharacterRange;
 use crate::lex::group::Group;
 use crate::lex::match_repeats::{GroupRepeats, MatchRepeats};
+use crate::lex::nested_group::NestedGroup;
+use crate::lex::not_group::NotGroup;
+use crate::lex::number_literal::{NUMBER_LITERAL, NumberSuffix};
 use crate::lex::or_group::OrGroup;
 use crate::lex::pattern::Pattern;
+use crate::lex::sql_pattern_init::SQL;
+use crate::lex::string_literal::{STRING_LITERAL, StringLiteralGroup};
 use crate::lex::text_pattern::TextPattern;
 
+pub const WORD: &str = "_word";
+
 static mut PATTERNS: Vec<Pattern> = Vec::new();
 static PATTERNS_INIT: Once = Once::new();
 
@@ -60,6 +68,7 @@ unsafe fn build_patterns() {
     PATTERNS.push(build_keyword("_enum", "enum"));
     PATTERNS.push(build_keyword("_trait", "trait"));
     PATTERNS.push(build_keyword("_impl", "impl"));
+    PATTERNS.push(build_keyword("_on", "on")); // impl trait on struct
     PATTERNS.push(build_keyword("_attribute", "attr")); // java annotation/rust attribute
 
     // qualifiers
@@ -100,7 +109,9 @@ unsafe fn build_patterns() {
     PATTERNS.push(build_keyword("_u64", "u64"));
 
     // building block patterns
+    PATTERNS.push(build_doc_comment());
     PATTERNS.push(build_comment());
+    PATTERNS.push(build_block_comment());
     PATTERNS.push(build_sql_string());
     PATTERNS.push(build_quoted_string());
     PATTERNS.push(build_number());
@@ -126,6 +137,7 @@ unsafe fn build_patterns() {
     PATTERNS.push(build_named_character("_exclamation", '!'));
     PATTERNS.push(build_named_character("_question_mark", '?'));
     PATTERNS.push(build_named_character("_colon", ':'));
+    PATTERNS.push(build_named_character("_semicolon", ';'));
     PATTERNS.push(build_named_character("_pipe", '|'));
 
     // whitespace
@@ -133,7 +145,7 @@ unsafe fn build_patterns() {
     PATTERNS.push(whitespace());
 }
 
-fn build_keyword(name: &str, text: &str) -> Pattern {
+pub fn build_keyword(name: &str, text: &str) -> Pattern {
     return Pattern {
         label: name.to_string(),
         pattern_group: Box::new(TextPattern {
@@ -143,37 +155,123 @@ fn build_keyword(name: &str, text: &str) -> Pattern {
     };
 }
 
+// `///` and `//!` comments document the declaration that follows them, so the parser needs
+// to see them. `////` is just a comment.
+fn build_doc_comment() -> Pattern {
+    return Pattern {
+        label: "_doc_comment".to_string(),
+        pattern_group: Box::new(OrGroup {
+            groups: vec![
+                Box::new(AndGroup {
+                    groups: vec![
+                        Box::new(TextPattern {
+                            match_text: "///".to_string()
+                        }),
+                        Box::new(OrGroup {
+                            groups: vec![
+                                // an empty line of documentation
+                                Box::new(GroupRepeats {
+                                    match_repeats: MatchRepeats::ZeroOrOne,
+                                    group: Box::new(CharacterRange {
+                                        match_start_char: '\n',
+                                        match_end_char: '\n',
+                                    }),
+                                }),
+                                Box::new(AndGroup {
+                                    groups: vec![
+                                        Box::new(NotGroup {
+                                            group: Box::new(OrGroup {
+                                                groups: vec![
+                                                    Box::new(CharacterRange {
+                                                        match_start_char: '/',
+                                                        match_end_char: '/',
+                                                    }),
+                                                    Box::new(CharacterRange {
+                                                        match_start_char: '\n',
+                                                        match_end_char: '\n',
+                                                    }),
+                                                ]
+                                            }),
+                                            length: 1,
+                                        }),
+                                        rest_of_line(),
+                                    ]
+                                }),
+                            ]
+                        }),
+                    ]
+                }),
+                Box::new(AndGroup {
+                    groups: vec![
+                        Box::new(TextPattern {
+                            match_text: "//!".to_string()
+                        }),
+                        rest_of_line(),
+                    ]
+                }),
+            ]
+        }),
+        skip: false,
+    };
+}
+
 fn build_comment() -> Pattern {
     return Pattern {
         label: "_comment".to_string(),
         pattern_group: Box::new(AndGroup {
             groups: vec![
-                Box::new(CharacterRange {
-                    match_start_char: '/',
-                    match_end_char: '/',
-                }),
-                Box::new(CharacterRange {
-                    match_start_char: '/',
-                    match_end_char: '/',
+                Box::new(TextPattern {
+                    match_text: "//".to_string()
                 }),
-                Box::new(GroupRepeats {
-                    match_repeats: MatchRepeats::ZeroOrMore,
-                    group: Box::new(AnyChar {}),
+                rest_of_line(),
+            ]
+        }),
+        skip: true,
+    };
+}
+
+// everything up to and including the end of the line. the last line of a file might not have
+// a newline.
+pub fn rest_of_line() -> Box<dyn Group> {
+    return Box::new(AndGroup {
+        groups: vec![
+            Box::new(GroupRepeats {
+                match_repeats: MatchRepeats::ZeroOrMore,
+                group: Box::new(NotGroup {
+                    group: Box::new(CharacterRange {
+                        match_start_char: '\n',
+                        match_end_char: '\n',
+                    }),
+                    length: 1,
                 }),
-                Box::new(CharacterRange {
+            }),
+            Box::new(GroupRepeats {
+                match_repeats: MatchRepeats::ZeroOrOne,
+                group: Box::new(CharacterRange {
                     match_start_char: '\n',
                     match_end_char: '\n',
                 }),
-            ]
+            }),
+        ]
+    });
+}
+
+fn build_block_comment() -> Pattern {
+    return Pattern {
+        label: "_comment".to_string(),
+        pattern_group: Box::new(NestedGroup {
+            open_text: "/*".to_string(),
+            close_text: "*/".to_string(),
         }),
         skip: true,
     };
 }
 
 
+// The body is lexed again with the sql patterns. See LexerMode.
 fn build_sql_string() -> Pattern {
     return Pattern {
-        label: "_sql".to_string(),
+        label: SQL.to_string(),
         pattern_group:
         Box::new(AndGroup {
             groups: vec![
@@ -202,171 +300,120 @@ fn build_sql_string() -> Pattern {
     };
 }
 
+// Escapes, raw strings, triple quotes and interpolation are handled by StringLiteralGroup.
 fn build_quoted_string() -> Pattern {
     return Pattern {
-        label: "_string_literal".to_string(),
-        pattern_group:
-        Box::new(OrGroup {
-            groups: vec![
-                Box::new(AndGroup {
-                    groups: vec![
-                        Box::new(CharacterRange {
-                            match_start_char: '"',
-                            match_end_char: '"',
-                        }),
-                        Box::new(GroupRepeats {
-                            match_repeats: MatchRepeats::ZeroOrMore,
-                            group: Box::new(OrGroup {
-                                groups: vec![
-                                    Box::new(AnyChar {}),
-                                    Box::new(TextPattern {
-                                        match_text: "\\\"".to_string()
-                                    })
-                                ]
-                            }),
-                        }),
-                        Box::new(CharacterRange {
-                            match_start_char: '"',
-                            match_end_char: '"',
-                        }),
-                    ]
-                }),
-                Box::new(AndGroup {
-                    groups: vec![
-                        Box::new(CharacterRange {
-                            match_start_char: '\'',
-                            match_end_char: '\'',
-                        }),
-                        Box::new(GroupRepeats {
-                            match_repeats: MatchRepeats::ZeroOrMore,
-                            group: Box::new(OrGroup {
-                                groups: vec![
-                                    Box::new(AnyChar {}),
-                                    Box::new(TextPattern {
-                                        match_text: "\\\'".to_string()
-                                    })
-                                ]
-                            }),
-                        }),
-                        Box::new(CharacterRange {
-                            match_start_char: '\'',
-                            match_end_char: '\'',
-                        }),
-                    ]
-                }),
-            ]
-        }),
+        label: STRING_LITERAL.to_string(),
+        pattern_group: Box::new(StringLiteralGroup {}),
         skip: false,
     };
 }
 
+// Numbers are decimal (1_000, 1.5, 2.5e-3), hexadecimal (0xFF), octal (0o17) or binary (0b1010),
+// with an optional type suffix (10u8, 1.5f32). Which values fit which suffix is checked by
+// number_literal::parse_number_literal.
 fn build_number() -> Pattern {
-    let mut hex_patterns: Vec<Box<dyn Group>> = vec![];
-    hex_patterns.push(Box::new(CharacterRange {
-        match_start_char: '0',
-        match_end_char: '9',
-    }));
-    hex_patterns.push(Box::new(CharacterRange {
-        match_start_char: 'a',
-        match_end_char: 'f',
-    }));
-    hex_patterns.push(Box::new(CharacterRange {
-        match_start_char: 'A',
-        match_end_char: 'F',
-    }));
+    let digits = || character_set(&[('0', '9'), ('_', '_')]);
+    let decimal = AndGroup {
+        groups: vec![
+            character_set(&[('0', '9')]),
+            repeat(MatchRepeats::ZeroOrMore, digits()),
+            // fraction
+            repeat(MatchRepeats::ZeroOrOne, Box::new(AndGroup {
+                groups: vec![
+                    character_set(&[('.', '.')]),
+                    character_set(&[('0', '9')]),
+                    repeat(MatchRepeats::ZeroOrMore, digits()),
+                ]
+            })),
+            // exponent
+            repeat(MatchRepeats::ZeroOrOne, Box::new(AndGroup {
+                groups: vec![
+                    character_set(&[('e', 'e'), ('E', 'E')]),
+                    repeat(MatchRepeats::ZeroOrOne, character_set(&[('+', '+'), ('-', '-')])),
+                    repeat(MatchRepeats::OneOrMore, digits()),
+                ]
+            })),
+            repeat(MatchRepeats::ZeroOrOne, number_suffix(true)),
+        ]
+    };
 
     return Pattern {
-        label: "_number_literal".to_string(),
+        label: NUMBER_LITERAL.to_string(),
         pattern_group: Box::new(OrGroup {
             groups: vec![
-                Box::new(AndGroup {
-                    groups: vec![
-                        Box::new(TextPattern {
-                            match_text: "0x".to_string()
-                        }),
-                        Box::new(GroupRepeats {
-                            match_repeats: MatchRepeats::OneOrMore,
-                            group: Box::new(OrGroup {
-                                groups: hex_patterns
-                            }),
-                        })
-                    ]
-                }),
-                Box::new(AndGroup {
-                    groups: vec![
-                        Box::new(GroupRepeats {
-                            match_repeats: MatchRepeats::OneOrMore,
-                            group: Box::new(CharacterRange {
-                                match_start_char: '0',
-                                match_end_char: '9',
-                            }),
-                        }),
-                        Box::new(CharacterRange {
-                            match_start_char: '.',
-                            match_end_char: '.',
-                        }),
-                        Box::new(GroupRepeats {
-                            match_repeats: MatchRepeats::OneOrMore,
-                            group: Box::new(CharacterRange {
-                                match_start_char: '0',
-                                match_end_char: '9',
-                            }),
-                        })
-                    ]
-                }),
-                Box::new(AndGroup {
-                    groups: vec![
-                        Box::new(GroupRepeats {
-                            match_repeats: MatchRepeats::OneOrMore,
-                            group: Box::new(CharacterRange {
-                                match_start_char: '0',
-                                match_end_char: '9',
-                            }),
-                        })
-                    ]
-                }),
+                prefixed_integer("0x", &[('0', '9'), ('a', 'f'), ('A', 'F'), ('_', '_')]),
+                prefixed_integer("0o", &[('0', '7'), ('_', '_')]),
+                prefixed_integer("0b", &[('0', '1'), ('_', '_')]),
+                Box::new(decimal),
             ]
         }),
         skip: false,
     };
 }
 
-fn build_word() -> Pattern {
-    let mut first_character_pattern: Vec<Box<dyn Group>> = vec![];
-
-    first_character_pattern.push(Box::new(CharacterRange {
-        match_start_char: 'a',
-        match_end_char: 'z',
-    }));
-    first_character_pattern.push(Box::new(CharacterRange {
-        match_start_char: 'A',
-        match_end_char: 'Z',
-    }));
-
-    let mut body_patterns: Vec<Box<dyn Group>> = vec![];
-    body_patterns.push(Box::new(CharacterRange {
-        match_start_char: 'a',
-        match_end_char: 'z',
-    }));
-    body_patterns.push(Box::new(CharacterRange {
-        match_start_char: 'A',
-        match_end_char: 'Z',
-    }));
-    body_patterns.push(Box::new(CharacterRange {
-        match_start_char: '0',
-        match_end_char: '9',
-    }));
-    body_patterns.push(Box::new(CharacterRange {
-        match_start_char: '_',
-        match_end_char: '_',
-    }));
+fn prefixed_integer(prefix: &str, digits: &[(char, char)]) -> Box<dyn Group> {
+    return Box::new(AndGroup {
+        groups: vec![
+            Box::new(TextPattern {
+                match_text: prefix.to_string()
+            }),
+            repeat(MatchRepeats::OneOrMore, character_set(digits)),
+            // hex digits include f, so only integer suffixes are allowed here
+            repeat(MatchRepeats::ZeroOrOne, number_suffix(false)),
+        ]
+    });
+}
+
+fn number_suffix(allow_float: bool) -> Box<dyn Group> {
+    let mut suffixes: Vec<Box<dyn Group>> = vec![];
+    for suffix in NumberSuffix::all() {
+        if allow_float || !suffix.is_float() {
+            suffixes.push(Box::new(TextPattern {
+                match_text: suffix.name().to_string()
+            }));
+        }
+    }
+    return Box::new(OrGroup {
+        groups: suffixes
+    });
+}
 
+pub fn character_set(ranges: &[(char, char)]) -> Box<dyn Group> {
+    let mut groups: Vec<Box<dyn Group>> = vec![];
+    for (start, end) in ranges {
+        groups.push(Box::new(CharacterRange {
+            match_start_char: *start,
+            match_end_char: *end,
+        }));
+    }
+    return Box::new(OrGroup {
+        groups
+    });
+}
+
+pub fn repeat(match_repeats: MatchRepeats, group: Box<dyn Group>) -> Box<dyn Group> {
+    return Box::new(GroupRepeats {
+        match_repeats,
+        group,
+    });
+}
+
+// identifiers follow unicode's identifier rules, so größe and 名前 are names too. the body
+// characters include the digits and `_`.
+fn build_word() -> Pattern {
+    let first_character_pattern: Vec<Box<dyn Group>> = vec![
+        Box::new(CharacterClassGroup::new(CharacterClass::XidStart)),
+    ];
+    let body_patterns: Vec<Box<dyn Group>> = vec![
+        Box::new(CharacterClassGroup::new(CharacterClass::XidContinue)),
+    ];
     return build_pattern(first_character_pattern, body_patterns);
 }
 
 pub fn build_pattern(first_character_pattern: Vec<Box<dyn Group>>, body_patterns: Vec<Box<dyn Group>>) -> Pattern {
     return Pattern {
-        label: "_word".to_string(),
+        label: WORD.to_string(),
         pattern_group: Box::new(AndGroup {
             groups: vec![
                 Box::new(OrGroup {
@@ -385,7 +432,7 @@ pub fn build_pattern(first_character_pattern: Vec<Box<dyn Group>>, body_patterns
 }
 
 
-fn build_named_character(label: &str, c: char) -> Pattern {
+pub fn build_named_character(label: &str, c: char) -> Pattern {
     return Pattern {
         label: label.to_string(),
         pattern_group: Box::new(TextPattern {
@@ -0,0 +1,243 @@
+// Editors change a file a few characters at a time, and lexing the whole file again after every
+// keystroke is wasteful. This lexes again from a little before the edit until the new tokens line
+// up with the old tokens after the edit, and reuses everything else.
+//
+// The lexer doesn't carry any state from one token to the next, so lexing from the start of any
+// token gives the same result as lexing the whole file. The only exceptions are the tokens inside
+// an interpolated string or a sql string, which are lexed as part of the string they belong to.
+// Once the new tokens reach an old token boundary after the edit, the rest of the text is the
+// same as before and so are its tokens; they just need to be moved.
+
+use std::collections::HashMap;
+
+use crate::lex::{find_longest_match_with_dfa, lex_next, LexContext, Position};
+use crate::lex::lex_error::LexError;
+use crate::lex::lexer_mode::LexerMode;
+use crate::lex::sql_pattern_init::{SQL_END, SQL_START};
+use crate::lex::string_literal::{INTERPOLATED_STRING_END, INTERPOLATED_STRING_START};
+use crate::lex::token_stream::{Match, TokenStream};
+use crate::source::{FileId, SourceMap};
+
+// Replaces the text between the byte offsets start and end with the replacement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+}
+
+// Applies the edit to the file in the source map, and returns what `lex` would return for the
+// edited file. `previous` must be the tokens of the file before the edit.
+#[allow(dead_code)]
+pub fn relex(source_map: &mut SourceMap, file_id: FileId, previous: &TokenStream, edit: &TextEdit) -> Result<Box<TokenStream>, LexError> {
+    let old_contents = &source_map.get(file_id).contents;
+    let edit_line = old_contents[..edit.start].matches('\n').count() + 1;
+    let mut new_contents = old_contents.clone();
+    new_contents.replace_range(edit.start..edit.end, &edit.replacement);
+    source_map.replace_contents(file_id, new_contents);
+
+    let old_tokens = &previous.matches;
+    let top_level = find_top_level_tokens(old_tokens);
+
+    // Start from the last token that begins on an earlier line than the edit. Tokens can look past
+    // their own end before deciding where they stop (1e+ becomes 1e+5), but never past a line
+    // that they aren't part of. Block comments are the exception: a /* that isn't closed is a `/`
+    // and a `*`, and it becomes a comment when the edit adds the */, however many lines later.
+    let mut restart_index = (0..old_tokens.len()).rev()
+        .find(|index| top_level[*index] && old_tokens[*index].line_number < edit_line);
+    if let Some(unclosed) = find_unclosed_comment(old_tokens) {
+        if restart_index.is_some_and(|index| index > unclosed) {
+            // one inside a string can only be lexed again with the whole string, from the start
+            restart_index = if top_level[unclosed] { Some(unclosed) } else { None };
+        }
+    }
+    let mut start = Position::start();
+    if let Some(index) = restart_index {
+        let token = &old_tokens[index];
+        start = Position {
+            line_number: token.line_number,
+            line_offset: token.line_offset,
+            byte_offset: token.start_byte_offset,
+        };
+    }
+
+    // where old tokens after the edit started, in the old text
+    let resync_points: HashMap<usize, usize> = (0..old_tokens.len())
+        .filter(|index| top_level[*index] && old_tokens[*index].start_byte_offset >= edit.end)
+        .map(|index| (old_tokens[index].start_byte_offset, index))
+        .collect();
+    let new_edit_end = edit.start + edit.replacement.len();
+
+    let source_file = source_map.get(file_id);
+    let context = LexContext {
+        file_id,
+        find_match: find_longest_match_with_dfa,
+        recover: false,
+        keep_trivia: false,
+    };
+    let text: Vec<char> = source_file.contents[start.byte_offset..].chars().collect();
+    let mut matches: Vec<Box<Match>> = old_tokens[..restart_index.unwrap_or(0)].to_vec();
+    let mut errors = vec![];
+    let mut offset = 0;
+    let mut position = start;
+    while offset < text.len() {
+        if position.byte_offset >= new_edit_end {
+            let old_byte_offset = position.byte_offset - new_edit_end + edit.end;
+            if let Some(index) = resync_points.get(&old_byte_offset) {
+                move_tokens(&old_tokens[*index..], position, &mut matches);
+                break;
+            }
+        }
+        let (next_offset, next_position) = lex_next(&context, LexerMode::Dog, &text, offset, position, &mut matches, &mut errors);
+        if !errors.is_empty() {
+            return Err(errors.remove(0));
+        }
+        offset = next_offset;
+        position = next_position;
+    }
+
+    return Ok(Box::new(TokenStream {
+        matches,
+        offset: 0,
+        fresh: true,
+        last_consumed_offset: None,
+    }));
+}
+
+// the index of the `/` of the first /* that was never closed
+fn find_unclosed_comment(tokens: &[Box<Match>]) -> Option<usize> {
+    return tokens.windows(2).position(|pair| {
+        pair[0].label == "_slash" && pair[1].label == "_star" && pair[0].end_byte_offset == pair[1].start_byte_offset
+    });
+}
+
+// Tokens that are part of an interpolated string or a sql string can't be lexed on their own.
+fn find_top_level_tokens(tokens: &[Box<Match>]) -> Vec<bool> {
+    let mut result = vec![];
+    let mut depth = 0;
+    for token in tokens {
+        let label = token.label.as_str();
+        if label == INTERPOLATED_STRING_START || label == SQL_START {
+            result.push(depth == 0);
+            depth += 1;
+        } else if label == INTERPOLATED_STRING_END || label == SQL_END {
+            // the end still belongs to the string
+            result.push(false);
+            depth -= 1;
+        } else {
+            result.push(depth == 0);
+        }
+    }
+    return result;
+}
+
+// Adds the old tokens, moved so that the first one starts at the position. Tokens on the same
+// line as the first one also move left or right.
+fn move_tokens(old_tokens: &[Box<Match>], position: Position, matches: &mut Vec<Box<Match>>) {
+    let first = &old_tokens[0];
+    let first_line = first.line_number;
+    let line_shift = position.line_number as isize - first.line_number as isize;
+    let offset_shift = position.line_offset as isize - first.line_offset as isize;
+    let byte_shift = position.byte_offset as isize - first.start_byte_offset as isize;
+    let shift = |value: usize, amount: isize| (value as isize + amount) as usize;
+    for old_token in old_tokens {
+        let mut token = old_token.clone();
+        if token.line_number == first_line {
+            token.line_offset = shift(token.line_offset, offset_shift);
+        }
+        if token.end_line_number == first_line {
+            token.end_line_offset = shift(token.end_line_offset, offset_shift);
+        }
+        token.line_number = shift(token.line_number, line_shift);
+        token.end_line_number = shift(token.end_line_number, line_shift);
+        token.start_byte_offset = shift(token.start_byte_offset, byte_shift);
+        token.end_byte_offset = shift(token.end_byte_offset, byte_shift);
+        matches.push(token);
+    }
+}
+
+#[cfg(test)]
+mod relex_tests {
+    use crate::lex::{lex, test_random};
+    use crate::lex::relex::{relex, TextEdit};
+    use crate::source::SourceMap;
+
+    const SAMPLE: &str = "mod my_mod\n\
+        /* a block\n\
+           comment */\n\
+        app fn main(args: [string]) {\n\
+            let apple: f32 = 1.5 + 0x1F * 42 / (app - lib) // trailing\n\
+            let query = `select * from t\n  where a = :apple`\n\
+            log(\"{apple} is {if apple > 2 { 'big' } otherwise { \"small\" }}\")\n\
+            return 1e+5\n\
+        }\n";
+
+    // relexing the edit has to give the same result as lexing the edited text from scratch
+    fn assert_relex(text: &str, start: usize, end: usize, replacement: &str) {
+        let mut source_map = SourceMap::new();
+        let file_id = source_map.add_file(Some("main.dog"), Some("src/main.dog"), text.to_string());
+        let previous = lex(&source_map, file_id).unwrap();
+        let edit = TextEdit {
+            start,
+            end,
+            replacement: replacement.to_string(),
+        };
+        let actual = relex(&mut source_map, file_id, &previous, &edit);
+
+        let mut expected_text = text.to_string();
+        expected_text.replace_range(start..end, replacement);
+        assert_eq!(expected_text, source_map.get(file_id).contents);
+        let mut expected_source_map = SourceMap::new();
+        let expected_file_id = expected_source_map.add_file(Some("main.dog"), Some("src/main.dog"), expected_text.clone());
+        let expected = lex(&expected_source_map, expected_file_id);
+        assert_eq!(expected, actual, "relexing differed for {:?} after {:?}", expected_text, edit);
+    }
+
+    fn offset_of(text: &str) -> usize {
+        return SAMPLE.find(text).unwrap();
+    }
+
+    #[test]
+    fn relex_edits() {
+        // rename, inside a token
+        assert_relex(SAMPLE, offset_of("apple"), offset_of("apple") + 2, "gr");
+        // the edit joins tokens
+        assert_relex(SAMPLE, offset_of("1e+5"), offset_of("1e+5") + 3, "1e+");
+        assert_relex("x = 1e+ 5", 7, 8, "");
+        // new lines move everything after them
+        assert_relex(SAMPLE, offset_of("let query"), offset_of("let query"), "let x = 1\n\n");
+        assert_relex(SAMPLE, offset_of("let apple"), offset_of("let query"), "");
+        // the edit changes what the rest of the file means
+        assert_relex(SAMPLE, offset_of("/* a block"), offset_of("/* a block"), "/*");
+        assert_relex(SAMPLE, offset_of("comment */"), offset_of("comment */") + 10, "");
+        assert_relex(SAMPLE, offset_of("'big'"), offset_of("'big'") + 1, "");
+        assert_relex(SAMPLE, offset_of("where a"), offset_of("where a"), "-- ");
+        // closing a comment that was opened lines before
+        assert_relex("/* x\ny\nz\n", 7, 7, "*/");
+        assert_relex("a /* /* x\ny\nz\n", 13, 13, "*/");
+        // the edit can't be lexed
+        assert_relex(SAMPLE, offset_of("lib"), offset_of("lib"), "$");
+        // the very beginning and end
+        assert_relex(SAMPLE, 0, 0, "use x\n");
+        assert_relex(SAMPLE, SAMPLE.len(), SAMPLE.len(), "x");
+        assert_relex("", 0, 0, "a b");
+        assert_relex(SAMPLE, 0, SAMPLE.len(), "");
+    }
+
+    #[test]
+    fn relex_generated_edits() {
+        let fragments = [
+            "", "x", " ", "\n", "\"", "'", "{", "}", "`", "/*", "*/", "//", "1", "e", "+", ".", "é",
+        ];
+        let char_offsets: Vec<usize> = SAMPLE.char_indices().map(|(offset, _)| offset)
+            .chain(std::iter::once(SAMPLE.len()))
+            .collect();
+        let mut next = test_random(98765);
+        for _ in 0..300 {
+            let start_index = next(char_offsets.len());
+            let end_index = start_index + next(4).min(char_offsets.len() - 1 - start_index);
+            let replacement = fragments[next(fragments.len())];
+            assert_relex(SAMPLE, char_offsets[start_index], char_offsets[end_index], replacement);
+        }
+    }
+}
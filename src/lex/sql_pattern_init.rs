@@ -0,0 +1,192 @@
+// The patterns for the sql between backticks. The dog patterns match the whole `...` as one `_sql`
+// token, then the lexer switches to these patterns for the text between the backticks, so that
+// the parser can see the keywords, names and :variable references that make up the query.
+
+use std::sync::OnceLock;
+
+use crate::lex::and_group::AndGroup;
+use crate::lex::group::Group;
+use crate::lex::match_repeats::MatchRepeats;
+use crate::lex::not_group::NotGroup;
+use crate::lex::or_group::OrGroup;
+use crate::lex::pattern::Pattern;
+use crate::lex::pattern_init::{build_keyword, build_named_character, character_set, repeat, rest_of_line};
+use crate::lex::text_pattern::TextPattern;
+
+pub const SQL: &str = "_sql";
+pub const SQL_START: &str = "_sql_start";
+pub const SQL_END: &str = "_sql_end";
+
+const SQL_KEYWORDS: [&str; 47] = [
+    "select", "from", "where", "and", "or", "not", "insert", "into", "values", "update", "set",
+    "delete", "create", "table", "drop", "alter", "join", "inner", "left", "right", "outer",
+    "full", "cross", "on", "as", "group", "by", "order", "having", "limit", "offset", "distinct",
+    "null", "is", "in", "like", "between", "union", "all", "asc", "desc", "exists", "case",
+    "when", "then", "else", "end",
+];
+
+static SQL_PATTERNS: OnceLock<Vec<Pattern>> = OnceLock::new();
+
+pub fn get_sql_patterns() -> &'static Vec<Pattern> {
+    return SQL_PATTERNS.get_or_init(build_sql_patterns);
+}
+
+fn build_sql_patterns() -> Vec<Pattern> {
+    // these patterns are in order of precedence from highest to lowest
+    let mut result = vec![];
+
+    for keyword in SQL_KEYWORDS.iter() {
+        result.push(build_sql_keyword(keyword));
+    }
+    result.push(build_sql_comment());
+    result.push(build_sql_variable());
+    result.push(build_sql_identifier());
+    result.push(build_sql_quoted_identifier());
+    result.push(build_sql_string_literal());
+    result.push(build_sql_number_literal());
+
+    for operator in ["=", "<>", "!=", "<", ">", "<=", ">=", "+", "-", "*", "/", "%", "||"].iter() {
+        result.push(build_keyword("_sql_operator", operator));
+    }
+    result.push(build_named_character("_sql_comma", ','));
+    result.push(build_named_character("_sql_period", '.'));
+    result.push(build_named_character("_sql_open_paren", '('));
+    result.push(build_named_character("_sql_close_paren", ')'));
+    result.push(build_named_character("_sql_semicolon", ';'));
+
+    result.push(Pattern {
+        label: "_sql_whitespace".to_string(),
+        pattern_group: repeat(MatchRepeats::OneOrMore, character_set(&[(' ', ' '), ('\t', '\t'), ('\r', '\r'), ('\n', '\n')])),
+        skip: true,
+    });
+    return result;
+}
+
+// sql keywords aren't case sensitive: select, SELECT and Select are all the same keyword
+fn build_sql_keyword(keyword: &str) -> Pattern {
+    let mut letters: Vec<Box<dyn Group>> = vec![];
+    for c in keyword.chars() {
+        letters.push(Box::new(OrGroup {
+            groups: vec![
+                Box::new(TextPattern {
+                    match_text: c.to_string()
+                }),
+                Box::new(TextPattern {
+                    match_text: c.to_ascii_uppercase().to_string()
+                }),
+            ]
+        }));
+    }
+    return Pattern {
+        label: "_sql_keyword".to_string(),
+        pattern_group: Box::new(AndGroup {
+            groups: letters
+        }),
+        skip: false,
+    };
+}
+
+fn build_sql_comment() -> Pattern {
+    return Pattern {
+        label: "_sql_comment".to_string(),
+        pattern_group: Box::new(AndGroup {
+            groups: vec![
+                Box::new(TextPattern {
+                    match_text: "--".to_string()
+                }),
+                rest_of_line(),
+            ]
+        }),
+        skip: true,
+    };
+}
+
+fn identifier_start() -> Box<dyn Group> {
+    return character_set(&[('a', 'z'), ('A', 'Z'), ('_', '_')]);
+}
+
+fn identifier_body() -> Box<dyn Group> {
+    return repeat(MatchRepeats::ZeroOrMore, character_set(&[('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')]));
+}
+
+// a reference to a dog variable, like :name
+fn build_sql_variable() -> Pattern {
+    return Pattern {
+        label: "_sql_variable".to_string(),
+        pattern_group: Box::new(AndGroup {
+            groups: vec![
+                character_set(&[(':', ':')]),
+                identifier_start(),
+                identifier_body(),
+            ]
+        }),
+        skip: false,
+    };
+}
+
+fn build_sql_identifier() -> Pattern {
+    return Pattern {
+        label: "_sql_identifier".to_string(),
+        pattern_group: Box::new(AndGroup {
+            groups: vec![
+                identifier_start(),
+                identifier_body(),
+            ]
+        }),
+        skip: false,
+    };
+}
+
+// "order", for names that are also keywords
+fn build_sql_quoted_identifier() -> Pattern {
+    return Pattern {
+        label: "_sql_identifier".to_string(),
+        pattern_group: Box::new(AndGroup {
+            groups: vec![
+                character_set(&[('"', '"')]),
+                repeat(MatchRepeats::ZeroOrMore, Box::new(NotGroup {
+                    group: character_set(&[('"', '"')]),
+                    length: 1,
+                })),
+                character_set(&[('"', '"')]),
+            ]
+        }),
+        skip: false,
+    };
+}
+
+// 'text', where a quote is escaped by doubling it: 'it''s' is read as the quoted pieces 'it' and 's'
+fn build_sql_string_literal() -> Pattern {
+    return Pattern {
+        label: "_sql_string_literal".to_string(),
+        pattern_group: repeat(MatchRepeats::OneOrMore, Box::new(AndGroup {
+            groups: vec![
+                character_set(&[('\'', '\'')]),
+                repeat(MatchRepeats::ZeroOrMore, Box::new(NotGroup {
+                    group: character_set(&[('\'', '\'')]),
+                    length: 1,
+                })),
+                character_set(&[('\'', '\'')]),
+            ]
+        })),
+        skip: false,
+    };
+}
+
+fn build_sql_number_literal() -> Pattern {
+    return Pattern {
+        label: "_sql_number_literal".to_string(),
+        pattern_group: Box::new(AndGroup {
+            groups: vec![
+                repeat(MatchRepeats::OneOrMore, character_set(&[('0', '9')])),
+                repeat(MatchRepeats::ZeroOrOne, Box::new(AndGroup {
+                    groups: vec![
+                        character_set(&[('.', '.')]),
+                        repeat(MatchRepeats::OneOrMore, character_set(&[('0', '9')])),
+                    ]
+                })),
+            ]
+        }),
+        skip: false,
+    };
+}
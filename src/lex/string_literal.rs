@@ -0,0 +1,358 @@
+// String literals can't be described with the other groups. They come in a few forms:
+//
+//   "text" or 'text'           escapes are decoded, `{expr}` is interpolated
+//   """text""" or '''text'''   same as above, but quotes don't need to be escaped
+//   r"text" or r"""text"""     raw: no escapes and no interpolation
+//
+// All forms can span multiple lines. Carriage returns are always dropped, so that newlines are
+// always represented as \n.
+//
+// The lexer matches a whole literal, including any interpolated expressions, as one token and
+// then splits interpolated literals into pieces so that the parser can see the expressions:
+//
+//   "a {x} b {y} c"  ->  _interpolated_string_start("a {), x, _interpolated_string_middle(} b {),
+//                        y, _interpolated_string_end(} c")
+//
+// Token values are always the original source text. Use `decode_string_token` to get the
+// actual value of a literal or one of its pieces.
+
+use crate::lex::dfa::{CharRanges, Nfa};
+use crate::lex::group::Group;
+
+pub const STRING_LITERAL: &str = "_string_literal";
+pub const INTERPOLATED_STRING_START: &str = "_interpolated_string_start";
+pub const INTERPOLATED_STRING_MIDDLE: &str = "_interpolated_string_middle";
+pub const INTERPOLATED_STRING_END: &str = "_interpolated_string_end";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiteralError {
+    pub message: String,
+    // in characters, relative to the start of the token
+    pub offset: usize,
+    pub length: usize,
+}
+
+pub struct StringLiteralGroup {}
+
+fn is_quote(c: char) -> bool {
+    return c == '"' || c == '\'';
+}
+
+// returns the quote character and how many of them make up the delimiter
+fn opening_delimiter(text: &[char], offset: usize) -> Option<(char, usize)> {
+    if offset >= text.len() || !is_quote(text[offset]) {
+        return None;
+    }
+    let quote = text[offset];
+    if offset + 2 < text.len() && text[offset + 1] == quote && text[offset + 2] == quote {
+        return Some((quote, 3));
+    }
+    return Some((quote, 1));
+}
+
+fn is_delimiter(text: &[char], offset: usize, quote: char, delimiter_length: usize) -> bool {
+    if offset + delimiter_length > text.len() {
+        return false;
+    }
+    return text[offset..offset + delimiter_length].iter().all(|c| *c == quote);
+}
+
+fn is_raw_prefix(text: &[char], offset: usize) -> bool {
+    return offset + 1 < text.len() && text[offset] == 'r' && is_quote(text[offset + 1]);
+}
+
+// Finds the length of the literal starting at offset, along with the character ranges (relative
+// to offset) of every interpolated expression, not including the braces.
+pub fn scan_string_literal(text: &[char], offset: usize) -> Option<(usize, Vec<(usize, usize)>)> {
+    let mut current_offset = offset;
+    let raw = is_raw_prefix(text, current_offset);
+    if raw {
+        current_offset += 1;
+    }
+    let (quote, delimiter_length) = opening_delimiter(text, current_offset)?;
+    current_offset += delimiter_length;
+    let mut expressions = vec![];
+    while current_offset < text.len() {
+        let c = text[current_offset];
+        if is_delimiter(text, current_offset, quote, delimiter_length) {
+            return Some((current_offset + delimiter_length - offset, expressions));
+        }
+        if !raw && c == '\\' {
+            // whether the escape is valid is checked when decoding
+            current_offset += escape_length(text, current_offset);
+        } else if !raw && c == '{' {
+            let expression_end = scan_interpolation(text, current_offset + 1)?;
+            expressions.push((current_offset + 1 - offset, expression_end - offset));
+            current_offset = expression_end + 1;
+        } else {
+            current_offset += 1;
+        }
+    }
+    // never closed
+    return None;
+}
+
+// the braces of a unicode escape don't start an interpolation
+fn escape_length(text: &[char], offset: usize) -> usize {
+    if offset + 2 < text.len() && text[offset + 1] == 'u' && text[offset + 2] == '{' {
+        if let Some(position) = text[offset + 3..].iter().position(|c| *c == '}' || is_quote(*c)) {
+            if text[offset + 3 + position] == '}' {
+                return position + 4;
+            }
+        }
+    }
+    return 2;
+}
+
+// returns the offset of the brace that closes the interpolation
+fn scan_interpolation(text: &[char], offset: usize) -> Option<usize> {
+    let mut depth = 1;
+    let mut current_offset = offset;
+    while current_offset < text.len() {
+        let c = text[current_offset];
+        let follows_word = current_offset > 0 && (text[current_offset - 1].is_alphanumeric() || text[current_offset - 1] == '_');
+        if is_quote(c) || (is_raw_prefix(text, current_offset) && !follows_word) {
+            let (length, _) = scan_string_literal(text, current_offset)?;
+            current_offset += length;
+            continue;
+        }
+        if c == '{' {
+            depth += 1;
+        } else if c == '}' {
+            depth -= 1;
+            if depth == 0 {
+                return Some(current_offset);
+            }
+        }
+        current_offset += 1;
+    }
+    return None;
+}
+
+impl Group for StringLiteralGroup {
+    fn match_with(&self, text: &[char], offset: usize) -> Option<u32> {
+        let (length, _) = scan_string_literal(text, offset)?;
+        return Some(length as u32);
+    }
+
+    fn contains_any(&self) -> bool {
+        return false;
+    }
+
+    fn name(&self) -> String {
+        return "StringLiteralGroup".to_string();
+    }
+
+    fn min_matches(&self) -> usize {
+        return 2;
+    }
+
+    fn render(&self) -> String {
+        return "string_literal".to_string();
+    }
+
+    fn build_nfa(&self, _nfa: &mut Nfa) -> Option<(usize, usize)> {
+        // interpolated expressions can hold strings that hold interpolated expressions...
+        return None;
+    }
+
+    fn single_char_ranges(&self) -> Option<CharRanges> {
+        return None;
+    }
+}
+
+// Decodes a `_string_literal` token, or one of the `_interpolated_string_*` pieces, into the
+// text it represents.
+pub fn decode_string_token(label: &str, value: &str) -> Result<String, LiteralError> {
+    let chars: Vec<char> = value.chars().collect();
+    let mut start = 0;
+    let mut end = chars.len();
+    let mut raw = false;
+    let mut delimiter_length = 1;
+
+    if label == INTERPOLATED_STRING_MIDDLE || label == INTERPOLATED_STRING_END {
+        // skip the `}` that closed the previous expression
+        start = 1;
+    } else {
+        raw = is_raw_prefix(&chars, 0);
+        if raw {
+            start = 1;
+        }
+        if let Some((_, opening_length)) = opening_delimiter(&chars, start) {
+            delimiter_length = opening_length;
+            start += opening_length;
+        }
+    }
+
+    if label == INTERPOLATED_STRING_START || label == INTERPOLATED_STRING_MIDDLE {
+        // skip the `{` that opens the next expression
+        end -= 1;
+    } else {
+        if label == INTERPOLATED_STRING_END {
+            // we don't know how the literal was opened, but only a triple quoted literal can end
+            // with three quotes
+            let quote = chars[end - 1];
+            delimiter_length = if end >= start + 3 && is_delimiter(&chars, end - 3, quote, 3) { 3 } else { 1 };
+        }
+        end -= delimiter_length;
+    }
+
+    if start > end {
+        return Err(LiteralError {
+            message: "String literal is not terminated".to_string(),
+            offset: 0,
+            length: chars.len(),
+        });
+    }
+
+    return decode_text(&chars[start..end], raw).map_err(|error| LiteralError {
+        message: error.message,
+        offset: error.offset + start,
+        length: error.length,
+    });
+}
+
+fn decode_text(chars: &[char], raw: bool) -> Result<String, LiteralError> {
+    let mut result = String::new();
+    let mut offset = 0;
+    while offset < chars.len() {
+        let c = chars[offset];
+        if c == '\r' {
+            offset += 1;
+            continue;
+        }
+        if raw || c != '\\' {
+            result.push(c);
+            offset += 1;
+            continue;
+        }
+        if offset + 1 >= chars.len() {
+            return Err(LiteralError {
+                message: "Escape sequence is not complete".to_string(),
+                offset,
+                length: 1,
+            });
+        }
+        let escaped = chars[offset + 1];
+        let decoded = match escaped {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' | '"' | '\'' | '{' | '}' | '`' => escaped,
+            'u' => {
+                let (decoded, length) = decode_unicode_escape(chars, offset)?;
+                result.push(decoded);
+                offset += length;
+                continue;
+            }
+            _ => {
+                return Err(LiteralError {
+                    message: format!("Unknown escape sequence `\\{}`", escaped),
+                    offset,
+                    length: 2,
+                });
+            }
+        };
+        result.push(decoded);
+        offset += 2;
+    }
+    return Ok(result);
+}
+
+// decodes \u{1F600}, returning the character and the length of the escape sequence
+fn decode_unicode_escape(chars: &[char], offset: usize) -> Result<(char, usize), LiteralError> {
+    let digits_start = offset + 3;
+    if digits_start > chars.len() || chars[offset + 2] != '{' {
+        return Err(LiteralError {
+            message: "Expected `{` after `\\u`".to_string(),
+            offset,
+            length: 2,
+        });
+    }
+    let closing = chars[digits_start..].iter().position(|c| *c == '}');
+    if closing.is_none() {
+        return Err(LiteralError {
+            message: "Unicode escape is missing its closing `}`".to_string(),
+            offset,
+            length: chars.len() - offset,
+        });
+    }
+    let digits_end = digits_start + closing.unwrap();
+    let length = digits_end + 1 - offset;
+    let digits: String = chars[digits_start..digits_end].iter().collect();
+    if digits.is_empty() || digits.len() > 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(LiteralError {
+            message: format!("Invalid unicode escape `\\u{{{}}}`: expected 1 to 6 hex digits", digits),
+            offset,
+            length,
+        });
+    }
+    let code_point = u32::from_str_radix(&digits, 16).unwrap();
+    if let Some(decoded) = std::char::from_u32(code_point) {
+        return Ok((decoded, length));
+    }
+    return Err(LiteralError {
+        message: format!("Invalid unicode escape `\\u{{{}}}`: not a unicode scalar value", digits),
+        offset,
+        length,
+    });
+}
+
+#[cfg(test)]
+mod string_literal_tests {
+    use crate::lex::string_literal::*;
+
+    fn scan(text: &str) -> Option<(usize, Vec<(usize, usize)>)> {
+        let chars: Vec<char> = text.chars().collect();
+        return scan_string_literal(&chars, 0);
+    }
+
+    #[test]
+    fn scan_forms() {
+        assert_eq!(Some((6, vec![])), scan("\"a\\\"b\" tail"));
+        assert_eq!(Some((4, vec![])), scan("'\"\"' tail"));
+        assert_eq!(Some((9, vec![])), scan("\"\"\"a\"b\"\"\" tail"));
+        assert_eq!(Some((5, vec![])), scan("r\"a\\\" tail"));
+        assert_eq!(Some((8, vec![(4, 5)])), scan("\"a {x} \""));
+        assert_eq!(Some((10, vec![(2, 8)])), scan("'{m[\"}\"]}'"));
+        assert_eq!(None, scan("\"never closed"));
+        assert_eq!(None, scan("\"{never closed\""));
+        assert_eq!(None, scan("r"));
+        assert_eq!(Some((11, vec![])), scan("'\\u{1F600}'"));
+    }
+
+    #[test]
+    fn decode_escapes() {
+        assert_eq!(Ok("a\n\t\"'\\{}\u{1F600}".to_string()),
+                   decode_string_token(STRING_LITERAL, "\"a\\n\\t\\\"\\'\\\\\\{\\}\\u{1F600}\""));
+        assert_eq!(Ok("line 1\nline 2".to_string()), decode_string_token(STRING_LITERAL, "'line 1\r\nline 2'"));
+        assert_eq!(Ok("C:\\path\\{x}".to_string()), decode_string_token(STRING_LITERAL, "r\"C:\\path\\{x}\""));
+        assert_eq!(Ok("say \"hi\"".to_string()), decode_string_token(STRING_LITERAL, "\"\"\"say \"hi\"\"\"\""));
+    }
+
+    #[test]
+    fn decode_interpolated_pieces() {
+        assert_eq!(Ok("a ".to_string()), decode_string_token(INTERPOLATED_STRING_START, "\"a {"));
+        assert_eq!(Ok(" b ".to_string()), decode_string_token(INTERPOLATED_STRING_MIDDLE, "} b {"));
+        assert_eq!(Ok(" c".to_string()), decode_string_token(INTERPOLATED_STRING_END, "} c\""));
+        assert_eq!(Ok(" \"c\"".to_string()), decode_string_token(INTERPOLATED_STRING_END, "} \"c\"\"\"\""));
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(Err(LiteralError {
+            message: "Unknown escape sequence `\\q`".to_string(),
+            offset: 3,
+            length: 2,
+        }), decode_string_token(STRING_LITERAL, "\"ab\\q\""));
+        assert_eq!(Err(LiteralError {
+            message: "Invalid unicode escape `\\u{110000}`: not a unicode scalar value".to_string(),
+            offset: 1,
+            length: 10,
+        }), decode_string_token(STRING_LITERAL, "'\\u{110000}'"));
+        assert_eq!("Expected `{` after `\\u`", decode_string_token(STRING_LITERAL, "'\\u0041'").unwrap_err().message);
+        assert_eq!("Unicode escape is missing its closing `}`", decode_string_token(STRING_LITERAL, "'\\u{41'").unwrap_err().message);
+        assert_eq!("Invalid unicode escape `\\u{}`: expected 1 to 6 hex digits", decode_string_token(STRING_LITERAL, "'\\u{}'").unwrap_err().message);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::lex::dfa::{CharRanges, Nfa};
 use crate::lex::group::Group;
 
 pub struct TextPattern {
@@ -27,6 +28,10 @@ impl Group for TextPattern {
     fn name(&self) -> String {
         return "TextPattern".to_string();
     }
+
+    fn fixed_text(&self) -> Option<String> {
+        return Some(self.match_text.clone());
+    }
     fn min_matches(&self) -> usize {
         return self.match_text.len();
     }
@@ -37,4 +42,25 @@ impl Group for TextPattern {
         result += "'";
         return result;
     }
+
+    fn build_nfa(&self, nfa: &mut Nfa) -> Option<(usize, usize)> {
+        let start = nfa.add_state();
+        let mut end = start;
+        for c in self.match_text.chars() {
+            let next = nfa.add_state();
+            nfa.add_transition(end, c as u32, c as u32, next);
+            end = next;
+        }
+        return Some((start, end));
+    }
+
+    fn single_char_ranges(&self) -> Option<CharRanges> {
+        let mut chars = self.match_text.chars();
+        if let Some(c) = chars.next() {
+            if chars.next().is_none() {
+                return Some(vec![(c as u32, c as u32)]);
+            }
+        }
+        return None;
+    }
 }
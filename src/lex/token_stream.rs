@@ -1,4 +1,6 @@
-#[derive(Debug)]
+use crate::source::FileId;
+
+#[derive(Debug, PartialEq)]
 pub struct TokenStream {
     pub matches: Vec<Box<Match>>,
     pub offset: usize,
@@ -6,16 +8,31 @@ pub struct TokenStream {
     pub last_consumed_offset: Option<usize>,
 }
 
-#[derive(Debug, Clone)]
+// Where a token stream is, so that it can go back there. Unlike an offset passed to `reset`, this
+// tells the start of the stream apart from having read the first token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StreamPosition {
+    offset: usize,
+    fresh: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Match {
     pub length: usize,
     pub label: String,
     pub value: String,
     pub skip: bool,
+    // where the token starts. both start at 1.
     pub line_number: usize,
     pub line_offset: usize,
-    pub file_name: Option<String>,
-    pub file_path: Option<String>,
+    // where the token ends. this is the position just past the last character, so a token that
+    // ends with a newline ends at offset 1 of the next line.
+    pub end_line_number: usize,
+    pub end_line_offset: usize,
+    // byte offsets into the source text, the end is exclusive
+    pub start_byte_offset: usize,
+    pub end_byte_offset: usize,
+    pub file_id: Option<FileId>,
 }
 
 impl TokenStream {
@@ -38,6 +55,11 @@ impl TokenStream {
         return None;
     }
 
+    // where the next token that isn't skipped is in the stream
+    pub fn next_offset(&self) -> Option<usize> {
+        return self.find_next_offset();
+    }
+
     #[allow(dead_code)]
     pub fn has_next(&self) -> bool {
         return self.find_next_offset().is_some();
@@ -86,6 +108,18 @@ impl TokenStream {
         self.offset = offset;
     }
 
+    pub fn position(&self) -> StreamPosition {
+        return StreamPosition {
+            offset: self.offset,
+            fresh: self.fresh,
+        };
+    }
+
+    pub fn restore(&mut self, position: StreamPosition) {
+        self.offset = position.offset;
+        self.fresh = position.fresh;
+    }
+
     #[allow(dead_code)]
     pub fn peek(&self, offset: usize) -> Option<&Match> {
         if self.matches.is_empty() || offset >= self.matches.len() {
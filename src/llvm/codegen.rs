@@ -0,0 +1,1501 @@
+// Lowers the syntax tree into an llvm module, one llvm function for each dog function.
+//
+// The compiler doesn't have the analyzer yet, so the types are worked out here, and only what
+// can be lowered without it is: functions of ints, floats, bools, chars and strings, with let,
+// assignment, if, while, for over a range, return and fail. Anything else, like structs or
+// `otherwise`, is an error that says it can't be compiled yet.
+//
+// A function doesn't say what it returns, so that comes from its first return statement whose
+// type can be told. A function without one returns nothing. The return of a function that calls
+// another function is only known once that function's is, so the functions are gone over until
+// no more of them become known.
+//
+// Strings are null terminated, and are printed and formatted with the c library's printf family.
+// The llvm functions are named by dog. and the modules they're in, like dog.math.double, so that
+// they don't clash with the c library, with each other or with main, which calls the entry point
+// and returns the int that it returns, if it does. A call is to the function of that name in the
+// caller's module, or else in the modules around it, the innermost first: `double` in math::more
+// is math::more::double, math::double or double, and `more::double` in math is math::more::double
+// or more::double. A test
+// harness has a main of its own instead, that runs the test its first argument names. Test mods
+// are only lowered into a test harness.
+//
+// config.a.b is a constant: the value that the config being built has for it goes right into the
+// code, see config/mod.rs.
+//
+// With debug info, each function gets a subprogram and each statement the line it's on, see
+// debug_info.rs.
+
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::ptr;
+
+use llvm_sys::{LLVMIntPredicate, LLVMRealPredicate};
+use llvm_sys::core::*;
+use llvm_sys::prelude::*;
+
+use crate::config::{Config, Value, ValueKind};
+use crate::lex::number_literal::NumberValue;
+use crate::llvm::codegen_error::CodegenError;
+use crate::llvm::debug_info::DebugInfo;
+use crate::source::{SourceMap, Span};
+use crate::transform::ast::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueType {
+    Int,
+    Float,
+    Bool,
+    Char,
+    Text,
+    Nothing,
+}
+
+impl ValueType {
+    fn name(&self) -> &'static str {
+        return match self {
+            ValueType::Int => "int",
+            ValueType::Float => "float",
+            ValueType::Bool => "bool",
+            ValueType::Char => "char",
+            ValueType::Text => "string",
+            ValueType::Nothing => "nothing",
+        };
+    }
+}
+
+struct Signature {
+    params: Vec<ValueType>,
+    // the default value of each param that has one
+    defaults: Vec<Option<Expr>>,
+    // none until it's known
+    returns: Option<ValueType>,
+}
+
+struct Variable {
+    // none while working out what functions return
+    pointer: Option<LLVMValueRef>,
+    // none when the value's type can't be told yet
+    value_type: Option<ValueType>,
+}
+
+pub struct Codegen<'a> {
+    source_map: &'a SourceMap,
+    // the values of config.a.b in the code
+    config: &'a Config,
+    context: LLVMContextRef,
+    module: LLVMModuleRef,
+    builder: LLVMBuilderRef,
+    // by the function's name and the modules it's in, like math::double
+    signatures: HashMap<String, Signature>,
+    functions: HashMap<String, LLVMValueRef>,
+    // the modules that the function being lowered is in, where its calls are looked up from
+    current_module: Vec<String>,
+    // the variables of each block that is being lowered, the innermost last
+    scopes: Vec<HashMap<String, Variable>>,
+    // the llvm function being lowered and what it returns
+    current: Option<(LLVMValueRef, ValueType)>,
+    emit_debug_info: bool,
+    // made by lower when there's debug info to emit
+    debug_info: Option<DebugInfo>,
+}
+
+impl<'a> Drop for Codegen<'a> {
+    fn drop(&mut self) {
+        // the debug info builder refers to the module, so it goes first
+        self.debug_info = None;
+        unsafe {
+            LLVMDisposeBuilder(self.builder);
+            LLVMDisposeModule(self.module);
+            LLVMContextDispose(self.context);
+        }
+    }
+}
+
+// the functions of a module and of the modules in it, and of the test mods in it when asked for,
+// each with the path of the modules it's in
+pub fn collect_functions<'m>(module: &'m Module, path: &[String], test_mods: bool, result: &mut Vec<(Vec<String>, &'m FnDecl)>) {
+    let mut path = path.to_vec();
+    path.extend(module.name.iter().map(|name| name.text.clone()));
+    for item in &module.items {
+        if let Item::Function(function) = item {
+            result.push((path.clone(), function));
+        }
+        if let Item::Module(module) = item {
+            if test_mods || !module.test {
+                collect_functions(module, &path, test_mods, result);
+            }
+        }
+    }
+}
+
+// math::double, what a function is found by
+fn qualified_name(module: &[String], name: &str) -> String {
+    let mut path = module.to_vec();
+    path.push(name.to_string());
+    return path.join("::");
+}
+
+// dog.math.double, what llvm and the linker know the function as
+fn symbol_name(qualified_name: &str) -> String {
+    return format!("dog.{}", qualified_name.replace("::", "."));
+}
+
+impl<'a> Codegen<'a> {
+    pub fn new(source_map: &'a SourceMap, config: &'a Config, name: &str, emit_debug_info: bool) -> Codegen<'a> {
+        let name = CString::new(name).unwrap_or_default();
+        unsafe {
+            let context = LLVMContextCreate();
+            return Codegen {
+                source_map,
+                config,
+                context,
+                module: LLVMModuleCreateWithNameInContext(name.as_ptr(), context),
+                builder: LLVMCreateBuilderInContext(context),
+                signatures: HashMap::new(),
+                functions: HashMap::new(),
+                current_module: vec![],
+                scopes: vec![],
+                current: None,
+                emit_debug_info,
+                debug_info: None,
+            };
+        }
+    }
+
+    pub fn module(&self) -> LLVMModuleRef {
+        return self.module;
+    }
+
+    // lowers every function of the modules, and a main that calls the entry point
+    pub fn lower(&mut self, modules: &[&Module], entry: &FnDecl) -> Result<(), CodegenError> {
+        let functions = self.lower_functions(modules, false, &entry.span)?;
+        let entry_name = match functions.iter().find(|(_, function)| ptr::eq(*function, entry)) {
+            Some((module, _)) => qualified_name(module, &entry.name.text),
+            None => return Err(self.error(entry.name.span, format!("fn {} isn't in the modules", entry.name.text))),
+        };
+        self.lower_main(entry, &entry_name)?;
+        self.finalize_debug_info();
+        return Ok(());
+    }
+
+    // lowers every function of the modules, and a main that runs one of the tests by its name
+    pub fn lower_tests(&mut self, modules: &[&Module], tests: &[(String, &FnDecl)]) -> Result<(), CodegenError> {
+        let span = match tests.first() {
+            Some((_, test)) => test.span,
+            None => return Ok(()),
+        };
+        self.lower_functions(modules, true, &span)?;
+        self.lower_test_main(tests)?;
+        self.finalize_debug_info();
+        return Ok(());
+    }
+
+    // the compile unit of the debug info is in the file of the span
+    fn lower_functions<'m>(&mut self, modules: &[&'m Module], test_mods: bool, span: &Span) -> Result<Vec<(Vec<String>, &'m FnDecl)>, CodegenError> {
+        let mut functions = vec![];
+        for module in modules {
+            self.check_items(module, test_mods)?;
+            collect_functions(module, &[], test_mods, &mut functions);
+        }
+        for (module, function) in &functions {
+            let name = qualified_name(module, &function.name.text);
+            if self.signatures.contains_key(&name) {
+                return Err(self.error(function.name.span, format!("fn {} is declared more than once", name)));
+            }
+            if !function.generics.is_empty() {
+                return Err(self.unsupported(function.span, "generic functions"));
+            }
+            let mut params = vec![];
+            for param in &function.params {
+                params.push(self.value_type(&param.data_type)?);
+            }
+            let defaults = function.params.iter().map(|param| param.default.clone()).collect();
+            self.signatures.insert(name, Signature { params, defaults, returns: None });
+        }
+        self.infer_return_types(&functions)?;
+
+        if self.emit_debug_info {
+            self.debug_info = Some(DebugInfo::new(self.context, self.module, self.source_map, span));
+        }
+        for (module, function) in &functions {
+            self.declare_function(module, function);
+        }
+        for (module, function) in &functions {
+            self.lower_function(module, function)?;
+        }
+        return Ok(functions);
+    }
+
+    fn finalize_debug_info(&self) {
+        if let Some(debug_info) = &self.debug_info {
+            debug_info.finalize();
+        }
+    }
+
+    fn error(&self, span: Span, message: String) -> CodegenError {
+        return CodegenError {
+            message,
+            file_name: self.source_map.name(span.file_id).to_string(),
+            span: Some(span),
+        };
+    }
+
+    fn unsupported(&self, span: Span, what: &str) -> CodegenError {
+        return self.error(span, format!("{} can't be compiled yet", what));
+    }
+
+    fn check_items(&self, module: &Module, test_mods: bool) -> Result<(), CodegenError> {
+        for item in &module.items {
+            match item {
+                Item::Function(_) => {}
+                Item::Module(module) if test_mods || !module.test => self.check_items(module, test_mods)?,
+                Item::Module(_) => {}
+                Item::Struct(decl) => return Err(self.unsupported(decl.span, "structs")),
+                Item::Enum(decl) => return Err(self.unsupported(decl.span, "enums")),
+                Item::Trait(decl) => return Err(self.unsupported(decl.span, "traits")),
+                Item::Impl(decl) => return Err(self.unsupported(decl.span, "impls")),
+                Item::Attribute(decl) => return Err(self.unsupported(decl.span, "attributes")),
+            }
+        }
+        return Ok(());
+    }
+
+    fn value_type(&self, data_type: &Type) -> Result<ValueType, CodegenError> {
+        return match &data_type.kind {
+            TypeKind::Integer => Ok(ValueType::Int),
+            TypeKind::Float => Ok(ValueType::Float),
+            TypeKind::Boolean => Ok(ValueType::Bool),
+            TypeKind::Character => Ok(ValueType::Char),
+            TypeKind::Named { path, generics } if path.len() == 1 && path[0].text == "string" && generics.is_empty() => Ok(ValueType::Text),
+            TypeKind::Named { .. } => Err(self.unsupported(data_type.span, "user types")),
+            TypeKind::Array(_) => Err(self.unsupported(data_type.span, "arrays")),
+        };
+    }
+
+    fn llvm_type(&self, value_type: ValueType) -> LLVMTypeRef {
+        unsafe {
+            return match value_type {
+                ValueType::Int => LLVMInt64TypeInContext(self.context),
+                ValueType::Float => LLVMDoubleTypeInContext(self.context),
+                ValueType::Bool => LLVMInt1TypeInContext(self.context),
+                ValueType::Char => LLVMInt32TypeInContext(self.context),
+                ValueType::Text => LLVMPointerType(LLVMInt8TypeInContext(self.context), 0),
+                ValueType::Nothing => LLVMVoidTypeInContext(self.context),
+            };
+        }
+    }
+
+    // variables
+
+    fn lookup(&self, name: &Name) -> Result<&Variable, CodegenError> {
+        return match self.scopes.iter().rev().find_map(|scope| scope.get(&name.text)) {
+            Some(variable) => Ok(variable),
+            None => Err(self.error(name.span, format!("{} isn't declared", name.text))),
+        };
+    }
+
+    fn declare_variable(&mut self, name: &Name, value_type: Option<ValueType>) -> Option<LLVMValueRef> {
+        let pointer = match (self.current, value_type) {
+            (Some((function, _)), Some(value_type)) => Some(self.entry_alloca(function, value_type, &name.text)),
+            _ => None,
+        };
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.text.clone(), Variable { pointer, value_type });
+        }
+        return pointer;
+    }
+
+    // variables live at the start of the function, so that a loop doesn't make a new one each time around
+    fn entry_alloca(&self, function: LLVMValueRef, value_type: ValueType, name: &str) -> LLVMValueRef {
+        let name = CString::new(name).unwrap_or_default();
+        unsafe {
+            let builder = LLVMCreateBuilderInContext(self.context);
+            let entry = LLVMGetEntryBasicBlock(function);
+            let first = LLVMGetFirstInstruction(entry);
+            if first.is_null() {
+                LLVMPositionBuilderAtEnd(builder, entry);
+            } else {
+                LLVMPositionBuilderBefore(builder, first);
+            }
+            let result = LLVMBuildAlloca(builder, self.llvm_type(value_type), name.as_ptr());
+            LLVMDisposeBuilder(builder);
+            return result;
+        }
+    }
+
+    // types
+
+    fn infer_return_types(&mut self, functions: &[(Vec<String>, &FnDecl)]) -> Result<(), CodegenError> {
+        let mut unknown: Vec<&(Vec<String>, &FnDecl)> = functions.iter().collect();
+        while !unknown.is_empty() {
+            let mut still_unknown = vec![];
+            for function in &unknown {
+                let (module, decl) = function;
+                match self.return_type(module, decl) {
+                    Ok(returns) => self.signatures.get_mut(&qualified_name(module, &decl.name.text)).unwrap().returns = Some(returns),
+                    Err(_) => still_unknown.push(*function),
+                }
+            }
+            if still_unknown.len() == unknown.len() {
+                let (module, decl) = still_unknown[0];
+                return Err(self.return_type(module, decl).unwrap_err());
+            }
+            unknown = still_unknown;
+        }
+        return Ok(());
+    }
+
+    // the type of the first return that can be told, or why none could be
+    fn return_type(&mut self, module: &[String], function: &FnDecl) -> Result<ValueType, CodegenError> {
+        let body = match &function.body {
+            Some(body) => body,
+            None => return Ok(ValueType::Nothing),
+        };
+        self.current_module = module.to_vec();
+        self.scopes.push(HashMap::new());
+        for (param, value_type) in function.params.iter().zip(self.signatures[&qualified_name(module, &function.name.text)].params.clone()) {
+            self.declare_variable(&param.name, Some(value_type));
+        }
+        let mut first_error = None;
+        let result = self.block_return_type(body, &mut first_error);
+        self.scopes.pop();
+        return match (result, first_error) {
+            (Some(returns), _) => Ok(returns),
+            (None, Some(error)) => Err(error),
+            (None, None) => Ok(ValueType::Nothing),
+        };
+    }
+
+    fn block_return_type(&mut self, block: &Block, first_error: &mut Option<CodegenError>) -> Option<ValueType> {
+        self.scopes.push(HashMap::new());
+        let mut result = None;
+        for statement in &block.statements {
+            result = match &statement.kind {
+                StmtKind::Return(value) => match self.type_of(value) {
+                    Ok(value_type) => Some(value_type),
+                    Err(error) => {
+                        first_error.get_or_insert(error);
+                        None
+                    }
+                },
+                StmtKind::Let { name, data_type, value } => {
+                    let value_type = match (data_type, value) {
+                        (Some(data_type), _) => self.value_type(data_type).ok(),
+                        (None, Some(value)) => self.type_of(value).ok(),
+                        (None, None) => None,
+                    };
+                    self.declare_variable(name, value_type);
+                    None
+                }
+                StmtKind::Block(body) | StmtKind::While { body, .. } | StmtKind::If { body, .. } => self.block_return_type(body, first_error),
+                StmtKind::For { variable, body, .. } => {
+                    self.scopes.push(HashMap::new());
+                    self.declare_variable(variable, Some(ValueType::Int));
+                    let result = self.block_return_type(body, first_error);
+                    self.scopes.pop();
+                    result
+                }
+                StmtKind::Assign { .. } | StmtKind::Expr(_) | StmtKind::Fail(_) => None,
+            };
+            if result.is_some() {
+                break;
+            }
+        }
+        self.scopes.pop();
+        return result;
+    }
+
+    fn type_of(&self, expr: &Expr) -> Result<ValueType, CodegenError> {
+        return match &expr.kind {
+            ExprKind::Identifier(path) => {
+                let name = self.variable_name(expr, path)?;
+                match self.lookup(name)?.value_type {
+                    Some(value_type) => Ok(value_type),
+                    None => Err(self.error(name.span, format!("can't tell what type {} is", name.text))),
+                }
+            }
+            ExprKind::Literal(literal) => self.literal_type(expr, literal),
+            ExprKind::Binary { operator, left, right } => self.binary_type(expr.span, *operator, self.type_of(left)?, self.type_of(right)?),
+            ExprKind::Unary { operator, operand } => self.unary_type(expr.span, *operator, self.type_of(operand)?),
+            ExprKind::Cast { value, data_type } => {
+                let to = self.value_type(data_type)?;
+                self.cast_type(expr.span, self.type_of(value)?, to)
+            }
+            ExprKind::Call { function, .. } => {
+                let path = self.function_path(function)?;
+                if is_builtin(&path) {
+                    return Ok(ValueType::Nothing);
+                }
+                let name = self.resolve_function(&path)?;
+                match self.signatures[&name].returns {
+                    Some(returns) => Ok(returns),
+                    None => Err(self.error(path[0].span, format!("can't tell what {} returns", display_path(&path)))),
+                }
+            }
+            ExprKind::InterpolatedString(_) => Ok(ValueType::Text),
+            ExprKind::Range { .. } => Err(self.error(expr.span, "a range can only be used in a for loop".to_string())),
+            ExprKind::Config | ExprKind::Member { .. } => self.config_type(expr, self.config_value(expr)?),
+            ExprKind::StructConstructor { .. } => Err(self.unsupported(expr.span, "structs")),
+        };
+    }
+
+    fn literal_type(&self, expr: &Expr, literal: &Literal) -> Result<ValueType, CodegenError> {
+        return match literal {
+            Literal::String(_) => Ok(ValueType::Text),
+            Literal::Number(number) => match number.value {
+                NumberValue::Integer(_) if !number.suffix.is_some_and(|suffix| suffix.is_float()) => Ok(ValueType::Int),
+                _ => Ok(ValueType::Float),
+            },
+            Literal::Boolean(_) => Ok(ValueType::Bool),
+            Literal::Null => Err(self.unsupported(expr.span, "null")),
+        };
+    }
+
+    fn binary_type(&self, span: Span, operator: BinaryOperator, left: ValueType, right: ValueType) -> Result<ValueType, CodegenError> {
+        let (verb, allowed): (&str, &[ValueType]) = match operator {
+            BinaryOperator::Add => ("add", &[ValueType::Int, ValueType::Float, ValueType::Text]),
+            BinaryOperator::Subtract => ("subtract", &[ValueType::Int, ValueType::Float]),
+            BinaryOperator::Multiply => ("multiply", &[ValueType::Int, ValueType::Float]),
+            BinaryOperator::Divide => ("divide", &[ValueType::Int, ValueType::Float]),
+            BinaryOperator::Equal | BinaryOperator::NotEqual => ("compare", &[ValueType::Int, ValueType::Float, ValueType::Bool, ValueType::Char, ValueType::Text]),
+            _ => ("compare", &[ValueType::Int, ValueType::Float, ValueType::Char, ValueType::Text]),
+        };
+        if left != right || !allowed.contains(&left) {
+            return Err(self.error(span, format!("can't {} {} and {}", verb, left.name(), right.name())));
+        }
+        return match operator {
+            BinaryOperator::Add | BinaryOperator::Subtract | BinaryOperator::Multiply | BinaryOperator::Divide => Ok(left),
+            _ => Ok(ValueType::Bool),
+        };
+    }
+
+    fn unary_type(&self, span: Span, operator: UnaryOperator, operand: ValueType) -> Result<ValueType, CodegenError> {
+        return match (operator, operand) {
+            (UnaryOperator::Not, ValueType::Bool) => Ok(ValueType::Bool),
+            (UnaryOperator::Negate, ValueType::Int) | (UnaryOperator::Negate, ValueType::Float) => Ok(operand),
+            (UnaryOperator::Not, _) => Err(self.error(span, format!("can't use ! on {}", operand.name()))),
+            (UnaryOperator::Negate, _) => Err(self.error(span, format!("can't negate {}", operand.name()))),
+        };
+    }
+
+    fn cast_type(&self, span: Span, from: ValueType, to: ValueType) -> Result<ValueType, CodegenError> {
+        let allowed = from == to || to == ValueType::Text && from != ValueType::Nothing || matches!((from, to),
+            (ValueType::Int, ValueType::Float) | (ValueType::Float, ValueType::Int) | (ValueType::Int, ValueType::Char) |
+            (ValueType::Char, ValueType::Int) | (ValueType::Bool, ValueType::Int));
+        if !allowed {
+            return Err(self.error(span, format!("can't cast {} to {}", from.name(), to.name())));
+        }
+        return Ok(to);
+    }
+
+    // the value of config.a.b, which is a constant
+    fn config_value(&self, expr: &Expr) -> Result<&'a Value, CodegenError> {
+        let mut path = vec![];
+        let mut object = expr;
+        while let ExprKind::Member { object: inner, member, .. } = &object.kind {
+            path.insert(0, member.text.as_str());
+            object = inner;
+        }
+        if object.kind != ExprKind::Config {
+            return Err(self.unsupported(expr.span, "members"));
+        }
+        if path.is_empty() {
+            return Err(self.error(expr.span, "expected a value of the config, like config.version".to_string()));
+        }
+        return match self.config.get(&path) {
+            Some(value) => Ok(value),
+            None => Err(self.error(expr.span, format!("config {} has no {}", self.config.name, path.join(".")))),
+        };
+    }
+
+    fn config_type(&self, expr: &Expr, value: &Value) -> Result<ValueType, CodegenError> {
+        return match value.kind {
+            ValueKind::String(_) => Ok(ValueType::Text),
+            ValueKind::Integer(_) => Ok(ValueType::Int),
+            ValueKind::Float(_) => Ok(ValueType::Float),
+            ValueKind::Boolean(_) => Ok(ValueType::Bool),
+            _ => Err(self.unsupported(expr.span, &format!("config values that are {}", value.kind.name()))),
+        };
+    }
+
+    fn variable_name<'e>(&self, expr: &Expr, path: &'e [Name]) -> Result<&'e Name, CodegenError> {
+        if path.len() != 1 {
+            return Err(self.unsupported(expr.span, "paths like a::b"));
+        }
+        return Ok(&path[0]);
+    }
+
+    // a::b is a constant member of a in an expression, which is the same path for a function
+    fn function_path(&self, function: &Expr) -> Result<Vec<Name>, CodegenError> {
+        return match &function.kind {
+            ExprKind::Identifier(path) => Ok(path.clone()),
+            ExprKind::Member { object, member, constant: true } => {
+                let mut path = self.function_path(object)?;
+                path.push(member.clone());
+                Ok(path)
+            }
+            _ => Err(self.unsupported(function.span, "calling anything but a function by name")),
+        };
+    }
+
+    // the qualified name of the function that a call from the current module means
+    fn resolve_function(&self, path: &[Name]) -> Result<String, CodegenError> {
+        let called: Vec<String> = path.iter().map(|name| name.text.clone()).collect();
+        for depth in (0..=self.current_module.len()).rev() {
+            let mut candidate = self.current_module[..depth].to_vec();
+            candidate.extend(called.iter().cloned());
+            let candidate = candidate.join("::");
+            if self.signatures.contains_key(&candidate) {
+                return Ok(candidate);
+            }
+        }
+        return Err(self.error(path[0].span, format!("fn {} isn't declared", display_path(path))));
+    }
+
+    // functions
+
+    fn declare_function(&mut self, module: &[String], function: &FnDecl) {
+        let name = qualified_name(module, &function.name.text);
+        let signature = &self.signatures[&name];
+        let mut params: Vec<LLVMTypeRef> = signature.params.iter().map(|param| self.llvm_type(*param)).collect();
+        let returns = self.llvm_type(signature.returns.unwrap_or(ValueType::Nothing));
+        let symbol = CString::new(symbol_name(&name)).unwrap_or_default();
+        unsafe {
+            let function_type = LLVMFunctionType(returns, params.as_mut_ptr(), params.len() as u32, 0);
+            let value = LLVMAddFunction(self.module, symbol.as_ptr(), function_type);
+            self.functions.insert(name, value);
+        }
+    }
+
+    fn lower_function(&mut self, module: &[String], function: &FnDecl) -> Result<(), CodegenError> {
+        let body = match &function.body {
+            Some(body) => body,
+            None => return Ok(()),
+        };
+        let name = qualified_name(module, &function.name.text);
+        let value = self.functions[&name];
+        let returns = self.signatures[&name].returns.unwrap_or(ValueType::Nothing);
+        let param_types = self.signatures[&name].params.clone();
+        self.current = Some((value, returns));
+        self.current_module = module.to_vec();
+        unsafe {
+            let entry = LLVMAppendBasicBlockInContext(self.context, value, c_str!("entry"));
+            LLVMPositionBuilderAtEnd(self.builder, entry);
+        }
+        if let Some(debug_info) = &mut self.debug_info {
+            debug_info.start_function(self.source_map, value, &function.name.text, &symbol_name(&name), &function.span);
+        }
+        self.scopes.push(HashMap::new());
+        for (index, (param, value_type)) in function.params.iter().zip(param_types).enumerate() {
+            let pointer = self.declare_variable(&param.name, Some(value_type)).unwrap();
+            unsafe {
+                LLVMBuildStore(self.builder, LLVMGetParam(value, index as u32), pointer);
+            }
+        }
+        let result = self.block(body);
+        self.scopes.pop();
+        result?;
+
+        // falling off the end of a function that returns something returns zero
+        if !self.terminated() {
+            unsafe {
+                if returns == ValueType::Nothing {
+                    LLVMBuildRetVoid(self.builder);
+                } else {
+                    LLVMBuildRet(self.builder, LLVMConstNull(self.llvm_type(returns)));
+                }
+            }
+        }
+        if let Some(debug_info) = &mut self.debug_info {
+            debug_info.end_function(self.builder);
+        }
+        self.current = None;
+        return Ok(());
+    }
+
+    fn lower_main(&mut self, entry: &FnDecl, entry_name: &str) -> Result<(), CodegenError> {
+        if !entry.params.is_empty() {
+            return Err(self.unsupported(entry.span, "entry points with parameters"));
+        }
+        let function = self.functions[entry_name];
+        let returns = self.signatures[entry_name].returns.unwrap_or(ValueType::Nothing);
+        unsafe {
+            let int_type = LLVMInt32TypeInContext(self.context);
+            let main_type = LLVMFunctionType(int_type, ptr::null_mut(), 0, 0);
+            let main = LLVMAddFunction(self.module, c_str!("main"), main_type);
+            let block = LLVMAppendBasicBlockInContext(self.context, main, c_str!("entry"));
+            LLVMPositionBuilderAtEnd(self.builder, block);
+            let result = LLVMBuildCall(self.builder, function, ptr::null_mut(), 0, c_str!(""));
+            if returns == ValueType::Int {
+                LLVMBuildRet(self.builder, LLVMBuildTrunc(self.builder, result, int_type, c_str!("exit_code")));
+            } else {
+                LLVMBuildRet(self.builder, LLVMConstInt(int_type, 0, 0));
+            }
+        }
+        return Ok(());
+    }
+
+    // main(argc, argv) returns 0 once the test that argv[1] names has run, and 2 when there's no
+    // such test. a test that fails exits with 1 before that, see fail(). the tests are named by
+    // their modules, like math_tests::adds, which is also what their functions are found by.
+    fn lower_test_main(&mut self, tests: &[(String, &FnDecl)]) -> Result<(), CodegenError> {
+        if let Some((_, test)) = tests.iter().find(|(_, test)| !test.params.is_empty()) {
+            return Err(self.unsupported(test.span, "tests with parameters"));
+        }
+        if let Some((test_name, test)) = tests.iter().find(|(test_name, _)| !self.functions.contains_key(test_name)) {
+            return Err(self.error(test.name.span, format!("test {} isn't in the modules", test_name)));
+        }
+        unsafe {
+            let int_type = LLVMInt32TypeInContext(self.context);
+            let text_type = self.llvm_type(ValueType::Text);
+            let mut params = [int_type, LLVMPointerType(text_type, 0)];
+            let main_type = LLVMFunctionType(int_type, params.as_mut_ptr(), params.len() as u32, 0);
+            let main = LLVMAddFunction(self.module, c_str!("main"), main_type);
+            let block = LLVMAppendBasicBlockInContext(self.context, main, c_str!("entry"));
+            let named_block = LLVMAppendBasicBlockInContext(self.context, main, c_str!("named"));
+            let unknown_block = LLVMAppendBasicBlockInContext(self.context, main, c_str!("unknown"));
+            LLVMPositionBuilderAtEnd(self.builder, block);
+            let named = LLVMBuildICmp(self.builder, LLVMIntPredicate::LLVMIntSGT, LLVMGetParam(main, 0), LLVMConstInt(int_type, 1, 0), c_str!("named"));
+            LLVMBuildCondBr(self.builder, named, named_block, unknown_block);
+
+            LLVMPositionBuilderAtEnd(self.builder, named_block);
+            let mut index = [LLVMConstInt(int_type, 1, 0)];
+            let name_pointer = LLVMBuildGEP(self.builder, LLVMGetParam(main, 1), index.as_mut_ptr(), 1, c_str!(""));
+            let name = LLVMBuildLoad(self.builder, name_pointer, c_str!("name"));
+            for (test_name, _) in tests {
+                let mut arguments = [name, self.global_string(test_name)];
+                let order = self.call_runtime("strcmp", &mut arguments);
+                let matches = LLVMBuildICmp(self.builder, LLVMIntPredicate::LLVMIntEQ, order, LLVMConstInt(int_type, 0, 0), c_str!("matches"));
+                let run_block = LLVMAppendBasicBlockInContext(self.context, main, c_str!("run"));
+                let next_block = LLVMAppendBasicBlockInContext(self.context, main, c_str!("next"));
+                LLVMBuildCondBr(self.builder, matches, run_block, next_block);
+                LLVMPositionBuilderAtEnd(self.builder, run_block);
+                LLVMBuildCall(self.builder, self.functions[test_name], ptr::null_mut(), 0, c_str!(""));
+                LLVMBuildRet(self.builder, LLVMConstInt(int_type, 0, 0));
+                LLVMPositionBuilderAtEnd(self.builder, next_block);
+            }
+            LLVMBuildBr(self.builder, unknown_block);
+            LLVMPositionBuilderAtEnd(self.builder, unknown_block);
+            LLVMBuildRet(self.builder, LLVMConstInt(int_type, 2, 0));
+        }
+        return Ok(());
+    }
+
+    fn terminated(&self) -> bool {
+        unsafe {
+            return !LLVMGetBasicBlockTerminator(LLVMGetInsertBlock(self.builder)).is_null();
+        }
+    }
+
+    fn append_block(&self, name: &str) -> LLVMBasicBlockRef {
+        let name = CString::new(name).unwrap_or_default();
+        unsafe {
+            return LLVMAppendBasicBlockInContext(self.context, self.current.unwrap().0, name.as_ptr());
+        }
+    }
+
+    // ends the block being lowered with a branch, unless it already returned
+    fn branch_to(&self, block: LLVMBasicBlockRef) {
+        if !self.terminated() {
+            unsafe {
+                LLVMBuildBr(self.builder, block);
+            }
+        }
+    }
+
+    // statements
+
+    fn block(&mut self, block: &Block) -> Result<(), CodegenError> {
+        if block.otherwise.is_some() {
+            return Err(self.unsupported(block.span, "otherwise"));
+        }
+        self.scopes.push(HashMap::new());
+        let mut result = Ok(());
+        for statement in &block.statements {
+            result = self.statement(statement);
+            if result.is_err() {
+                break;
+            }
+        }
+        self.scopes.pop();
+        return result;
+    }
+
+    fn statement(&mut self, statement: &Stmt) -> Result<(), CodegenError> {
+        if statement.otherwise.is_some() {
+            return Err(self.unsupported(statement.span, "otherwise"));
+        }
+        if let Some(debug_info) = &self.debug_info {
+            debug_info.set_location(self.context, self.builder, &statement.span);
+        }
+        match &statement.kind {
+            StmtKind::Block(block) => self.block(block)?,
+            StmtKind::Return(value) => self.return_statement(value)?,
+            StmtKind::For { variable, data_type, iterable, body } => self.for_loop(variable, data_type, iterable, body)?,
+            StmtKind::While { condition, body } => self.while_loop(condition.as_ref(), body)?,
+            StmtKind::If { condition, body } => self.if_statement(condition, body)?,
+            StmtKind::Let { name, data_type, value } => self.let_statement(name, data_type.as_ref(), value.as_ref())?,
+            StmtKind::Assign { target, value } => self.assign(target, value)?,
+            StmtKind::Expr(value) => {
+                self.expr(value)?;
+            }
+            StmtKind::Fail(fail) => self.fail(fail)?,
+        }
+        return Ok(());
+    }
+
+    // the code after a return is never run, it goes in a block of its own that nothing branches to
+    fn after_jump(&self) {
+        let block = self.append_block("unreachable");
+        unsafe {
+            LLVMPositionBuilderAtEnd(self.builder, block);
+        }
+    }
+
+    fn return_statement(&mut self, value: &Expr) -> Result<(), CodegenError> {
+        let (llvm_value, value_type) = self.expr(value)?;
+        let returns = self.current.unwrap().1;
+        if value_type != returns {
+            return Err(self.error(value.span, format!("expected {}, found {}", returns.name(), value_type.name())));
+        }
+        unsafe {
+            if returns == ValueType::Nothing {
+                LLVMBuildRetVoid(self.builder);
+            } else {
+                LLVMBuildRet(self.builder, llvm_value);
+            }
+        }
+        self.after_jump();
+        return Ok(());
+    }
+
+    fn condition(&mut self, condition: &Expr) -> Result<LLVMValueRef, CodegenError> {
+        let (value, value_type) = self.expr(condition)?;
+        if value_type != ValueType::Bool {
+            return Err(self.error(condition.span, format!("expected bool, found {}", value_type.name())));
+        }
+        return Ok(value);
+    }
+
+    fn if_statement(&mut self, condition: &Expr, body: &Block) -> Result<(), CodegenError> {
+        let value = self.condition(condition)?;
+        let then_block = self.append_block("then");
+        let end_block = self.append_block("end_if");
+        unsafe {
+            LLVMBuildCondBr(self.builder, value, then_block, end_block);
+            LLVMPositionBuilderAtEnd(self.builder, then_block);
+        }
+        self.block(body)?;
+        self.branch_to(end_block);
+        unsafe {
+            LLVMPositionBuilderAtEnd(self.builder, end_block);
+        }
+        return Ok(());
+    }
+
+    fn while_loop(&mut self, condition: Option<&Expr>, body: &Block) -> Result<(), CodegenError> {
+        let condition_block = self.append_block("while");
+        let body_block = self.append_block("while_body");
+        let end_block = self.append_block("end_while");
+        self.branch_to(condition_block);
+        unsafe {
+            LLVMPositionBuilderAtEnd(self.builder, condition_block);
+        }
+        match condition {
+            Some(condition) => {
+                let value = self.condition(condition)?;
+                unsafe {
+                    LLVMBuildCondBr(self.builder, value, body_block, end_block);
+                }
+            }
+            None => self.branch_to(body_block),
+        }
+        unsafe {
+            LLVMPositionBuilderAtEnd(self.builder, body_block);
+        }
+        self.block(body)?;
+        self.branch_to(condition_block);
+        unsafe {
+            LLVMPositionBuilderAtEnd(self.builder, end_block);
+        }
+        return Ok(());
+    }
+
+    fn for_loop(&mut self, variable: &Name, data_type: &Option<Type>, iterable: &Expr, body: &Block) -> Result<(), CodegenError> {
+        let (start, end, inclusive) = match &iterable.kind {
+            ExprKind::Range { start, end, inclusive } => (start, end, *inclusive),
+            _ => return Err(self.unsupported(iterable.span, "for loops over anything but a range")),
+        };
+        if let Some(data_type) = data_type {
+            let value_type = self.value_type(data_type)?;
+            if value_type != ValueType::Int {
+                return Err(self.error(data_type.span, format!("expected int, found {}", value_type.name())));
+            }
+        }
+        let (start_value, start_type) = self.expr(start)?;
+        let (end_value, end_type) = self.expr(end)?;
+        for (bound, bound_type) in [(start, start_type), (end, end_type)] {
+            if bound_type != ValueType::Int {
+                return Err(self.error(bound.span, format!("expected int, found {}", bound_type.name())));
+            }
+        }
+
+        self.scopes.push(HashMap::new());
+        let pointer = self.declare_variable(variable, Some(ValueType::Int)).unwrap();
+        let condition_block = self.append_block("for");
+        let body_block = self.append_block("for_body");
+        let end_block = self.append_block("end_for");
+        unsafe {
+            LLVMBuildStore(self.builder, start_value, pointer);
+            LLVMBuildBr(self.builder, condition_block);
+            LLVMPositionBuilderAtEnd(self.builder, condition_block);
+            let current = LLVMBuildLoad(self.builder, pointer, c_str!(""));
+            let predicate = if inclusive { LLVMIntPredicate::LLVMIntSLE } else { LLVMIntPredicate::LLVMIntSLT };
+            let in_range = LLVMBuildICmp(self.builder, predicate, current, end_value, c_str!(""));
+            LLVMBuildCondBr(self.builder, in_range, body_block, end_block);
+            LLVMPositionBuilderAtEnd(self.builder, body_block);
+        }
+        let result = self.block(body);
+        if result.is_ok() && !self.terminated() {
+            unsafe {
+                let current = LLVMBuildLoad(self.builder, pointer, c_str!(""));
+                let one = LLVMConstInt(self.llvm_type(ValueType::Int), 1, 0);
+                LLVMBuildStore(self.builder, LLVMBuildAdd(self.builder, current, one, c_str!("")), pointer);
+                LLVMBuildBr(self.builder, condition_block);
+            }
+        }
+        self.scopes.pop();
+        result?;
+        unsafe {
+            LLVMPositionBuilderAtEnd(self.builder, end_block);
+        }
+        return Ok(());
+    }
+
+    fn let_statement(&mut self, name: &Name, data_type: Option<&Type>, value: Option<&Expr>) -> Result<(), CodegenError> {
+        let declared = match data_type {
+            Some(data_type) => Some(self.value_type(data_type)?),
+            None => None,
+        };
+        let value = match value {
+            Some(value) => {
+                let (llvm_value, value_type) = self.expr(value)?;
+                if declared.is_some_and(|declared| declared != value_type) || value_type == ValueType::Nothing {
+                    return Err(self.error(value.span, format!("expected {}, found {}",
+                                                              declared.map_or("a value", |declared| declared.name()), value_type.name())));
+                }
+                Some((llvm_value, value_type))
+            }
+            None => None,
+        };
+        let value_type = match (declared, value) {
+            (Some(declared), _) => declared,
+            (None, Some((_, value_type))) => value_type,
+            (None, None) => return Err(self.error(name.span, format!("can't tell what type {} is", name.text))),
+        };
+        let pointer = self.declare_variable(name, Some(value_type)).unwrap();
+        unsafe {
+            let initial = value.map_or(LLVMConstNull(self.llvm_type(value_type)), |(llvm_value, _)| llvm_value);
+            LLVMBuildStore(self.builder, initial, pointer);
+        }
+        return Ok(());
+    }
+
+    fn assign(&mut self, target: &Expr, value: &Expr) -> Result<(), CodegenError> {
+        let name = match &target.kind {
+            ExprKind::Identifier(path) => self.variable_name(target, path)?,
+            _ => return Err(self.unsupported(target.span, "assigning to anything but a variable")),
+        };
+        let (llvm_value, value_type) = self.expr(value)?;
+        let variable = self.lookup(name)?;
+        let variable_type = variable.value_type.unwrap_or(ValueType::Nothing);
+        if variable_type != value_type {
+            return Err(self.error(value.span, format!("expected {}, found {}", variable_type.name(), value_type.name())));
+        }
+        unsafe {
+            LLVMBuildStore(self.builder, llvm_value, variable.pointer.unwrap());
+        }
+        return Ok(());
+    }
+
+    // prints the arguments to stderr and ends the program with exit code 1
+    fn fail(&mut self, fail: &Fail) -> Result<(), CodegenError> {
+        let mut parts = vec![FormatPart::Text("fail".to_string())];
+        for (index, argument) in fail.arguments.iter().enumerate() {
+            let separator = if index == 0 { ": " } else { ", " };
+            let name = argument.name.as_ref().map_or(String::new(), |name| format!("{}: ", name.text));
+            parts.push(FormatPart::Text(format!("{}{}", separator, name)));
+            let value = self.expr(&argument.value)?;
+            parts.push(self.format_value(&argument.value, value)?);
+        }
+        parts.push(FormatPart::Text("\n".to_string()));
+        let mut arguments = vec![unsafe { LLVMConstInt(LLVMInt32TypeInContext(self.context), 2, 0) }];
+        arguments.append(&mut self.format_arguments(parts));
+        self.call_runtime("dprintf", &mut arguments);
+        unsafe {
+            let mut exit_code = [LLVMConstInt(LLVMInt32TypeInContext(self.context), 1, 0)];
+            self.call_runtime("exit", &mut exit_code);
+            LLVMBuildUnreachable(self.builder);
+        }
+        self.after_jump();
+        return Ok(());
+    }
+
+    // expressions
+
+    fn expr(&mut self, expr: &Expr) -> Result<(LLVMValueRef, ValueType), CodegenError> {
+        return match &expr.kind {
+            ExprKind::Identifier(path) => {
+                let name = self.variable_name(expr, path)?;
+                let variable = self.lookup(name)?;
+                let value_type = variable.value_type.unwrap_or(ValueType::Nothing);
+                unsafe {
+                    Ok((LLVMBuildLoad(self.builder, variable.pointer.unwrap(), c_str!("")), value_type))
+                }
+            }
+            ExprKind::Literal(literal) => self.literal(expr, literal),
+            ExprKind::Binary { operator, left, right } => {
+                let (left_value, left_type) = self.expr(left)?;
+                let (right_value, right_type) = self.expr(right)?;
+                let value_type = self.binary_type(expr.span, *operator, left_type, right_type)?;
+                Ok((self.binary(*operator, left_type, left_value, right_value), value_type))
+            }
+            ExprKind::Unary { operator, operand } => {
+                let (value, value_type) = self.expr(operand)?;
+                let value_type = self.unary_type(expr.span, *operator, value_type)?;
+                unsafe {
+                    let result = match (operator, value_type) {
+                        (UnaryOperator::Not, _) => LLVMBuildNot(self.builder, value, c_str!("")),
+                        (UnaryOperator::Negate, ValueType::Float) => LLVMBuildFNeg(self.builder, value, c_str!("")),
+                        (UnaryOperator::Negate, _) => LLVMBuildNeg(self.builder, value, c_str!("")),
+                    };
+                    Ok((result, value_type))
+                }
+            }
+            ExprKind::Cast { value, data_type } => {
+                let to = self.value_type(data_type)?;
+                let (llvm_value, from) = self.expr(value)?;
+                self.cast_type(expr.span, from, to)?;
+                Ok((self.cast(value, llvm_value, from, to)?, to))
+            }
+            ExprKind::Call { function, arguments } => self.call(function, arguments),
+            ExprKind::Config | ExprKind::Member { .. } => {
+                let value = self.config_value(expr)?;
+                let value_type = self.config_type(expr, value)?;
+                let llvm_type = self.llvm_type(value_type);
+                unsafe {
+                    let llvm_value = match &value.kind {
+                        ValueKind::String(text) => self.global_string(text),
+                        ValueKind::Integer(integer) => LLVMConstInt(llvm_type, *integer as u64, 1),
+                        ValueKind::Float(float) => LLVMConstReal(llvm_type, *float),
+                        ValueKind::Boolean(value) => LLVMConstInt(llvm_type, *value as u64, 0),
+                        _ => unreachable!(),
+                    };
+                    Ok((llvm_value, value_type))
+                }
+            }
+            ExprKind::InterpolatedString(string_parts) => {
+                let mut parts = vec![];
+                for part in string_parts {
+                    match part {
+                        StringPart::Text(text) => parts.push(FormatPart::Text(text.clone())),
+                        StringPart::Expr(value) => {
+                            let llvm_value = self.expr(value)?;
+                            parts.push(self.format_value(value, llvm_value)?);
+                        }
+                    }
+                }
+                Ok((self.format_text(parts), ValueType::Text))
+            }
+            _ => Err(self.type_of(expr).unwrap_err()),
+        };
+    }
+
+    fn literal(&self, expr: &Expr, literal: &Literal) -> Result<(LLVMValueRef, ValueType), CodegenError> {
+        let value_type = self.literal_type(expr, literal)?;
+        unsafe {
+            let value = match literal {
+                Literal::String(text) => self.global_string(text),
+                Literal::Number(number) => match number.value {
+                    NumberValue::Integer(integer) if value_type == ValueType::Int => LLVMConstInt(self.llvm_type(value_type), integer, 0),
+                    NumberValue::Integer(integer) => LLVMConstReal(self.llvm_type(value_type), integer as f64),
+                    NumberValue::Float(float) => LLVMConstReal(self.llvm_type(value_type), float),
+                },
+                Literal::Boolean(value) => LLVMConstInt(self.llvm_type(value_type), *value as u64, 0),
+                Literal::Null => unreachable!(),
+            };
+            return Ok((value, value_type));
+        }
+    }
+
+    fn global_string(&self, text: &str) -> LLVMValueRef {
+        // a string with a null in it ends there, like it would in c
+        let text = CString::new(text.split('\0').next().unwrap_or_default()).unwrap_or_default();
+        unsafe {
+            return LLVMBuildGlobalStringPtr(self.builder, text.as_ptr(), c_str!("string"));
+        }
+    }
+
+    fn binary(&mut self, operator: BinaryOperator, operand_type: ValueType, left: LLVMValueRef, right: LLVMValueRef) -> LLVMValueRef {
+        if operand_type == ValueType::Text {
+            if operator == BinaryOperator::Add {
+                return self.format_text(vec![FormatPart::Value("%s", left), FormatPart::Value("%s", right)]);
+            }
+            // strings compare by what's in them
+            let mut arguments = [left, right];
+            let order = self.call_runtime("strcmp", &mut arguments);
+            let zero = unsafe { LLVMConstInt(LLVMInt32TypeInContext(self.context), 0, 0) };
+            return self.compare(operator, ValueType::Int, order, zero);
+        }
+        unsafe {
+            let float = operand_type == ValueType::Float;
+            return match operator {
+                BinaryOperator::Add if float => LLVMBuildFAdd(self.builder, left, right, c_str!("")),
+                BinaryOperator::Add => LLVMBuildAdd(self.builder, left, right, c_str!("")),
+                BinaryOperator::Subtract if float => LLVMBuildFSub(self.builder, left, right, c_str!("")),
+                BinaryOperator::Subtract => LLVMBuildSub(self.builder, left, right, c_str!("")),
+                BinaryOperator::Multiply if float => LLVMBuildFMul(self.builder, left, right, c_str!("")),
+                BinaryOperator::Multiply => LLVMBuildMul(self.builder, left, right, c_str!("")),
+                BinaryOperator::Divide if float => LLVMBuildFDiv(self.builder, left, right, c_str!("")),
+                BinaryOperator::Divide => LLVMBuildSDiv(self.builder, left, right, c_str!("")),
+                _ => self.compare(operator, operand_type, left, right),
+            };
+        }
+    }
+
+    fn compare(&self, operator: BinaryOperator, operand_type: ValueType, left: LLVMValueRef, right: LLVMValueRef) -> LLVMValueRef {
+        unsafe {
+            if operand_type == ValueType::Float {
+                let predicate = match operator {
+                    BinaryOperator::Equal => LLVMRealPredicate::LLVMRealOEQ,
+                    BinaryOperator::NotEqual => LLVMRealPredicate::LLVMRealONE,
+                    BinaryOperator::Less => LLVMRealPredicate::LLVMRealOLT,
+                    BinaryOperator::Greater => LLVMRealPredicate::LLVMRealOGT,
+                    BinaryOperator::LessOrEqual => LLVMRealPredicate::LLVMRealOLE,
+                    _ => LLVMRealPredicate::LLVMRealOGE,
+                };
+                return LLVMBuildFCmp(self.builder, predicate, left, right, c_str!(""));
+            }
+            let predicate = match operator {
+                BinaryOperator::Equal => LLVMIntPredicate::LLVMIntEQ,
+                BinaryOperator::NotEqual => LLVMIntPredicate::LLVMIntNE,
+                BinaryOperator::Less => LLVMIntPredicate::LLVMIntSLT,
+                BinaryOperator::Greater => LLVMIntPredicate::LLVMIntSGT,
+                BinaryOperator::LessOrEqual => LLVMIntPredicate::LLVMIntSLE,
+                _ => LLVMIntPredicate::LLVMIntSGE,
+            };
+            return LLVMBuildICmp(self.builder, predicate, left, right, c_str!(""));
+        }
+    }
+
+    fn cast(&mut self, value: &Expr, llvm_value: LLVMValueRef, from: ValueType, to: ValueType) -> Result<LLVMValueRef, CodegenError> {
+        if from == to {
+            return Ok(llvm_value);
+        }
+        if to == ValueType::Text {
+            let part = self.format_value(value, (llvm_value, from))?;
+            return Ok(self.format_text(vec![part]));
+        }
+        let llvm_type = self.llvm_type(to);
+        unsafe {
+            return Ok(match (from, to) {
+                (ValueType::Int, ValueType::Float) => LLVMBuildSIToFP(self.builder, llvm_value, llvm_type, c_str!("")),
+                (ValueType::Float, ValueType::Int) => LLVMBuildFPToSI(self.builder, llvm_value, llvm_type, c_str!("")),
+                (ValueType::Int, ValueType::Char) => LLVMBuildTrunc(self.builder, llvm_value, llvm_type, c_str!("")),
+                // char and bool
+                _ => LLVMBuildZExt(self.builder, llvm_value, llvm_type, c_str!("")),
+            });
+        }
+    }
+
+    fn call(&mut self, function: &Expr, arguments: &[Argument]) -> Result<(LLVMValueRef, ValueType), CodegenError> {
+        let path = self.function_path(function)?;
+        if let Some(argument) = arguments.iter().find(|argument| argument.name.is_some()) {
+            return Err(self.unsupported(argument.span, "named arguments"));
+        }
+        if is_builtin(&path) {
+            return self.call_builtin(function, &path[0], arguments);
+        }
+
+        let name = self.resolve_function(&path)?;
+        let signature = &self.signatures[&name];
+        let (decl_params, defaults, returns) = (signature.params.clone(), signature.defaults.clone(), signature.returns.unwrap_or(ValueType::Nothing));
+        let required = defaults.iter().take_while(|default| default.is_none()).count();
+        if arguments.len() < required || arguments.len() > decl_params.len() {
+            return Err(self.error(function.span, format!("fn {} takes {} argument(s), found {}", display_path(&path), decl_params.len(), arguments.len())));
+        }
+        let mut values = vec![];
+        for (index, param_type) in decl_params.iter().enumerate() {
+            let value = match arguments.get(index) {
+                Some(argument) => &argument.value,
+                None => defaults[index].as_ref().unwrap(),
+            };
+            let (llvm_value, value_type) = self.expr(value)?;
+            if value_type != *param_type {
+                return Err(self.error(value.span, format!("expected {}, found {}", param_type.name(), value_type.name())));
+            }
+            values.push(llvm_value);
+        }
+        unsafe {
+            // llvm doesn't name what a function that returns nothing returns
+            let result_name = if returns == ValueType::Nothing { c_str!("") } else { c_str!("result") };
+            let result = LLVMBuildCall(self.builder, self.functions[&name], values.as_mut_ptr(), values.len() as u32, result_name);
+            return Ok((result, returns));
+        }
+    }
+
+    // print and println write their argument to stdout
+    fn call_builtin(&mut self, function: &Expr, name: &Name, arguments: &[Argument]) -> Result<(LLVMValueRef, ValueType), CodegenError> {
+        if arguments.len() != 1 {
+            return Err(self.error(function.span, format!("fn {} takes 1 argument(s), found {}", name.text, arguments.len())));
+        }
+        let value = self.expr(&arguments[0].value)?;
+        let mut parts = vec![self.format_value(&arguments[0].value, value)?];
+        if name.text == "println" {
+            parts.push(FormatPart::Text("\n".to_string()));
+        }
+        let mut format_arguments = self.format_arguments(parts);
+        let result = self.call_runtime("printf", &mut format_arguments);
+        return Ok((result, ValueType::Nothing));
+    }
+
+    // formatting
+
+    fn format_value(&self, expr: &Expr, (value, value_type): (LLVMValueRef, ValueType)) -> Result<FormatPart, CodegenError> {
+        return match value_type {
+            ValueType::Int => Ok(FormatPart::Value("%lld", value)),
+            ValueType::Float => Ok(FormatPart::Value("%g", value)),
+            ValueType::Char => Ok(FormatPart::Value("%c", value)),
+            ValueType::Text => Ok(FormatPart::Value("%s", value)),
+            ValueType::Bool => unsafe {
+                let text = LLVMBuildSelect(self.builder, value, self.global_string("true"), self.global_string("false"), c_str!(""));
+                Ok(FormatPart::Value("%s", text))
+            },
+            ValueType::Nothing => Err(self.error(expr.span, "expected a value, found nothing".to_string())),
+        };
+    }
+
+    // the format string and the values for it, for the printf family
+    fn format_arguments(&self, parts: Vec<FormatPart>) -> Vec<LLVMValueRef> {
+        let mut format = String::new();
+        let mut values = vec![];
+        for part in parts {
+            match part {
+                FormatPart::Text(text) => format += &text.replace('%', "%%"),
+                FormatPart::Value(spec, value) => {
+                    format += spec;
+                    values.push(value);
+                }
+            }
+        }
+        let mut result = vec![self.global_string(&format)];
+        result.append(&mut values);
+        return result;
+    }
+
+    // a new string of the parts, as long as it needs to be
+    // todo: strings are never freed, that waits on the analyzer working out who owns them
+    fn format_text(&mut self, parts: Vec<FormatPart>) -> LLVMValueRef {
+        let mut format_arguments = self.format_arguments(parts);
+        unsafe {
+            let size_type = LLVMInt64TypeInContext(self.context);
+            let mut measure = vec![LLVMConstNull(self.llvm_type(ValueType::Text)), LLVMConstInt(size_type, 0, 0)];
+            measure.extend(format_arguments.iter());
+            let length = self.call_runtime("snprintf", &mut measure);
+            let length = LLVMBuildSExt(self.builder, length, size_type, c_str!(""));
+            let size = LLVMBuildAdd(self.builder, length, LLVMConstInt(size_type, 1, 0), c_str!(""));
+            let buffer = self.call_runtime("malloc", &mut [size]);
+            let mut write = vec![buffer, size];
+            write.append(&mut format_arguments);
+            self.call_runtime("snprintf", &mut write);
+            return buffer;
+        }
+    }
+
+    // a function of the c library, declared the first time that it's used
+    fn call_runtime(&mut self, name: &str, arguments: &mut [LLVMValueRef]) -> LLVMValueRef {
+        let c_name = CString::new(name).unwrap_or_default();
+        unsafe {
+            let mut function = LLVMGetNamedFunction(self.module, c_name.as_ptr());
+            if function.is_null() {
+                let int = LLVMInt32TypeInContext(self.context);
+                let size = LLVMInt64TypeInContext(self.context);
+                let text = self.llvm_type(ValueType::Text);
+                let (returns, mut params, variadic) = match name {
+                    "printf" => (int, vec![text], true),
+                    "dprintf" => (int, vec![int, text], true),
+                    "snprintf" => (int, vec![text, size, text], true),
+                    "malloc" => (text, vec![size], false),
+                    "strcmp" => (int, vec![text, text], false),
+                    _ => (LLVMVoidTypeInContext(self.context), vec![int], false),
+                };
+                let function_type = LLVMFunctionType(returns, params.as_mut_ptr(), params.len() as u32, variadic as i32);
+                function = LLVMAddFunction(self.module, c_name.as_ptr(), function_type);
+            }
+            let result_name = if name == "exit" { c_str!("") } else { c_str!("result") };
+            return LLVMBuildCall(self.builder, function, arguments.as_mut_ptr(), arguments.len() as u32, result_name);
+        }
+    }
+}
+
+enum FormatPart {
+    Text(String),
+    // the printf conversion for the value, like %lld
+    Value(&'static str, LLVMValueRef),
+}
+
+fn is_builtin(path: &[Name]) -> bool {
+    return path.len() == 1 && (path[0].text == "print" || path[0].text == "println");
+}
+
+fn display_path(path: &[Name]) -> String {
+    let names: Vec<&str> = path.iter().map(|name| name.text.as_str()).collect();
+    return names.join("::");
+}
+
+#[cfg(test)]
+mod codegen_tests {
+    use std::ptr;
+    use std::slice;
+
+    use llvm_sys::analysis::{LLVMVerifierFailureAction, LLVMVerifyModule};
+    use llvm_sys::core::LLVMGetValueName2;
+
+    use crate::config::{Config, Entry, Value, ValueKind};
+    use crate::llvm::codegen::{collect_functions, Codegen};
+    use crate::source::{SourceMap, Span};
+    use crate::transform::transform_module;
+
+    // lowers the code with its last function as the entry point
+    fn lower(code: &str) -> Result<(), String> {
+        return lower_with(code, &Config::default());
+    }
+
+    fn lower_with(code: &str, config: &Config) -> Result<(), String> {
+        let mut source_map = SourceMap::new();
+        let module = transform_module(&mut source_map, code);
+        let mut functions = vec![];
+        collect_functions(&module, &[], true, &mut functions);
+        let mut codegen = Codegen::new(&source_map, config, "main", true);
+        codegen.lower(&[&module], functions.last().unwrap().1).map_err(|error| error.to_string())?;
+        unsafe {
+            let mut message = ptr::null_mut();
+            assert_eq!(0, LLVMVerifyModule(codegen.module(), LLVMVerifierFailureAction::LLVMReturnStatusAction, &mut message));
+        }
+        return Ok(());
+    }
+
+    #[test]
+    fn lower_a_program() {
+        // returns come from callees that are declared later and from calls to the function itself
+        assert_eq!(Ok(()), lower("\
+fn double(n: int) {
+  return twice(n, 2)
+}
+fn twice(n: int, times: int = 2) {
+  if times == 0 {
+    return 0
+  }
+  return n + twice(n, times - 1)
+}
+fn describe(value: float, flag: bool) {
+  return \"{value} {flag} {value as int}\"
+}
+app fn main() {
+  let total = 0
+  for i in [0..=10] {
+    total = total + double(i)
+  }
+  while {
+    fail(\"stuck\", total: total)
+  }
+  println(describe(1.5, !true) + \"!\")
+  return total
+}
+"));
+    }
+
+    #[test]
+    fn lower_config_values() {
+        let span = Span {
+            file_id: None,
+            start: 0,
+            end: 0,
+            line_number: 1,
+            line_offset: 1,
+        };
+        let value = |kind: ValueKind| Value { kind, span };
+        let config = Config {
+            entries: vec![
+                Entry { name: "version".to_string(), value: value(ValueKind::String("1.2".to_string())) },
+                Entry {
+                    name: "database".to_string(),
+                    value: value(ValueKind::Map(vec![
+                        Entry { name: "port".to_string(), value: value(ValueKind::Integer(5432)) },
+                        Entry { name: "hosts".to_string(), value: value(ValueKind::List(vec![])) },
+                    ])),
+                },
+            ],
+            ..Config::default()
+        };
+        assert_eq!(Ok(()), lower_with("app fn main() {\n  println(\"{config.version} on {config.database.port + 1}\")\n}\n", &config));
+        assert_eq!(Err("config default has no database.name at main.dog:2:11".to_string()),
+                   lower_with("app fn main() {\n  println(config.database.name)\n}\n", &config));
+        assert_eq!(Err("config values that are a list can't be compiled yet at main.dog:2:11".to_string()),
+                   lower_with("app fn main() {\n  println(config.database.hosts)\n}\n", &config));
+    }
+
+    #[test]
+    fn lower_a_test_harness() {
+        let mut source_map = SourceMap::new();
+        let module = transform_module(&mut source_map, "\
+fn double(n: int) {
+  return n * 2
+}
+test mod checks {
+  fn expect(value: int, expected: int) {
+    if value != expected {
+      fail(\"wrong\", value: value)
+    }
+  }
+  test fn doubles() {
+    expect(double(2), 4)
+  }
+  test fn counts(n: int) {
+  }
+}
+app fn main() {
+  return double(1)
+}
+");
+        let mut functions = vec![];
+        collect_functions(&module, &[], true, &mut functions);
+        let config = Config::default();
+        let verify = |codegen: &Codegen| unsafe {
+            let mut message = ptr::null_mut();
+            assert_eq!(0, LLVMVerifyModule(codegen.module(), LLVMVerifierFailureAction::LLVMReturnStatusAction, &mut message));
+        };
+
+        let mut codegen = Codegen::new(&source_map, &config, "tests", true);
+        codegen.lower_tests(&[&module], &[("checks::doubles".to_string(), functions[2].1)]).unwrap();
+        verify(&codegen);
+
+        // the test mod isn't part of the app
+        let mut codegen = Codegen::new(&source_map, &config, "main", true);
+        codegen.lower(&[&module], functions[4].1).unwrap();
+        verify(&codegen);
+        assert!(codegen.functions.contains_key("main") && !codegen.functions.contains_key("checks::expect"));
+
+        let mut codegen = Codegen::new(&source_map, &config, "tests", true);
+        assert_eq!(Err("tests with parameters can't be compiled yet at main.dog:13:3".to_string()),
+                   codegen.lower_tests(&[&module], &[("checks::counts".to_string(), functions[3].1)]).map_err(|error| error.to_string()));
+
+        let mut codegen = Codegen::new(&source_map, &config, "tests", true);
+        assert_eq!(Err("test doubles isn't in the modules at main.dog:10:11".to_string()),
+                   codegen.lower_tests(&[&module], &[("doubles".to_string(), functions[2].1)]).map_err(|error| error.to_string()));
+    }
+
+    #[test]
+    fn lower_tests_with_the_same_name() {
+        let mut source_map = SourceMap::new();
+        let module = transform_module(&mut source_map, "\
+fn add(a: int, b: int) {
+  return a + b
+}
+test mod math_tests {
+  test fn adds() {
+    if add(1, 1) != 2 {
+      fail(\"wrong\")
+    }
+  }
+}
+test mod text_tests {
+  test fn adds() {
+    println(\"a\" + \"b\")
+  }
+}
+");
+        let mut functions = vec![];
+        collect_functions(&module, &[], true, &mut functions);
+        let config = Config::default();
+        let mut codegen = Codegen::new(&source_map, &config, "tests", true);
+        let tests = [("math_tests::adds".to_string(), functions[1].1), ("text_tests::adds".to_string(), functions[2].1)];
+        codegen.lower_tests(&[&module], &tests).unwrap();
+        unsafe {
+            let mut message = ptr::null_mut();
+            assert_eq!(0, LLVMVerifyModule(codegen.module(), LLVMVerifierFailureAction::LLVMReturnStatusAction, &mut message));
+        }
+        assert!(codegen.functions.contains_key("math_tests::adds") && codegen.functions.contains_key("text_tests::adds"));
+    }
+
+    #[test]
+    fn lower_functions_by_module() {
+        // each helper is found from its own module first, then from the modules around it
+        assert_eq!(Ok(()), lower("\
+fn helper() {
+  return 1
+}
+mod math {
+  fn helper() {
+    return 2.0
+  }
+  mod more {
+    fn twice() {
+      return helper() * 2.0
+    }
+  }
+  fn sum() {
+    return helper() + more::twice()
+  }
+}
+mod text {
+  fn helper() {
+    return \"three\"
+  }
+  fn describe() {
+    return \"{helper()} {math::sum()}\"
+  }
+}
+app fn main() {
+  let description = text::describe()
+  println(description)
+  return helper() + math::more::twice() as int
+}
+"));
+        assert_eq!(Err("fn more::twice isn't declared at main.dog:10:3".to_string()),
+                   lower("fn first() {\n}\nmod math {\n  mod more {\n    fn twice() {\n    }\n  }\n}\napp fn main() {\n  more::twice()\n}\n"));
+        assert_eq!(Err("fn math::helper is declared more than once at main.dog:8:6".to_string()),
+                   lower("fn first() {\n}\nmod math {\n  fn helper() {\n  }\n}\nmod math {\n  fn helper() {\n  }\n}\napp fn main() {\n}\n"));
+
+        let mut source_map = SourceMap::new();
+        let module = transform_module(&mut source_map, "fn helper() {\n}\nmod math {\n  fn helper() {\n  }\n}\napp fn main() {\n  helper()\n}\n");
+        let mut functions = vec![];
+        collect_functions(&module, &[], false, &mut functions);
+        let config = Config::default();
+        let mut codegen = Codegen::new(&source_map, &config, "main", true);
+        codegen.lower(&[&module], functions[2].1).unwrap();
+        let symbols: Vec<String> = ["helper", "math::helper", "main"].iter().map(|name| unsafe {
+            let mut length = 0;
+            let symbol = LLVMGetValueName2(codegen.functions[*name], &mut length);
+            String::from_utf8_lossy(slice::from_raw_parts(symbol as *const u8, length)).into_owned()
+        }).collect();
+        assert_eq!(vec!["dog.helper", "dog.math.helper", "dog.main"], symbols);
+    }
+
+    #[test]
+    fn reject_what_cant_be_lowered() {
+        assert_eq!(Err("can't add int and float at main.dog:2:11".to_string()),
+                   lower("app fn main() {\n  let x = 1 + 2.0\n}\n"));
+        assert_eq!(Err("expected bool, found int at main.dog:2:6".to_string()),
+                   lower("app fn main() {\n  if 1 {\n  }\n}\n"));
+        assert_eq!(Err("x isn't declared at main.dog:2:3".to_string()),
+                   lower("app fn main() {\n  x = 1\n}\n"));
+        assert_eq!(Err("fn f takes 1 argument(s), found 0 at main.dog:4:3".to_string()),
+                   lower("fn f(a: int) {\n}\napp fn main() {\n  f()\n}\n"));
+        assert_eq!(Err("can't tell what loop returns at main.dog:2:10".to_string()),
+                   lower("fn loop() {\n  return loop()\n}\napp fn main() {\n}\n"));
+        assert_eq!(Err("arrays can't be compiled yet at main.dog:1:9".to_string()),
+                   lower("fn f(a: [int]) {\n}\napp fn main() {\n}\n"));
+    }
+}
@@ -0,0 +1,22 @@
+use std::fmt;
+
+use crate::source::Span;
+
+// Something in the syntax tree that can't be turned into llvm code, either because it's wrong,
+// like adding a bool to an int, or because the compiler can't do it yet. When there's no span,
+// the problem is with the output, like an object file that couldn't be written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodegenError {
+    pub message: String,
+    pub file_name: String,
+    pub span: Option<Span>,
+}
+
+impl fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.span {
+            Some(span) => write!(f, "{} at {}:{}:{}", self.message, self.file_name, span.line_number, span.line_offset),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+// Line tables for the functions codegen.rs lowers, so that a debugger can step through the dog
+// code and a crash says which line it was on. There's one compile unit for the module, a file
+// for each source file and a subprogram for each function, and every statement gets the
+// location it starts at. Release builds don't have any of it, see target/output.rs.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::ptr;
+
+use llvm_sys::core::{LLVMAddModuleFlag, LLVMConstInt, LLVMInt32TypeInContext, LLVMSetCurrentDebugLocation2, LLVMValueAsMetadata};
+use llvm_sys::debuginfo::*;
+use llvm_sys::LLVMModuleFlagBehavior;
+use llvm_sys::prelude::*;
+
+use crate::source::{FileId, SourceMap, Span};
+
+const PRODUCER: &str = concat!("dog ", env!("CARGO_PKG_VERSION"));
+
+pub struct DebugInfo {
+    builder: LLVMDIBuilderRef,
+    files: HashMap<FileId, LLVMMetadataRef>,
+    // the subprogram of the function being lowered
+    scope: Option<LLVMMetadataRef>,
+}
+
+impl Drop for DebugInfo {
+    fn drop(&mut self) {
+        unsafe {
+            LLVMDisposeDIBuilder(self.builder);
+        }
+    }
+}
+
+// the file name and the folder it's in, as llvm wants them
+fn split_path(path: &str) -> (String, String) {
+    let path = Path::new(path);
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let folder = path.parent().map(|folder| folder.to_string_lossy().into_owned()).unwrap_or_default();
+    return (name, folder);
+}
+
+impl DebugInfo {
+    // the compile unit is said to be in the file of the entry point
+    pub fn new(context: LLVMContextRef, module: LLVMModuleRef, source_map: &SourceMap, entry: &Span) -> DebugInfo {
+        unsafe {
+            let version = LLVMConstInt(LLVMInt32TypeInContext(context), LLVMDebugMetadataVersion() as u64, 0);
+            let key = "Debug Info Version";
+            LLVMAddModuleFlag(module, LLVMModuleFlagBehavior::LLVMModuleFlagBehaviorWarning,
+                              key.as_ptr() as *const i8, key.len(), LLVMValueAsMetadata(version));
+
+            let builder = LLVMCreateDIBuilder(module);
+            let mut result = DebugInfo {
+                builder,
+                files: HashMap::new(),
+                scope: None,
+            };
+            let file = result.file(source_map, entry.file_id);
+            let empty = "";
+            // dog isn't a language dwarf knows, c is the closest. the subprograms find the unit
+            // through the builder.
+            LLVMDIBuilderCreateCompileUnit(builder, LLVMDWARFSourceLanguage::LLVMDWARFSourceLanguageC, file,
+                                           PRODUCER.as_ptr() as *const i8, PRODUCER.len(), 0,
+                                           empty.as_ptr() as *const i8, 0, 0,
+                                           empty.as_ptr() as *const i8, 0,
+                                           LLVMDWARFEmissionKind::LLVMDWARFEmissionKindLineTablesOnly,
+                                           0, 0, 0,
+                                           empty.as_ptr() as *const i8, 0,
+                                           empty.as_ptr() as *const i8, 0);
+            return result;
+        }
+    }
+
+    fn file(&mut self, source_map: &SourceMap, file_id: Option<FileId>) -> LLVMMetadataRef {
+        let builder = self.builder;
+        let create = |path: &str| {
+            let (name, folder) = split_path(path);
+            unsafe {
+                return LLVMDIBuilderCreateFile(builder, name.as_ptr() as *const i8, name.len(), folder.as_ptr() as *const i8, folder.len());
+            }
+        };
+        return match file_id {
+            Some(file_id) => *self.files.entry(file_id).or_insert_with(|| create(&source_map.get(file_id).path)),
+            None => create(crate::UNKNOWN),
+        };
+    }
+
+    // a function's instructions can only have locations once it has a subprogram
+    pub fn start_function(&mut self, source_map: &SourceMap, function: LLVMValueRef, name: &str, linkage_name: &str, span: &Span) {
+        let file = self.file(source_map, span.file_id);
+        let line = span.line_number as u32;
+        unsafe {
+            let function_type = LLVMDIBuilderCreateSubroutineType(self.builder, file, ptr::null_mut(), 0, LLVMDIFlagZero);
+            let subprogram = LLVMDIBuilderCreateFunction(self.builder, file, name.as_ptr() as *const i8, name.len(),
+                                                         linkage_name.as_ptr() as *const i8, linkage_name.len(),
+                                                         file, line, function_type, 0, 1, line, LLVMDIFlagZero, 0);
+            LLVMSetSubprogram(function, subprogram);
+            self.scope = Some(subprogram);
+        }
+    }
+
+    // the instructions the builder makes from here on are at the span
+    pub fn set_location(&self, context: LLVMContextRef, builder: LLVMBuilderRef, span: &Span) {
+        if let Some(scope) = self.scope {
+            unsafe {
+                let location = LLVMDIBuilderCreateDebugLocation(context, span.line_number as u32, span.line_offset as u32, scope, ptr::null_mut());
+                LLVMSetCurrentDebugLocation2(builder, location);
+            }
+        }
+    }
+
+    // so that the next function's instructions don't point into this one
+    pub fn end_function(&mut self, builder: LLVMBuilderRef) {
+        self.scope = None;
+        unsafe {
+            LLVMSetCurrentDebugLocation2(builder, ptr::null_mut());
+        }
+    }
+
+    // the subprograms aren't done until this is called, it has to be before the module is written
+    pub fn finalize(&self) {
+        unsafe {
+            LLVMDIBuilderFinalize(self.builder);
+        }
+    }
+}
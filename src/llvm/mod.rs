@@ -2,78 +2,68 @@
 
 extern crate llvm_sys;
 
-use llvm_sys::{LLVMBuilder, LLVMIntPredicate, LLVMModule};
-use llvm_sys::bit_writer::*;
-use llvm_sys::core::*;
-use llvm_sys::core::*;
-use llvm_sys::prelude::*;
-use llvm_sys::target::*;
-use llvm_sys::target_machine::*;
-//use llvm_sys::transforms::pass_manager_builder::*;
-use std::ffi::{CStr, CString};
-use std::ptr;
+use std::path::Path;
+use std::slice;
 
+use crate::config::Config;
+use crate::llvm::codegen::Codegen;
+use crate::llvm::codegen_error::CodegenError;
+use crate::source::SourceMap;
+use crate::transform::ast::{FnDecl, Module};
+
+// a null terminated string for llvm, from a literal
 macro_rules! c_str {
     ($s:expr) => (
         concat!($s, "\0").as_ptr() as *const i8
     );
 }
 
-pub fn get_default_target_triple() -> CString {
-    let target_triple;
-    unsafe {
-        let target_triple_ptr = LLVMGetDefaultTargetTriple();
-        target_triple = CStr::from_ptr(target_triple_ptr as *const _).to_owned();
-        LLVMDisposeMessage(target_triple_ptr);
-    }
-
-    target_triple
-}
+pub(crate) mod codegen;
+pub(crate) mod codegen_error;
+mod debug_info;
+mod native;
 
-pub fn compile() {
-    unsafe {
-// setup
-        let context = LLVMContextCreate();
-        let module = LLVMModuleCreateWithName(c_str!("main"));
-        let builder = LLVMCreateBuilderInContext(context);
+pub use native::default_target_triple;
 
-// common types
-        let void_type = LLVMVoidTypeInContext(context);
-        let i8_type = LLVMIntTypeInContext(context, 8);
-        let i8_pointer_type = LLVMPointerType(i8_type, 0);
-
-// declare that there's a `void log(i8*)` function in the environment
-// but don't provide a block (aka body) so that it in the wasm module
-// it'll be imported
-        let log_func_type = LLVMFunctionType(void_type, [i8_pointer_type].as_ptr() as *mut _, 1, 0);
-        let log_func = LLVMAddFunction(module, c_str!("log"), log_func_type);
+// how the code is built: a debug build has line tables and isn't optimized, a release build is
+// optimized and stripped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CodegenOptions {
+    pub optimize: bool,
+    pub debug_info: bool,
+}
 
-// our "main" function which we'll need to call explicitly from JavaScript
-// after we've instantiated the WebAssembly.Instance
-        let main_func_type = LLVMFunctionType(void_type, ptr::null_mut(), 0, 0);
-        let main_func = LLVMAddFunction(module, c_str!("main"), main_func_type);
-        let main_block = LLVMAppendBasicBlockInContext(context, main_func, c_str!("main"));
-        LLVMPositionBuilderAtEnd(builder, main_block);
+impl CodegenOptions {
+    pub fn debug() -> CodegenOptions {
+        return CodegenOptions { optimize: false, debug_info: true };
+    }
 
-// main's function body
-        let hello_world_str = LLVMBuildGlobalStringPtr(builder, c_str!("hello, world."), c_str!(""));
-        let log_args = [hello_world_str].as_ptr() as *mut _;
-// calling `log("hello, world.")`
-        LLVMBuildCall(builder, log_func, log_args, 1, c_str!(""));
-        LLVMBuildRetVoid(builder);
+    pub fn release() -> CodegenOptions {
+        return CodegenOptions { optimize: true, debug_info: false };
+    }
+}
 
-// write our bitcode file
-// NOTE: you can read a bitcode file by using `llvm-dis <bc filename>.bc` and then looking at the resulting text file '<filename>.ll`
-//let default_triple_cstring = get_default_target_triple();
-//let default_triple = default_triple_cstring.to_str().unwrap();
-        let target_triple_ptr = LLVMGetDefaultTargetTriple();
+// Lowers the modules into an executable that starts at the entry point, for the config's target
+// and with its libraries. The object file is left where it was written, it's up to the caller
+// whether to keep it.
+pub fn build_executable(source_map: &SourceMap, config: &Config, options: CodegenOptions, modules: &[&Module], entry: &FnDecl,
+                        object: &Path, executable: &Path) -> Result<(), CodegenError> {
+    let mut codegen = Codegen::new(source_map, config, &entry.name.text, options.debug_info);
+    codegen.lower(modules, entry)?;
+    return write_executable(&codegen, config, options, object, executable);
+}
 
-        LLVMSetTarget(module, target_triple_ptr);
-        LLVMWriteBitcodeToFile(module, c_str!("main.bc"));
+// Like build_executable, but the executable runs the test that its first argument names, by the
+// name that it's given here. It exits with 1 when the test fails and with 2 when there's no such
+// test.
+pub fn build_test_harness(source_map: &SourceMap, config: &Config, options: CodegenOptions, modules: &[&Module], tests: &[(String, &FnDecl)],
+                          object: &Path, executable: &Path) -> Result<(), CodegenError> {
+    let mut codegen = Codegen::new(source_map, config, "tests", options.debug_info);
+    codegen.lower_tests(modules, tests)?;
+    return write_executable(&codegen, config, options, object, executable);
+}
 
-// clean up
-        LLVMDisposeBuilder(builder);
-        LLVMDisposeModule(module);
-        LLVMContextDispose(context);
-    }
-}
\ No newline at end of file
+fn write_executable(codegen: &Codegen, config: &Config, options: CodegenOptions, object: &Path, executable: &Path) -> Result<(), CodegenError> {
+    native::write_object(codegen.module(), object, config.target_triple.as_deref(), options.optimize)?;
+    return native::link(slice::from_ref(&object.to_path_buf()), executable, &config.libraries, !options.debug_info);
+}
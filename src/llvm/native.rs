@@ -0,0 +1,130 @@
+// Turns an llvm module into an object file for the machine the compiler runs on, and links
+// object files into an executable with the system's c compiler, which knows where the c library
+// and the start up code are. CC picks another compiler than cc, like a cross compiler for a
+// config's target_triple.
+//
+// Optimized objects go through llvm's O3 pipeline before they're written, and a stripped
+// executable has no symbols or debug info left in it. That's what release builds are.
+
+use std::env;
+use std::ffi::{CStr, CString};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::ptr;
+use std::sync::Once;
+
+use llvm_sys::analysis::{LLVMVerifierFailureAction, LLVMVerifyModule};
+use llvm_sys::core::{LLVMCreateMessage, LLVMCreatePassManager, LLVMDisposeMessage, LLVMDisposePassManager, LLVMRunPassManager, LLVMSetTarget};
+use llvm_sys::prelude::LLVMModuleRef;
+use llvm_sys::target::{LLVM_InitializeAllAsmPrinters, LLVM_InitializeAllTargetInfos, LLVM_InitializeAllTargetMCs, LLVM_InitializeAllTargets};
+use llvm_sys::target_machine::*;
+use llvm_sys::transforms::pass_manager_builder::*;
+
+use crate::llvm::codegen_error::CodegenError;
+
+static INITIALIZE: Once = Once::new();
+
+fn output_error(message: String) -> CodegenError {
+    return CodegenError {
+        message,
+        file_name: String::new(),
+        span: None,
+    };
+}
+
+// takes the message that llvm allocated
+unsafe fn take_message(message: *mut i8) -> String {
+    if message.is_null() {
+        return String::new();
+    }
+    let result = CStr::from_ptr(message).to_string_lossy().into_owned();
+    LLVMDisposeMessage(message);
+    return result;
+}
+
+fn initialize() {
+    INITIALIZE.call_once(|| unsafe {
+        LLVM_InitializeAllTargetInfos();
+        LLVM_InitializeAllTargets();
+        LLVM_InitializeAllTargetMCs();
+        LLVM_InitializeAllAsmPrinters();
+    });
+}
+
+// the machine the compiler runs on, like x86_64-pc-linux-gnu
+pub fn default_target_triple() -> String {
+    unsafe {
+        return take_message(LLVMGetDefaultTargetTriple());
+    }
+}
+
+// for the target triple, or the machine the compiler runs on when there's none
+pub fn write_object(module: LLVMModuleRef, path: &Path, target_triple: Option<&str>, optimize: bool) -> Result<(), CodegenError> {
+    initialize();
+    let file_name = CString::new(path.to_string_lossy().as_bytes()).map_err(|_| output_error(format!("Unable to write {}", path.display())))?;
+    unsafe {
+        // a module that isn't valid is a bug in codegen.rs rather than in the program
+        let mut message = ptr::null_mut();
+        if LLVMVerifyModule(module, LLVMVerifierFailureAction::LLVMReturnStatusAction, &mut message) != 0 {
+            return Err(output_error(format!("Generated code isn't valid: {}", take_message(message))));
+        }
+        take_message(message);
+
+        let triple = match target_triple {
+            Some(target_triple) => match CString::new(target_triple) {
+                Ok(target_triple) => LLVMCreateMessage(target_triple.as_ptr()),
+                Err(_) => return Err(output_error(format!("No target for {}", target_triple))),
+            },
+            None => LLVMGetDefaultTargetTriple(),
+        };
+        let mut target = ptr::null_mut();
+        let mut message = ptr::null_mut();
+        if LLVMGetTargetFromTriple(triple, &mut target, &mut message) != 0 {
+            let triple_text = take_message(triple);
+            return Err(output_error(format!("No target for {}: {}", triple_text, take_message(message))));
+        }
+        if optimize {
+            let pass_manager = LLVMCreatePassManager();
+            let pass_manager_builder = LLVMPassManagerBuilderCreate();
+            LLVMPassManagerBuilderSetOptLevel(pass_manager_builder, 3);
+            LLVMPassManagerBuilderPopulateModulePassManager(pass_manager_builder, pass_manager);
+            LLVMPassManagerBuilderDispose(pass_manager_builder);
+            LLVMRunPassManager(pass_manager, module);
+            LLVMDisposePassManager(pass_manager);
+        }
+        let level = if optimize { LLVMCodeGenOptLevel::LLVMCodeGenLevelAggressive } else { LLVMCodeGenOptLevel::LLVMCodeGenLevelDefault };
+        let machine = LLVMCreateTargetMachine(target, triple, c_str!("generic"), c_str!(""),
+                                              level,
+                                              LLVMRelocMode::LLVMRelocPIC,
+                                              LLVMCodeModel::LLVMCodeModelDefault);
+        LLVMSetTarget(module, triple);
+        take_message(triple);
+        let failed = LLVMTargetMachineEmitToFile(machine, module, file_name.as_ptr() as *mut _,
+                                                 LLVMCodeGenFileType::LLVMObjectFile, &mut message);
+        LLVMDisposeTargetMachine(machine);
+        if failed != 0 {
+            return Err(output_error(format!("Unable to write {}: {}", path.display(), take_message(message))));
+        }
+    }
+    return Ok(());
+}
+
+// a library is a path when it has a slash in it, and otherwise a name for -l
+pub fn link(objects: &[PathBuf], executable: &Path, libraries: &[String], strip: bool) -> Result<(), CodegenError> {
+    let linker = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let libraries = libraries.iter().map(|library| {
+        if library.contains('/') || library.contains('\\') { library.clone() } else { format!("-l{}", library) }
+    });
+    let output = Command::new(&linker)
+        .args(objects)
+        .args(libraries)
+        .args(if strip { &["-s"][..] } else { &[] })
+        .arg("-o")
+        .arg(executable)
+        .output()
+        .map_err(|error| output_error(format!("Unable to run {}: {}", linker, error)))?;
+    if !output.status.success() {
+        return Err(output_error(format!("Failed to link {}: {}", executable.display(), String::from_utf8_lossy(&output.stderr).trim())));
+    }
+    return Ok(());
+}
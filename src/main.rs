@@ -1,11 +1,13 @@
 use std::env;
 
+mod config;
 mod goal;
 mod target;
 mod lex;
 mod transform;
 mod parse;
-//mod llvm;
+mod source;
+mod llvm;
 
 fn main() {
     let user_goal = goal::parse(env::args());
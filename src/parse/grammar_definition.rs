@@ -0,0 +1,172 @@
+// Reads a grammar written in the notation of docs/grammar.md, one rule to a line:
+//
+//   name: _token                         matches a token
+//   name: _token && _token               matches tokens in a row
+//   name: rule && rule                   matches each rule in turn
+//   name: rule || rule                   matches the first rule that matches
+//   name: (rule && rule)*                repeated: * any number, + at least once, ? at most once
+//   name: precedence(rule)               an expression, see precedence.rs
+//
+// Anything after // is a comment. && and || can't be mixed in one rule, and tokens and rules
+// can't be mixed in one rule, so that every line turns into exactly one RuleStruct.
+//
+// The rule names are slices of the definition itself, which is why it must be 'static. The Dog
+// grammar is compiled into the binary from parser_def.txt.
+
+use crate::parse::grammar_validation::GrammarError;
+use crate::parse::parse_rules::{create_and_rule, create_label_match, create_or_rule, create_precedence_rule, Grammar, RuleRepeats, RuleStruct};
+
+pub fn read_grammar(definition: &'static str, start: &'static str) -> Result<Grammar, Vec<GrammarError>> {
+    let mut result = Grammar::new(start);
+    let mut errors = vec![];
+    for (index, line) in definition.lines().enumerate() {
+        let text = match line.find("//") {
+            Some(comment_start) => &line[..comment_start],
+            None => line,
+        }.trim();
+        if text.is_empty() {
+            continue;
+        }
+        match read_rule(text) {
+            Ok((name, rule)) => result.insert(name, rule),
+            Err(reason) => errors.push(GrammarError::MalformedRule { line_number: index + 1, line: line.trim(), reason }),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    return Ok(result);
+}
+
+fn read_rule(text: &'static str) -> Result<(&'static str, Box<RuleStruct>), &'static str> {
+    let (name, body) = text.split_once(':').ok_or("expected `name: rule`")?;
+    let name = name.trim();
+    let body = body.trim();
+    if !is_name(name) {
+        return Err("the rule name isn't a name");
+    }
+
+    if let Some(operand) = body.strip_prefix("precedence(").and_then(|rest| rest.strip_suffix(')')) {
+        let operand = operand.trim();
+        if !is_name(operand) || operand.starts_with('_') {
+            return Err("precedence takes the name of one rule");
+        }
+        return Ok((name, create_precedence_rule(operand)));
+    }
+
+    let (body, repeat) = match body.chars().last() {
+        Some('*') => (&body[..body.len() - 1], RuleRepeats::ZeroOrMore),
+        Some('+') => (&body[..body.len() - 1], RuleRepeats::OneOrMore),
+        Some('?') => (&body[..body.len() - 1], RuleRepeats::ZeroOrOne),
+        _ => (body, RuleRepeats::Once),
+    };
+    let body = body.trim();
+    let body = match body.strip_prefix('(').and_then(|rest| rest.strip_suffix(')')) {
+        Some(inside) => inside.trim(),
+        None => body,
+    };
+
+    if body.contains("&&") && body.contains("||") {
+        return Err("&& and || can't be mixed in one rule");
+    }
+    let or = body.contains("||");
+    let children: Vec<&'static str> = body.split(if or { "||" } else { "&&" }).map(str::trim).collect();
+    if !children.iter().all(|child| is_name(child)) {
+        return Err("expected names joined by && or ||");
+    }
+
+    let tokens = children.iter().filter(|child| child.starts_with('_')).count();
+    if tokens == 0 {
+        let rule = if or { create_or_rule(repeat, children) } else { create_and_rule(repeat, children) };
+        return Ok((name, rule));
+    }
+    if tokens < children.len() {
+        return Err("tokens and rules can't be mixed in one rule");
+    }
+    if or || !matches!(repeat, RuleRepeats::Once) {
+        return Err("tokens can only be matched in a row, once");
+    }
+    return Ok((name, create_label_match(children)));
+}
+
+fn is_name(text: &str) -> bool {
+    return !text.is_empty() && text.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+}
+
+#[cfg(test)]
+mod grammar_definition_tests {
+    use crate::lex::lex_code;
+    use crate::parse::grammar_definition::read_grammar;
+    use crate::parse::grammar_validation::GrammarError;
+    use crate::parse::parse_rules::{Parser, RuleRepeats, RuleType};
+
+    #[test]
+    fn read_and_parse_with_a_definition() {
+        let definition = "
+            // a list of names
+            open_bracket: _open_bracket
+            close_bracket: _close_bracket
+            comma: _comma
+            item: _word || _number_literal    // only rules can be alternatives
+        ";
+        assert_eq!(vec![GrammarError::MalformedRule {
+            line_number: 6,
+            line: "item: _word || _number_literal    // only rules can be alternatives",
+            reason: "tokens can only be matched in a row, once",
+        }], read_grammar(definition, "item").err().unwrap());
+
+        let errors = read_grammar("items: (item && comma?)*", "items").err().unwrap();
+        assert_eq!("line 1: expected names joined by && or || [items: (item && comma?)*]", errors[0].to_string());
+
+        let definition = "
+            open_bracket: _open_bracket
+            close_bracket: _close_bracket
+            comma: _comma
+            optional_comma: comma?
+            item: _word
+            items: (item && optional_comma)*
+            list: open_bracket && items && close_bracket
+        ";
+        let grammar = read_grammar(definition, "list").unwrap();
+        assert!(grammar.validate().is_empty());
+        let mut token_stream = lex_code("[a, b c]").unwrap();
+        let list = Parser::new(&grammar).match_document(&mut token_stream).unwrap();
+        assert_eq!(3, list.children[1].children.len());
+        assert!(!token_stream.has_next());
+    }
+
+    #[test]
+    fn read_every_kind_of_rule() {
+        let definition = "
+            plus: _plus
+            double_plus: _plus && _plus
+            operand: _word
+            sum: precedence(operand)
+            sums: (sum && double_plus)+
+            maybe: sum?
+            either: sums || maybe
+        ";
+        let grammar = read_grammar(definition, "either").unwrap();
+        let kind = |name: &str| {
+            let rule = grammar.get(name).unwrap();
+            (rule.rule_type.clone(), rule.repeat.clone(), rule.children.clone())
+        };
+        assert_eq!((RuleType::Match, RuleRepeats::Once, vec![]), kind("double_plus"));
+        assert_eq!((RuleType::Precedence, RuleRepeats::Once, vec!["operand"]), kind("sum"));
+        assert_eq!((RuleType::And, RuleRepeats::OneOrMore, vec!["sum", "double_plus"]), kind("sums"));
+        assert_eq!((RuleType::And, RuleRepeats::ZeroOrOne, vec!["sum"]), kind("maybe"));
+        assert_eq!((RuleType::Or, RuleRepeats::Once, vec!["sums", "maybe"]), kind("either"));
+
+        for (rule, error) in [
+            ("a b: _word", "the rule name isn't a name"),
+            ("a _word", "expected `name: rule`"),
+            ("a: precedence(_word)", "precedence takes the name of one rule"),
+            ("a: b && c || d", "&& and || can't be mixed in one rule"),
+            ("a: b && _word", "tokens and rules can't be mixed in one rule"),
+            ("a: _word*", "tokens can only be matched in a row, once"),
+        ] {
+            assert_eq!(GrammarError::MalformedRule { line_number: 1, line: rule, reason: error },
+                       read_grammar(rule, "a").err().unwrap()[0]);
+        }
+    }
+}
@@ -0,0 +1,234 @@
+// Checks a grammar for mistakes that the parser would otherwise only find when a document
+// happens to take the path with the mistake on it, or never find at all: a rule that isn't
+// defined panics, a rule that calls itself before matching a token loops forever, and a rule
+// that is defined twice or that nothing uses is dead weight.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use crate::parse::parse_rules::{Grammar, RuleRepeats, RuleStruct, RuleType};
+use crate::parse::precedence;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GrammarError {
+    // a rule uses one that isn't defined
+    UndefinedRule { rule: &'static str, child: &'static str },
+    // the start rule can never get to this rule
+    UnreachableRule(&'static str),
+    // the rule was inserted more than once, and all but the last were thrown away
+    DuplicateRule(&'static str),
+    // the rules can get back to the first one without matching a token, so they never end
+    LeftRecursion(Vec<&'static str>),
+    // a line of a grammar definition that isn't a rule, see grammar_definition.rs
+    MalformedRule { line_number: usize, line: &'static str, reason: &'static str },
+}
+
+impl fmt::Display for GrammarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GrammarError::UndefinedRule { rule, child } => write!(f, "{} uses {}, which isn't defined", rule, child),
+            GrammarError::UnreachableRule(rule) => write!(f, "{} can't be reached from the start rule", rule),
+            GrammarError::DuplicateRule(rule) => write!(f, "{} is defined more than once", rule),
+            GrammarError::LeftRecursion(rules) => write!(f, "left recursion: {}", rules.join(" -> ")),
+            GrammarError::MalformedRule { line_number, line, reason } => write!(f, "line {}: {} [{}]", line_number, reason, line),
+        }
+    }
+}
+
+impl Grammar {
+    // every problem with the grammar, or nothing if it is fine
+    pub fn validate(&self) -> Vec<GrammarError> {
+        let mut result = vec![];
+        let mut names: Vec<&'static str> = self.rules.keys().copied().collect();
+        names.sort();
+
+        let mut duplicates = self.duplicates.clone();
+        duplicates.sort();
+        duplicates.dedup();
+        result.extend(duplicates.into_iter().map(GrammarError::DuplicateRule));
+
+        if !self.rules.contains_key(self.start) {
+            result.push(GrammarError::UndefinedRule { rule: "start", child: self.start });
+        }
+        for name in &names {
+            for child in uses(&self.rules[name]) {
+                if !self.rules.contains_key(child) {
+                    result.push(GrammarError::UndefinedRule { rule: name, child });
+                }
+            }
+        }
+
+        // every lexer token has a rule of its own, whether anything uses it or not
+        let reachable = self.reachable();
+        for name in &names {
+            let token_rule = matches!(self.rules[name].rule_type, RuleType::Match);
+            if !reachable.contains(name) && !token_rule {
+                result.push(GrammarError::UnreachableRule(name));
+            }
+        }
+
+        result.extend(self.left_recursion(&names).into_iter().map(GrammarError::LeftRecursion));
+        return result;
+    }
+
+    fn reachable(&self) -> HashSet<&'static str> {
+        let mut result = HashSet::new();
+        let mut queue = VecDeque::from([self.start]);
+        queue.extend(&self.unused);
+        while let Some(name) = queue.pop_front() {
+            if !result.insert(name) {
+                continue;
+            }
+            if let Some(rule) = self.rules.get(name) {
+                queue.extend(uses(rule));
+            }
+        }
+        return result;
+    }
+
+    // rules that can match without using up a token
+    fn nullable(&self) -> HashSet<&'static str> {
+        let mut result = HashSet::new();
+        loop {
+            let mut changed = false;
+            for (name, rule) in &self.rules {
+                if result.contains(name) {
+                    continue;
+                }
+                let optional = matches!(rule.repeat, RuleRepeats::ZeroOrMore | RuleRepeats::ZeroOrOne);
+                let empty = match rule.rule_type {
+                    RuleType::Match => false,
+                    RuleType::And => rule.children.iter().all(|child| result.contains(child)),
+                    RuleType::Or | RuleType::Precedence => rule.children.iter().any(|child| result.contains(child)),
+                };
+                if optional || empty {
+                    result.insert(*name);
+                    changed = true;
+                }
+            }
+            if !changed {
+                return result;
+            }
+        }
+    }
+
+    // the rules that a rule can try before it has matched a token
+    fn left_edges(&self, nullable: &HashSet<&'static str>) -> HashMap<&'static str, Vec<&'static str>> {
+        let mut result = HashMap::new();
+        for (name, rule) in &self.rules {
+            let mut edges = vec![];
+            match rule.rule_type {
+                RuleType::Match => {}
+                RuleType::Or => edges.extend(&rule.children),
+                RuleType::And => {
+                    for child in &rule.children {
+                        edges.push(*child);
+                        if !nullable.contains(child) {
+                            break;
+                        }
+                    }
+                }
+                RuleType::Precedence => {
+                    edges.extend(&rule.children);
+                    edges.extend(precedence::prefix_rules());
+                }
+            }
+            result.insert(*name, edges);
+        }
+        return result;
+    }
+
+    // each cycle of rules that can get back to where they started without matching a token,
+    // starting from its first rule by name
+    fn left_recursion(&self, names: &[&'static str]) -> Vec<Vec<&'static str>> {
+        let left_edges = self.left_edges(&self.nullable());
+        let mut result = vec![];
+        let mut in_cycle: HashSet<&'static str> = HashSet::new();
+        for name in names {
+            if in_cycle.contains(name) {
+                continue;
+            }
+            // breadth first, so that the shortest way back is the one reported
+            let mut came_from: HashMap<&'static str, &'static str> = HashMap::new();
+            let mut queue = VecDeque::from([*name]);
+            let mut found = false;
+            while let Some(current) = queue.pop_front() {
+                for next in left_edges.get(current).into_iter().flatten() {
+                    if next == name {
+                        came_from.insert(name, current);
+                        found = true;
+                        break;
+                    }
+                    if !came_from.contains_key(next) {
+                        came_from.insert(next, current);
+                        queue.push_back(next);
+                    }
+                }
+                if found {
+                    break;
+                }
+            }
+            if !found {
+                continue;
+            }
+            let mut cycle = vec![*name];
+            let mut current = came_from[name];
+            while current != *name {
+                cycle.push(current);
+                current = came_from[current];
+            }
+            cycle.push(name);
+            cycle.reverse();
+            in_cycle.extend(&cycle);
+            result.push(cycle);
+        }
+        return result;
+    }
+}
+
+// the names of the rules that a rule uses
+fn uses(rule: &RuleStruct) -> Vec<&'static str> {
+    let mut result = rule.children.clone();
+    if let RuleType::Precedence = rule.rule_type {
+        result.extend(precedence::referenced_rules());
+    }
+    return result;
+}
+
+#[cfg(test)]
+mod grammar_validation_tests {
+    use crate::parse::grammar_validation::GrammarError;
+    use crate::parse::parse_rules::{build_rules, create_and_rule, create_and_rule_once, create_label_match,
+                                    create_or_rule_once, Grammar, RuleRepeats};
+
+    #[test]
+    fn dog_grammar_is_valid() {
+        let errors: Vec<String> = build_rules().validate().iter().map(|error| error.to_string()).collect();
+        assert!(errors.is_empty(), "{:#?}", errors);
+    }
+
+    #[test]
+    fn find_every_kind_of_mistake() {
+        let mut grammar = Grammar::new("list");
+        grammar.insert("comma", create_label_match(vec!["_comma"]));
+        grammar.insert("comma", create_label_match(vec!["_comma"]));
+        grammar.insert("item", create_label_match(vec!["_word"]));
+        grammar.insert("unused_token", create_label_match(vec!["_plus"]));
+        grammar.insert("list", create_or_rule_once(vec!["nested_list", "items"]));
+        grammar.insert("items", create_and_rule(RuleRepeats::ZeroOrMore, vec!["item", "comma", "missing"]));
+        // a list may start with a list before it has read anything
+        grammar.insert("nested_list", create_and_rule_once(vec!["items", "list", "comma"]));
+        grammar.insert("orphan", create_and_rule_once(vec!["item", "comma"]));
+        grammar.insert("self_first", create_and_rule_once(vec!["self_first", "item"]));
+        grammar.allow_unused(vec!["self_first"]);
+
+        assert_eq!(vec![
+            GrammarError::DuplicateRule("comma"),
+            GrammarError::UndefinedRule { rule: "items", child: "missing" },
+            GrammarError::UnreachableRule("orphan"),
+            GrammarError::LeftRecursion(vec!["list", "nested_list", "list"]),
+            GrammarError::LeftRecursion(vec!["self_first", "self_first"]),
+        ], grammar.validate());
+        assert_eq!("left recursion: list -> nested_list -> list", grammar.validate()[3].to_string());
+    }
+}
@@ -2,13 +2,23 @@
 // takes the token stream from the lexer and translates it into a structure that it can give
 // to the analyzer
 
+use std::sync::OnceLock;
+
 use crate::lex::token_stream::TokenStream;
 use crate::parse::parse_model::ParseModel;
-use crate::parse::parse_rules::match_document;
-use crate::UNKNOWN;
+use crate::parse::parse_rules::{build_config_rules, build_rules, Grammar, Parser};
+use crate::parse::syntax_error::{END_OF_FILE, SyntaxError};
+use crate::parse::trivia::attach_trivia;
+use crate::source::FileId;
 
-mod parse_model;
+mod grammar_definition;
+mod grammar_validation;
+pub(crate) mod parse_model;
 mod parse_rules;
+mod precedence;
+pub(crate) mod syntax_error;
+pub(crate) mod trace;
+mod trivia;
 
 
 // In my first attempt at writing a parser in Rust, I tried an OO model like I did with the
@@ -55,30 +65,111 @@ mod parse_rules;
 // is not worth it. The recursive nature of this problem doesn't suit Rust well in a way
 // that also performs well and doesn't require unreusable code. The table lookup method is more
 // or less creating our own, slower, weak reference.
+//
+// The table started out as a static global. It is now a Grammar value that parsers borrow, so
+// that other grammars can be built next to it and files can be parsed on several threads.
+
+static DOG_GRAMMAR: OnceLock<Grammar> = OnceLock::new();
+
+pub fn dog_grammar() -> &'static Grammar {
+    return DOG_GRAMMAR.get_or_init(build_rules);
+}
+
+static CONFIG_GRAMMAR: OnceLock<Grammar> = OnceLock::new();
+
+// the grammar of a build plan like config.dog, which only holds config declarations
+pub fn config_grammar() -> &'static Grammar {
+    return CONFIG_GRAMMAR.get_or_init(build_config_rules);
+}
+
+// A file that doesn't match is reported at the furthest token that any rule got to, since the
+// rules that backtracked to an earlier token only did so because of what they found there.
+#[allow(dead_code)]
+pub fn parse(mut token_stream: Box<TokenStream>, file_id: FileId) -> Result<Box<ParseModel>, SyntaxError> {
+    let result = parse_document(&mut Parser::new(dog_grammar()), &mut token_stream, file_id)?;
+    return Ok(Box::new(result));
+}
+
+// Parses a token stream from lex_lossless into a model that has every byte of the file: each
+// token holds the whitespace and comments around it as trivia, see trivia.rs.
+#[allow(dead_code)]
+pub fn parse_lossless(mut token_stream: Box<TokenStream>, file_id: FileId) -> Result<Box<ParseModel>, SyntaxError> {
+    let mut result = parse_document(&mut Parser::new(dog_grammar()), &mut token_stream, file_id)?;
+    attach_trivia(&mut result, &token_stream);
+    return Ok(Box::new(result));
+}
+
+// Parses a file and reports every syntax error in it, not just the first. The parts that don't
+// match are skipped and left in the model as `error` nodes. There is no model if the file still
+// doesn't match after skipping them.
+pub fn parse_with_recovery(token_stream: Box<TokenStream>, file_id: FileId) -> (Option<Box<ParseModel>>, Vec<SyntaxError>) {
+    return recover(dog_grammar(), token_stream, file_id);
+}
+
+// The same as parse_with_recovery, for a build plan. Code in a build plan is an error.
+pub fn parse_config_with_recovery(token_stream: Box<TokenStream>, file_id: FileId) -> (Option<Box<ParseModel>>, Vec<SyntaxError>) {
+    return recover(config_grammar(), token_stream, file_id);
+}
 
-// todo: right now, this is only using the dog grammar, but we need to add a function or enum
-// that supports the SQL grammar.
-pub fn parse(mut token_stream: Box<TokenStream>, file_name: Option<&str>, file_path: Option<&str>) -> Option<Box<ParseModel>> {
-    let real_file_name = String::from(file_name.unwrap_or(UNKNOWN));
-    let real_file_path = String::from(file_path.unwrap_or(UNKNOWN));
-    println!("Parsing: {} ({})", real_file_name, real_file_path);
+fn recover(grammar: &Grammar, mut token_stream: Box<TokenStream>, file_id: FileId) -> (Option<Box<ParseModel>>, Vec<SyntaxError>) {
+    let start = token_stream.position();
+    let first_error = match parse_document(&mut Parser::new(grammar), &mut token_stream, file_id) {
+        Ok(result) => return (Some(Box::new(result)), vec![]),
+        Err(first_error) => first_error,
+    };
 
-    println!("{:?}", token_stream);
+    token_stream.restore(start);
+    let mut parser = Parser::with_recovery(grammar);
+    let result = parse_document(&mut parser, &mut token_stream, file_id);
+    let mut errors = vec![];
+    if let Ok(result) = &result {
+        collect_errors(result, &parser, &token_stream, file_id, &mut errors);
+    }
+    if let Err(last_error) = &result {
+        errors.push(last_error.clone());
+    }
+    if errors.is_empty() {
+        errors.push(first_error);
+    }
+    errors.sort_by_key(|error| error.start);
+    errors.dedup_by_key(|error| error.start);
+    return (result.ok().map(Box::new), errors);
+}
+
+fn collect_errors(model: &ParseModel, parser: &Parser, token_stream: &TokenStream, file_id: FileId, errors: &mut Vec<SyntaxError>) {
+    if model.label == "error" {
+        if let Some((found_offset, expected)) = parser.recovered_error(model.tokens[0].start_byte_offset) {
+            errors.push(SyntaxError::new(expected, found_offset, token_stream, file_id));
+        }
+    }
+    for child in &model.children {
+        collect_errors(child, parser, token_stream, file_id, errors);
+    }
+}
+
+fn parse_document(parser: &mut Parser, token_stream: &mut TokenStream, file_id: FileId) -> Result<ParseModel, SyntaxError> {
+    let result = parser.match_document(token_stream);
+    let mut expected: Vec<&str> = vec![];
+    let mut found_offset = token_stream.matches.len();
+    if let Some((offset, labels)) = parser.furthest_failure() {
+        expected.extend(labels);
+        found_offset = offset;
+    }
 
-    if let Some(result) = match_document(&mut token_stream) {
-        if !token_stream.has_next() {
-            println!("Parsed successfully: {} ({})", real_file_name, real_file_path);
-            return Some(Box::new(result));
+    if let Some(result) = result {
+        let leftover_offset = match token_stream.next_offset() {
+            Some(leftover_offset) => leftover_offset,
+            None => return Ok(result),
+        };
+        // the document ended before the file did. the file could also have ended there.
+        if parser.furthest_failure().is_none_or(|(offset, _)| offset < leftover_offset) {
+            expected.clear();
+            found_offset = leftover_offset;
         }
-        if let Some(last_consumed) = token_stream.last_consumed() {
-            println!("Syntax error at token {} \"{}\" found at line {} offset {}.",
-                     last_consumed.label, last_consumed.value,
-                     last_consumed.line_number, last_consumed.line_offset);
-        } else {
-            println!("Unknown parsing error."); // improve this message with diagnostic info.
+        if found_offset == leftover_offset {
+            expected.push(END_OF_FILE);
         }
     }
 
-    println!("Parsing failed for: {} ({})", real_file_name, real_file_path);
-    return None;
-}
\ No newline at end of file
+    return Err(SyntaxError::new(&expected, found_offset, token_stream, file_id));
+}
@@ -1,14 +1,27 @@
+use std::sync::Arc;
+
 use crate::lex::token_stream::Match;
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct ParseModel {
     pub label: String,
     pub tokens: Vec<Box<Match>>,
-    pub children: Vec<Box<ParseModel>>,
+    pub children: Vec<Arc<ParseModel>>,
+    // one for each of tokens when the file was parsed with parse_lossless, otherwise empty
+    pub trivia: Vec<Trivia>,
+}
+
+// The whitespace, line ends and comments around a token. A token's trailing trivia runs to the end
+// of its line, and everything after that up to the next token leads the next token. Whatever comes
+// after the last token in the file trails it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trivia {
+    pub leading: Vec<Box<Match>>,
+    pub trailing: Vec<Box<Match>>,
 }
 
 impl ParseModel {
-    #[allow(dead_code)]
-    fn get_children(&self, name: &str) -> Vec<&Box<ParseModel>> {
+    pub(crate) fn get_children(&self, name: &str) -> Vec<&Arc<ParseModel>> {
         let result = self.children.iter()
             .filter(|model| model.label.eq(name))
             .collect();
@@ -16,8 +29,7 @@ impl ParseModel {
         return result;
     }
 
-    #[allow(dead_code)]
-    fn get_child(&self, name: &str) -> Option<&ParseModel> {
+    pub(crate) fn get_child(&self, name: &str) -> Option<&ParseModel> {
         if let Some(result) = self.get_children(name).first() {
             return Some(*result);
         }
@@ -25,6 +37,60 @@ impl ParseModel {
         return None;
     }
 
+    // The text of the doc comments (`///` or `//!`) attached to this declaration, without the
+    // comment markers.
+    pub(crate) fn doc_comments(&self) -> Vec<String> {
+        let mut result = vec![];
+        if let Some(doc_comments) = self.get_child("optional_doc_comments") {
+            let mut tokens = vec![];
+            doc_comments.collect_tokens(&mut tokens);
+            for token in tokens {
+                let text = token.value.trim_end_matches(|c| c == '\n' || c == '\r');
+                let text = text.trim_start_matches("///").trim_start_matches("//!");
+                result.push(text.strip_prefix(' ').unwrap_or(text).to_string());
+            }
+        }
+        return result;
+    }
+
+    pub(crate) fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a Match>) {
+        for token in &self.tokens {
+            tokens.push(token);
+        }
+        for child in &self.children {
+            child.collect_tokens(tokens);
+        }
+    }
+
+    // The text of the file that the model covers, exactly as it is in source, the text of the
+    // file. Only a model made by parse_lossless has what's between the tokens.
+    #[allow(dead_code)]
+    pub fn source_text(&self, source: &str) -> String {
+        let mut tokens = vec![];
+        self.collect_tokens_with_trivia(&mut tokens);
+        let mut result = String::new();
+        for token in tokens {
+            result += &source[token.start_byte_offset..token.end_byte_offset];
+        }
+        return result;
+    }
+
+    fn collect_tokens_with_trivia<'a>(&'a self, tokens: &mut Vec<&'a Match>) {
+        for (index, token) in self.tokens.iter().enumerate() {
+            let trivia = self.trivia.get(index);
+            if let Some(trivia) = trivia {
+                tokens.extend(trivia.leading.iter().map(|trivia| trivia.as_ref()));
+            }
+            tokens.push(token);
+            if let Some(trivia) = trivia {
+                tokens.extend(trivia.trailing.iter().map(|trivia| trivia.as_ref()));
+            }
+        }
+        for child in &self.children {
+            child.collect_tokens_with_trivia(tokens);
+        }
+    }
+
     #[allow(dead_code)]
     fn as_text(&self) -> Option<String> {
         if self.children.is_empty() || self.tokens.is_empty() {
@@ -59,40 +125,49 @@ impl ParseModel {
 
 #[cfg(test)]
 mod parse_model_tests {
-    use crate::lex::lex;
+    use std::sync::Arc;
+
+    use crate::lex::{lex, lex_code};
+    use crate::parse::parse;
     use crate::parse::parse_model::ParseModel;
+    use crate::source::SourceMap;
 
     #[test]
     fn test_children_1() {
-        let code = lex("Hello there,", None, None).unwrap();
-        let code2 = lex("world!", None, None).unwrap();
+        let code = lex_code("Hello there,").unwrap();
+        let code2 = lex_code("world!").unwrap();
 
         let pm = ParseModel {
             label: "my label".to_string(),
             tokens: code.matches,
             children: vec![
-                Box::new(ParseModel {
+                Arc::new(ParseModel {
                     label: "other label".to_string(),
                     tokens: code2.matches,
                     children: vec![
-                        Box::new(ParseModel {
+                        Arc::new(ParseModel {
                             label: "nothing".to_string(),
                             tokens: vec![],
                             children: vec![],
+                            trivia: vec![],
                         })
                     ],
+                    trivia: vec![],
                 }),
-                Box::new(ParseModel {
+                Arc::new(ParseModel {
                     label: "other label".to_string(),
                     tokens: vec![],
                     children: vec![],
+                    trivia: vec![],
                 }),
-                Box::new(ParseModel {
+                Arc::new(ParseModel {
                     label: "other label".to_string(),
                     tokens: vec![],
                     children: vec![],
+                    trivia: vec![],
                 })
             ],
+            trivia: vec![],
         };
 
         // ParseModel isn't realistic, so the output is just as unrealistic. We're just proving that
@@ -105,4 +180,20 @@ mod parse_model_tests {
         assert!(pm.get_child("garbage").is_none());
         assert_eq!(3, pm.get_children("other label").len());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_doc_comments() {
+        let mut source_map = SourceMap::new();
+        let file_id = source_map.add_file(None, None,
+                                          "/// The base configuration.\n///\n/// Everything extends it.\nconfig Base { version: \"1\" }\nconfig Release : Base {}".to_string());
+        let token_stream = lex(&source_map, file_id).unwrap();
+        let document = parse(token_stream, file_id).unwrap();
+        let config_decls = document.get_children("config_document");
+        assert_eq!(2, config_decls.len());
+
+        let base = config_decls[0].get_child("config_decl").unwrap();
+        assert_eq!(vec!["The base configuration.", "", "Everything extends it."], base.doc_comments());
+        let release = config_decls[1].get_child("config_decl").unwrap();
+        assert!(release.doc_comments().is_empty());
+    }
+}
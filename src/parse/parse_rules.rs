@@ -1,16 +1,23 @@
 use std::collections::HashMap;
-use std::sync::Once;
+use std::sync::Arc;
 
-use crate::lex::token_stream::TokenStream;
+use crate::lex::token_stream::{Match, StreamPosition, TokenStream};
+use crate::parse::grammar_definition::read_grammar;
+use crate::parse::grammar_validation::GrammarError;
 use crate::parse::parse_model::ParseModel;
+use crate::parse::trace::{default_tracer, Tracer};
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum RuleType {
     And,
     Or,
     Match,
+    // an expression of operands joined by operators, see precedence.rs
+    Precedence,
 }
 
 #[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum RuleRepeats {
     Once,
     ZeroOrMore,
@@ -19,658 +26,532 @@ pub enum RuleRepeats {
 }
 
 pub struct RuleStruct {
-    rule_type: RuleType,
-    repeat: RuleRepeats,
-    children: Vec<&'static str>,
+    pub(super) rule_type: RuleType,
+    pub(super) repeat: RuleRepeats,
+    pub(super) children: Vec<&'static str>,
     match_labels: Vec<&'static str>,
 }
 
-static mut RULES: Option<HashMap<&'static str, Box<RuleStruct>>> = None;
+// The rules of a language, by name. Rules refer to each other by name, so a grammar can be built
+// in any order and shared by any number of parsers, on any number of threads.
+pub struct Grammar {
+    // the rule that matches a whole document
+    pub start: &'static str,
+    pub(super) rules: HashMap<&'static str, Box<RuleStruct>>,
+    // rules that were inserted more than once. the last one wins, see validate.
+    pub(super) duplicates: Vec<&'static str>,
+    // rules that no document can use yet, which validate doesn't report as unreachable
+    pub(super) unused: Vec<&'static str>,
+    recoveries: HashMap<&'static str, Recovery>,
+}
 
-static RULES_INIT: Once = Once::new();
+// How a repeated rule like the statements of a block gets past an item that doesn't match. The
+// tokens are skipped up to one that an item could start with, and the rule carries on from there.
+pub struct Recovery {
+    // tokens that end the repeated rule, like the `}` after the statements of a block
+    stop: Vec<&'static str>,
+    // tokens that an item could start with, like `let`. the first token on a line is one too.
+    sync: Vec<&'static str>,
+}
 
-pub fn match_document(token_stream: &mut TokenStream) -> Option<ParseModel> {
-    unsafe {
-        RULES_INIT.call_once(|| {
-            RULES = build_rules();
-        });
+// the furthest token offset that a token rule failed on, and the labels that it wanted there
+type Failure = (Option<usize>, Vec<&'static str>);
+
+impl Grammar {
+    pub fn new(start: &'static str) -> Grammar {
+        return Grammar {
+            start,
+            rules: HashMap::new(),
+            duplicates: vec![],
+            unused: vec![],
+            recoveries: HashMap::new(),
+        };
+    }
+
+    pub fn insert(&mut self, name: &'static str, rule: Box<RuleStruct>) {
+        if self.rules.insert(name, rule).is_some() {
+            self.duplicates.push(name);
+        }
+    }
 
-        return match_rule_by_name("document", token_stream);
+    pub fn get(&self, name: &str) -> Option<&RuleStruct> {
+        return self.rules.get(name).map(|rule| rule.as_ref());
+    }
+
+    // rules for features that the rest of the grammar doesn't use yet
+    pub fn allow_unused(&mut self, names: Vec<&'static str>) {
+        self.unused.extend(names);
+    }
+
+    // lets a rule that repeats any number of times skip the items that don't match
+    pub fn recover(&mut self, name: &'static str, stop: Vec<&'static str>, sync: Vec<&'static str>) {
+        self.recoveries.insert(name, Recovery {
+            stop,
+            sync,
+        });
     }
 }
 
-unsafe fn match_rule_by_name(label: &str, token_stream: &mut TokenStream) -> Option<ParseModel> {
-    if let Some(rules) = &RULES {
-        if let Some(rule) = rules.get(label) {
-            return match_with(label, rule, token_stream);
-        } else {
-            panic!("!!! Rule not found: {} !!!", label);
+// Packrat parsing: the or rules backtrack and try their next child from the same place, so without
+// help the same rule is matched at the same token over and over, and nested expressions take
+// exponential time. Every result is remembered by rule name and position for the rest of the
+// parse, so each rule is only matched once at each token.
+pub struct Parser<'a> {
+    grammar: &'a Grammar,
+    memoize: bool,
+    memo: HashMap<(&'static str, StreamPosition), MemoizedMatch>,
+    // how many times a rule was actually matched, rather than found in the memo
+    pub rule_evaluations: usize,
+    // the offset of the furthest token that a token rule failed on, and the labels that the
+    // token rules wanted there. this is where a syntax error is reported.
+    furthest_failure: Option<usize>,
+    expected: Vec<&'static str>,
+    // whether the rules with a Recovery skip the items that don't match, see with_recovery
+    recovering: bool,
+    // the failures that were skipped, by the byte offset of the first skipped token
+    recovered: HashMap<usize, (usize, Vec<&'static str>)>,
+    // see trace.rs
+    tracer: Option<Tracer>,
+}
+
+// where the token stream was left after matching a rule, so that a remembered match can move the
+// stream just like matching again would. the model is shared with every node that holds the match,
+// a rule that's found in the memo doesn't copy what it matched.
+struct MemoizedMatch {
+    result: Option<Arc<ParseModel>>,
+    position: StreamPosition,
+    last_consumed_offset: Option<usize>,
+    // the furthest failure while matching, to report again when the match is reused
+    failure: Failure,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(grammar: &'a Grammar) -> Parser<'a> {
+        return Parser {
+            grammar,
+            memoize: true,
+            memo: HashMap::new(),
+            rule_evaluations: 0,
+            furthest_failure: None,
+            expected: vec![],
+            recovering: false,
+            recovered: HashMap::new(),
+            tracer: default_tracer(),
+        };
+    }
+
+    // Matches a document that has syntax errors in it. Where an item of a rule with a Recovery
+    // doesn't match, the tokens up to the next item go into an `error` node instead, and the
+    // failure is kept for recovered_error. This is only for documents that are already known to
+    // be wrong: an or rule takes the first child that matches, and with recovery a child might
+    // match with errors where a later one would have matched without any.
+    pub fn with_recovery(grammar: &'a Grammar) -> Parser<'a> {
+        let mut result = Parser::new(grammar);
+        result.recovering = true;
+        return result;
+    }
+
+    // traces every rule that the parser tries, see trace.rs
+    #[allow(dead_code)]
+    pub fn with_tracer(grammar: &'a Grammar, tracer: Tracer) -> Parser<'a> {
+        let mut result = Parser::new(grammar);
+        result.tracer = Some(tracer);
+        return result;
+    }
+
+    // matches every rule from scratch, however often it was matched before at the same token.
+    #[allow(dead_code)]
+    pub fn without_memo(grammar: &'a Grammar) -> Parser<'a> {
+        let mut result = Parser::new(grammar);
+        result.memoize = false;
+        return result;
+    }
+
+    pub fn match_document(&mut self, token_stream: &mut TokenStream) -> Option<ParseModel> {
+        // remembered matches are only good for the token stream they were made on
+        self.memo.clear();
+        self.furthest_failure = None;
+        self.expected.clear();
+        self.recovered.clear();
+        let result = self.match_rule_by_name(self.grammar.start, token_stream);
+        // once the memo lets go of them, the nodes of the document are only held by the document
+        self.memo.clear();
+        return result.map(|result| Arc::try_unwrap(result).unwrap_or_else(|shared| (*shared).clone()));
+    }
+
+    // the furthest token offset that the last document failed to match at, and the token labels
+    // that would have matched there. the offset is past the last token at the end of the file.
+    pub fn furthest_failure(&self) -> Option<(usize, &[&'static str])> {
+        return self.furthest_failure.map(|offset| (offset, self.expected.as_slice()));
+    }
+
+    // the failure that was skipped over by the `error` node whose first token starts at this byte
+    pub fn recovered_error(&self, start_byte_offset: usize) -> Option<(usize, &[&'static str])> {
+        return self.recovered.get(&start_byte_offset).map(|(offset, expected)| (*offset, expected.as_slice()));
+    }
+
+    // starts tracking failures from nothing, so that the ones made by a single rule can be told
+    // apart. give the result to restore_failure afterwards.
+    fn take_failure(&mut self) -> Failure {
+        return (self.furthest_failure.take(), std::mem::take(&mut self.expected));
+    }
+
+    // goes back to the failure from take_failure, and returns the one made since then
+    fn restore_failure(&mut self, outer: Failure) -> Failure {
+        let inner = (self.furthest_failure, std::mem::take(&mut self.expected));
+        (self.furthest_failure, self.expected) = outer;
+        return inner;
+    }
+
+    fn merge_failure(&mut self, failure: &Failure) {
+        if let (Some(offset), expected) = failure {
+            for label in expected {
+                self.record_failure(*offset, label);
+            }
         }
     }
 
-    return None;
-}
+    fn record_failure(&mut self, offset: usize, expected: &'static str) {
+        if self.furthest_failure.is_none_or(|furthest| offset > furthest) {
+            self.furthest_failure = Some(offset);
+            self.expected.clear();
+        }
+        if self.furthest_failure == Some(offset) && !self.expected.contains(&expected) {
+            self.expected.push(expected);
+        }
+    }
 
-unsafe fn match_with(label: &str, rule_struct: &RuleStruct, token_stream: &mut TokenStream) -> Option<ParseModel> {
-    return match rule_struct.repeat {
-        RuleRepeats::Once => {
-            //print!("Once: ");
-            // return
-            single_match_with(label, rule_struct, token_stream)
+    // the furthest failure so far, to pass to expect_instead
+    pub(super) fn failure_mark(&self) -> (Option<usize>, usize) {
+        return (self.furthest_failure, self.expected.len());
+    }
+
+    // reports the tokens expected at offset since the mark as one thing, like "operator" for all
+    // of the operators. failures past offset are left alone, they tell more than a summary would.
+    pub(super) fn expect_instead(&mut self, mark: (Option<usize>, usize), offset: usize, expected: &'static str) {
+        if self.furthest_failure != Some(offset) {
+            return;
         }
-        RuleRepeats::ZeroOrMore => {
-            //print!("ZeroOrMore: ");
-            let mut children = vec![];
-            loop {
-                if let Some(match_option) = single_match_with(label, rule_struct, token_stream) {
-                    children.push(Box::new(match_option));
-                } else {
-                    break;
-                }
+        let (furthest_failure, expected_count) = mark;
+        if furthest_failure == Some(offset) {
+            if self.expected.len() == expected_count {
+                return;
             }
+            self.expected.truncate(expected_count);
+        } else {
+            self.expected.clear();
+        }
+        if !self.expected.contains(&expected) {
+            self.expected.push(expected);
+        }
+    }
 
-            // return
-            Some(ParseModel {
-                label: label.to_string(),
-                tokens: vec![],
-                children,
-            })
+    pub(super) fn match_rule_by_name(&mut self, label: &'static str, token_stream: &mut TokenStream) -> Option<Arc<ParseModel>> {
+        let key = (label, token_stream.position());
+        if let Some(tracer) = &mut self.tracer {
+            tracer.enter(label, token_stream);
         }
-        RuleRepeats::OneOrMore => {
-            //print!("OneOrMore: ");
-            let mut children = vec![];
-            loop {
-                if let Some(match_option) = single_match_with(label, rule_struct, token_stream) {
-                    children.push(Box::new(match_option));
-                } else {
-                    break;
+        if let Some(memoized) = self.memo.get(&key) {
+            token_stream.restore(memoized.position);
+            token_stream.last_consumed_offset = token_stream.last_consumed_offset.max(memoized.last_consumed_offset);
+            let result = memoized.result.clone();
+            let failure = memoized.failure.clone();
+            self.merge_failure(&failure);
+            if let Some(tracer) = &mut self.tracer {
+                tracer.exit(label, result.is_some(), true, token_stream);
+            }
+            return result;
+        }
+
+        self.rule_evaluations += 1;
+        let grammar = self.grammar;
+        let outer_failure = self.take_failure();
+        let result = if let Some(rule) = grammar.get(label) {
+            self.match_with(label, rule, token_stream)
+        } else {
+            panic!("!!! Rule not found: {} !!!", label);
+        };
+        let failure = self.restore_failure(outer_failure);
+        self.merge_failure(&failure);
+        if self.memoize {
+            self.memo.insert(key, MemoizedMatch {
+                result: result.clone(),
+                position: token_stream.position(),
+                last_consumed_offset: token_stream.last_consumed_offset,
+                failure,
+            });
+        }
+        if let Some(tracer) = &mut self.tracer {
+            tracer.exit(label, result.is_some(), false, token_stream);
+        }
+        return result;
+    }
+
+    fn match_with(&mut self, label: &str, rule_struct: &RuleStruct, token_stream: &mut TokenStream) -> Option<Arc<ParseModel>> {
+        return match rule_struct.repeat {
+            RuleRepeats::Once => {
+                // return
+                self.single_match_with(label, rule_struct, token_stream)
+            }
+            RuleRepeats::ZeroOrMore => {
+                let mut children = vec![];
+                let recovery = if self.recovering { self.grammar.recoveries.get(label) } else { None };
+                loop {
+                    if let Some(recovery) = recovery {
+                        if let Some(match_option) = self.recovering_match_with(label, rule_struct, recovery, token_stream) {
+                            children.push(match_option);
+                            continue;
+                        }
+                        break;
+                    }
+                    if let Some(match_option) = self.single_match_with(label, rule_struct, token_stream) {
+                        children.push(match_option);
+                    } else {
+                        break;
+                    }
                 }
+
+                // return
+                Some(Arc::new(ParseModel {
+                    label: label.to_string(),
+                    tokens: vec![],
+                    children,
+                    trivia: vec![],
+                }))
             }
-            if children.is_empty() {
-                return None;
+            RuleRepeats::OneOrMore => {
+                let mut children = vec![];
+                loop {
+                    if let Some(match_option) = self.single_match_with(label, rule_struct, token_stream) {
+                        children.push(match_option);
+                    } else {
+                        break;
+                    }
+                }
+                if children.is_empty() {
+                    return None;
+                }
+
+                // return
+                Some(Arc::new(ParseModel {
+                    label: label.to_string(),
+                    tokens: vec![],
+                    children,
+                    trivia: vec![],
+                }))
             }
+            RuleRepeats::ZeroOrOne => {
+                let mut children = vec![];
+                if let Some(match_option) = self.single_match_with(label, rule_struct, token_stream) {
+                    children.push(match_option);
+                }
 
-            // return
-            Some(ParseModel {
-                label: label.to_string(),
-                tokens: vec![],
-                children,
-            })
-        }
-        RuleRepeats::ZeroOrOne => {
-            //print!("ZeroOrOne: ");
-            let mut children = vec![];
-            if let Some(match_option) = single_match_with(label, rule_struct, token_stream) {
-                children.push(Box::new(match_option));
+                // return
+                Some(Arc::new(ParseModel {
+                    label: label.to_string(),
+                    tokens: vec![],
+                    children,
+                    trivia: vec![],
+                }))
             }
+        };
+    }
 
-            // return
-            Some(ParseModel {
-                label: label.to_string(),
-                tokens: vec![],
-                children,
-            })
+    // matches one item of a repeated rule, or skips the tokens of one that doesn't match and
+    // returns an `error` node with them. none means that the rule ends here.
+    fn recovering_match_with(&mut self, label: &str, rule_struct: &RuleStruct, recovery: &Recovery, token_stream: &mut TokenStream) -> Option<Arc<ParseModel>> {
+        let position = token_stream.position();
+        let outer_failure = self.take_failure();
+        let result = self.single_match_with(label, rule_struct, token_stream);
+        let failure = self.restore_failure(outer_failure);
+        if result.is_some() {
+            self.merge_failure(&failure);
+            return result;
         }
-    };
-}
 
-unsafe fn single_match_with(label: &str, rule_struct: &RuleStruct, token_stream: &mut TokenStream) -> Option<ParseModel> {
-    return match rule_struct.rule_type {
-        RuleType::And => {
-            // return
-            and_match_with(label, rule_struct, token_stream)
-        }
-        RuleType::Or => {
-            // return
-            or_match_with(label, rule_struct, token_stream)
-        }
-        RuleType::Match => {
-            // return
-            label_match_with(label, rule_struct, token_stream)
+        token_stream.restore(position);
+        let first_offset = token_stream.next_offset()?;
+        if recovery.stop.contains(&token_stream.matches[first_offset].label.as_str()) {
+            // the end of the rule, not an error
+            self.merge_failure(&failure);
+            return None;
         }
-    };
-}
 
-unsafe fn label_match_with(label: &str, rule_struct: &RuleStruct, token_stream: &mut TokenStream) -> Option<ParseModel> {
-    println!("Trying `token` rule: {} {}", label, token_stream.offset);
+        let (found_offset, expected) = match failure {
+            (Some(found_offset), expected) => (found_offset, expected),
+            (None, _) => (first_offset, vec![]),
+        };
+        // the tokens up to the one that didn't match are skipped whatever they are, so that a
+        // keyword in the middle of an item doesn't start the next one. blocks are skipped whole.
+        // parentheses aren't counted, since one that is never closed would hide the rest of the
+        // file.
+        let mut tokens: Vec<Box<Match>> = vec![];
+        let mut open_blocks = 0;
+        while let Some(next_offset) = token_stream.next_offset() {
+            let next = &token_stream.matches[next_offset];
+            let next_label = next.label.as_str();
+            if let Some(last) = tokens.last() {
+                let starts_item = next_offset > found_offset
+                    && (recovery.sync.contains(&next_label) || next.line_number > last.end_line_number);
+                if open_blocks == 0 && (starts_item || recovery.stop.contains(&next_label)) {
+                    break;
+                }
+            }
+            if next_label == "_open_curly" {
+                open_blocks += 1;
+            } else if next_label == "_close_curly" && open_blocks > 0 {
+                open_blocks -= 1;
+            }
+            tokens.push(Box::new(token_stream.next().unwrap().clone()));
+        }
 
-    if !token_stream.has_next() {
-        //println!("Missed `token` rule: {}", label);
-        return None;
+        self.recovered.insert(tokens[0].start_byte_offset, (found_offset, expected));
+        return Some(Arc::new(ParseModel {
+            label: "error".to_string(),
+            tokens,
+            children: vec![],
+            trivia: vec![],
+        }));
     }
 
-    let mut result = Vec::new();
-    let offset = token_stream.offset;
-    for token in &rule_struct.match_labels {
-        if let Some(next) = token_stream.next() {
-            //println!("Look for [{}] in input [{} : {}] {}", token, &next.label, &next.value, &next.skip);
-            if token.to_string() == next.label {
-                println!("label_match_with found a match for: {} {}", token, next.value);
-                // I'm not thrilled about doing a clone here, but my rust expertise
-                // is not enough to figure out how to return this data without a clone.
-                // I suspect there is a way to do it with a 'lifetime' specifier, but
-                // as I'm learning rust and I don't understand it well, this is what we are
-                // doing for now.
-                result.push(Box::new((*next).clone()));
-                continue;
+    fn single_match_with(&mut self, label: &str, rule_struct: &RuleStruct, token_stream: &mut TokenStream) -> Option<Arc<ParseModel>> {
+        return match rule_struct.rule_type {
+            RuleType::And => {
+                // return
+                self.and_match_with(label, rule_struct, token_stream)
             }
-        }
-        token_stream.reset(offset);
-        //println!("Missed `token` rule: {}", label);
-        return None;
+            RuleType::Or => {
+                // return
+                self.or_match_with(rule_struct, token_stream)
+            }
+            RuleType::Match => {
+                // return
+                self.label_match_with(label, rule_struct, token_stream)
+            }
+            RuleType::Precedence => {
+                // return
+                self.precedence_match_with(label, rule_struct.children[0], token_stream)
+            }
+        };
     }
 
-    println!("Hit `token` rule: {}", label);
-    return Some(ParseModel {
-        label: label.to_string(),
-        tokens: result,
-        children: vec![],
-    });
-}
+    fn label_match_with(&mut self, label: &str, rule_struct: &RuleStruct, token_stream: &mut TokenStream) -> Option<Arc<ParseModel>> {
+        if !token_stream.has_next() {
+            self.record_failure(token_stream.matches.len(), rule_struct.match_labels[0]);
+            return None;
+        }
 
-unsafe fn or_match_with(label: &str, rule_struct: &RuleStruct, token_stream: &mut TokenStream) -> Option<ParseModel> {
-    println!("Trying `or` rule: {} {}", label, token_stream.offset);
+        let mut result = Vec::new();
+        let position = token_stream.position();
+        for token in &rule_struct.match_labels {
+            if let Some(next) = token_stream.next() {
+                if token.to_string() == next.label {
+                    // I'm not thrilled about doing a clone here, but my rust expertise
+                    // is not enough to figure out how to return this data without a clone.
+                    // I suspect there is a way to do it with a 'lifetime' specifier, but
+                    // as I'm learning rust and I don't understand it well, this is what we are
+                    // doing for now.
+                    result.push(Box::new((*next).clone()));
+                    continue;
+                }
+                let offset = token_stream.offset;
+                self.record_failure(offset, *token);
+            } else {
+                self.record_failure(token_stream.matches.len(), *token);
+            }
+            token_stream.restore(position);
+            return None;
+        }
 
-    if !token_stream.has_next() {
-        //println!("Missed `or` rule: {}", label);
-        return None;
+        return Some(Arc::new(ParseModel {
+            label: label.to_string(),
+            tokens: result,
+            children: vec![],
+            trivia: vec![],
+        }));
     }
 
-    let offset = token_stream.offset;
-    for rule in &rule_struct.children {
-        if let Some(next) = match_rule_by_name(rule, token_stream) {
-            println!("Hit `or` rule: {}", label);
-            return Some(next);
+    fn or_match_with(&mut self, rule_struct: &RuleStruct, token_stream: &mut TokenStream) -> Option<Arc<ParseModel>> {
+        if !token_stream.has_next() {
+            return None;
         }
-    }
 
-    token_stream.reset(offset);
-    //println!("Missed `or` rule: {}", label);
-    return None;
-}
-
-unsafe fn and_match_with(label: &str, rule_struct: &RuleStruct, token_stream: &mut TokenStream) -> Option<ParseModel> {
-    println!("Trying `and` rule: {} {}", label, token_stream.offset);
+        let position = token_stream.position();
+        for rule in &rule_struct.children {
+            if let Some(next) = self.match_rule_by_name(rule, token_stream) {
+                return Some(next);
+            }
+        }
 
-    if !token_stream.has_next() {
-        //println!("Token Stream Empty: Missed `and` rule: {}", label);
+        token_stream.restore(position);
         return None;
     }
-    let mut children: Vec<Box<ParseModel>> = Vec::new();
-    let offset = token_stream.offset;
-    for rule in &rule_struct.children {
-        if let Some(next) = match_rule_by_name(rule, token_stream) {
-            children.push(Box::new(next));
-        } else {
-            token_stream.reset(offset);
-            //println!("Missed `and` rule: {}", label);
+
+    fn and_match_with(&mut self, label: &str, rule_struct: &RuleStruct, token_stream: &mut TokenStream) -> Option<Arc<ParseModel>> {
+        if !token_stream.has_next() {
             return None;
         }
+        let mut children: Vec<Arc<ParseModel>> = Vec::new();
+        let position = token_stream.position();
+        for rule in &rule_struct.children {
+            if let Some(next) = self.match_rule_by_name(rule, token_stream) {
+                children.push(next);
+            } else {
+                token_stream.restore(position);
+                return None;
+            }
+        }
+
+        return Some(Arc::new(ParseModel {
+            label: label.to_string(),
+            tokens: vec![],
+            children,
+            trivia: vec![],
+        }));
     }
+}
 
-    println!("Hit `and` rule: {}", label);
-    return Some(ParseModel {
-        label: label.to_string(),
-        tokens: vec![],
-        children,
-    });
+// The Dog grammar. The rules are read from parser_def.txt, see grammar_definition.rs, and a
+// mistake in them is found the first time the grammar is built rather than when a document
+// happens to need the rule with the mistake.
+pub fn build_rules() -> Grammar {
+    let mut result = read_grammar(include_str!("parser_def.txt"), "document")
+        .unwrap_or_else(|errors| panic!("parser_def.txt has mistakes:\n{}", describe_errors(&errors)));
+
+    // error recovery, see Parser::with_recovery. an item that doesn't match is skipped up to the
+    // next line, the `}` that ends its block, or a keyword that starts another item.
+    let declaration_keywords = vec!["_doc_comment", "_hash", "_app", "_test", "_lib", "_service", "_ui", "_function",
+                                    "_struct", "_trait", "_impl", "_enum", "_module", "_attribute"];
+    result.recover("mod_body_decls", vec!["_close_curly"], declaration_keywords.clone());
+    result.recover("statements", vec!["_close_curly"], vec!["_let", "_return", "_for", "_while", "_if", "_fail"]);
+    result.recover("struct_body", vec!["_close_curly"], vec![]);
+    result.recover("impl_body", vec!["_close_curly"], declaration_keywords.clone());
+    result.recover("trait_body", vec!["_close_curly"], declaration_keywords);
+
+    // written for features that no document can hold yet
+    result.allow_unused(vec!["log_decl", "sql", "struct_constructor_list"]);
+
+    let errors = result.validate();
+    if !errors.is_empty() {
+        panic!("The grammar in parser_def.txt has mistakes:\n{}", describe_errors(&errors));
+    }
+    return result;
+}
+
+fn describe_errors(errors: &[GrammarError]) -> String {
+    let lines: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+    return lines.join("\n");
 }
 
-fn build_rules() -> Option<HashMap<&'static str, Box<RuleStruct>>> {
-    let mut result: HashMap<&'static str, Box<RuleStruct>> = HashMap::new();
-    // Rust Parser Rules 0.0.1
-    // open_curly: _open_curly
-    result.insert("open_curly", create_label_match(vec!["_open_curly"]));
-    // close_curly: _close_curly
-    result.insert("close_curly", create_label_match(vec!["_close_curly"]));
-    // comma: _comma
-    result.insert("comma", create_label_match(vec!["_comma"]));
-    // equal: _equal
-    result.insert("equal", create_label_match(vec!["_equal"]));
-    // greater: _greater
-    result.insert("greater", create_label_match(vec!["_greater"]));
-    // less: _less
-    result.insert("less", create_label_match(vec!["_less"]));
-    // plus: _plus
-    result.insert("plus", create_label_match(vec!["_plus"]));
-    // minus: _minus
-    result.insert("minus", create_label_match(vec!["_minus"]));
-    // star: _star
-    result.insert("star", create_label_match(vec!["_star"]));
-    // period: _period
-    result.insert("period", create_label_match(vec!["_period"]));
-    // slash: _slash
-    result.insert("slash", create_label_match(vec!["_slash"]));
-    // hash: _hash
-    result.insert("hash", create_label_match(vec!["_hash"]));
-    // open_paren: _open_paren
-    result.insert("open_paren", create_label_match(vec!["_open_paren"]));
-    // close_paren: _close_paren
-    result.insert("close_paren", create_label_match(vec!["_close_paren"]));
-    // open_bracket: _open_bracket
-    result.insert("open_bracket", create_label_match(vec!["_open_bracket"]));
-    // close_bracket: _close_bracket
-    result.insert("close_bracket", create_label_match(vec!["_close_bracket"]));
-    // exclamation: _exclamation
-    result.insert("exclamation", create_label_match(vec!["_exclamation"]));
-    // question_mark: _question_mark
-    result.insert("question_mark", create_label_match(vec!["_question_mark"]));
-    // colon: _colon
-    result.insert("colon", create_label_match(vec!["_colon"]));
-    // pipe: _pipe
-    result.insert("pipe", create_label_match(vec!["_pipe"]));
-    // let: _let
-    result.insert("let", create_label_match(vec!["_let"]));
-    // return: _return
-    result.insert("return", create_label_match(vec!["_return"]));
-    // if: _if
-    result.insert("if", create_label_match(vec!["_if"]));
-    // else: _else
-    result.insert("else", create_label_match(vec!["_else"]));
-    // switch: _switch
-    result.insert("switch", create_label_match(vec!["_switch"]));
-    // case: _case
-    result.insert("case", create_label_match(vec!["_case"]));
-    // default: _default
-    result.insert("default", create_label_match(vec!["_default"]));
-    // fail: _fail
-    result.insert("fail", create_label_match(vec!["_fail"]));
-    // otherwise: _otherwise
-    result.insert("otherwise", create_label_match(vec!["_otherwise"]));
-    // for: _for
-    result.insert("for", create_label_match(vec!["_for"]));
-    // in: _in
-    result.insert("in", create_label_match(vec!["_in"]));
-    // while: _while
-    result.insert("while", create_label_match(vec!["_while"]));
-    // break: _break
-    result.insert("break", create_label_match(vec!["_break"]));
-    // continue: _continue
-    result.insert("continue", create_label_match(vec!["_continue"]));
-    // with: _with
-    result.insert("with", create_label_match(vec!["_with"]));
-    // isa: _isa
-    result.insert("isa", create_label_match(vec!["_isa"]));
-    // app: _app
-    result.insert("app", create_label_match(vec!["_app"]));
-    // lib: _lib
-    result.insert("lib", create_label_match(vec!["_lib"]));
-    // ui: _ui
-    result.insert("ui", create_label_match(vec!["_ui"]));
-    // service: _service
-    result.insert("service", create_label_match(vec!["_service"]));
-    // test: _test
-    result.insert("test", create_label_match(vec!["_test"]));
-    // log: _log
-    result.insert("log", create_label_match(vec!["_log"]));
-    // config: _config
-    result.insert("config", create_label_match(vec!["_config"]));
-    // function: _function
-    result.insert("function", create_label_match(vec!["_function"]));
-    // struct: _struct
-    result.insert("struct", create_label_match(vec!["_struct"]));
-    // enum: _enum
-    result.insert("enum", create_label_match(vec!["_enum"]));
-    // trait: _trait
-    result.insert("trait", create_label_match(vec!["_trait"]));
-    // impl: _impl
-    result.insert("impl", create_label_match(vec!["_impl"]));
-    // attribute: _attribute
-    result.insert("attribute", create_label_match(vec!["_attribute"]));
-    // self: _self
-    result.insert("self", create_label_match(vec!["_self"]));
-    // public: _public
-    result.insert("public", create_label_match(vec!["_public"]));
-    // mutable: _mutable
-    result.insert("mutable", create_label_match(vec!["_mutable"]));
-    // constant: _constant
-    result.insert("constant", create_label_match(vec!["_constant"]));
-    // once: _once
-    result.insert("once", create_label_match(vec!["_once"]));
-    //reference: _reference
-    // unsafe: _unsafe
-    result.insert("unsafe", create_label_match(vec!["_unsafe"]));
-    // use: _use
-    result.insert("use", create_label_match(vec!["_use"]));
-    // as: _as
-    result.insert("as", create_label_match(vec!["_as"]));
-    // module: _module
-    result.insert("module", create_label_match(vec!["_module"]));
-    // unsigned_integer: _unsigned_integer
-    result.insert("unsigned_integer", create_label_match(vec!["_unsigned_integer"]));
-    // integer: _integer
-    result.insert("integer", create_label_match(vec!["_integer"]));
-    // float: _float
-    result.insert("float", create_label_match(vec!["_float"]));
-    // boolean: _boolean
-    result.insert("boolean", create_label_match(vec!["_boolean"]));
-    // character: _character
-    result.insert("character", create_label_match(vec!["_character"]));
-    // void: _void
-    result.insert("void", create_label_match(vec!["_void"]));
-    // false: _false
-    result.insert("false", create_label_match(vec!["_false"]));
-    // true: _true
-    result.insert("true", create_label_match(vec!["_true"]));
-    // null: _null
-    result.insert("null", create_label_match(vec!["_null"]));
-    // f32: _f32
-    result.insert("f32", create_label_match(vec!["_f32"]));
-    // f64: _f64
-    result.insert("f64", create_label_match(vec!["_f64"]));
-    // i8: _i8
-    result.insert("i8", create_label_match(vec!["_i8"]));
-    // i16: _i16
-    result.insert("i16", create_label_match(vec!["_i16"]));
-    // i32: _i32
-    result.insert("i32", create_label_match(vec!["_i32"]));
-    // i64: _i64
-    result.insert("i64", create_label_match(vec!["_i64"]));
-    // u8: _u8
-    result.insert("u8", create_label_match(vec!["_u8"]));
-    // u16: _u16
-    result.insert("u16", create_label_match(vec!["_u16"]));
-    // u32: _u32
-    result.insert("u32", create_label_match(vec!["_u32"]));
-    // u64: _u64
-    result.insert("u64", create_label_match(vec!["_u64"]));
-    // sql: _sql
-    result.insert("sql", create_label_match(vec!["_sql"]));
-    // bool_literal: true || false
-    result.insert("bool_literal", create_or_rule_once( vec!["true", "false"]));
-    // string_literal: _string_literal
-    result.insert("string_literal", create_label_match(vec!["_string_literal"]));
-    // number_literal: _number_literal
-    result.insert("number_literal", create_label_match(vec!["_number_literal"]));
-    // identifier: _word
-    result.insert("identifier", create_label_match(vec!["_word"]));
-    // null: _null
-    result.insert("null", create_label_match(vec!["_null"]));
-    // literal: string_literal || number_literal || bool_literal || null
-    result.insert("literal", create_or_rule_once( vec!["string_literal", "number_literal", "bool_literal", "null"]));
-    // external_identifier_tail: (double_colon && identifier)*
-    result.insert("external_identifier_tail", create_and_rule(RuleRepeats::ZeroOrMore, vec!["double_colon", "identifier"]));
-    // external_identifier: identifier && external_identifier_tail
-    result.insert("external_identifier", create_and_rule_once( vec!["identifier", "external_identifier_tail"]));
-    // identifier_part: external_identifier || config || string_literal || number_literal || bool_literal
-    result.insert("identifier_part", create_or_rule_once( vec!["external_identifier", "config", "string_literal", "number_literal", "bool_literal"]));
-    // additional_identifier_part: (period && identifier)*
-    result.insert("additional_identifier_part", create_and_rule(RuleRepeats::ZeroOrMore, vec!["period", "identifier"]));
-    // qualified_identifier: identifier_part && additional_identifier_part
-    result.insert("qualified_identifier", create_and_rule_once( vec!["identifier_part", "additional_identifier_part"]));
-    // literal_or_identifier: literal || qualified_identifier
-    result.insert("literal_or_identifier", create_or_rule_once( vec!["literal", "qualified_identifier"]));
-    // optional_generic_of_decl: (colon && data_type)?
-    result.insert("optional_generic_of_decl", create_and_rule(RuleRepeats::ZeroOrOne, vec!["colon", "data_type"]));
-    // generics: (external_identifier && optional_generic_of_decl && optional_comma)+
-    result.insert("generics", create_and_rule(RuleRepeats::OneOrMore, vec!["external_identifier", "optional_generic_of_decl", "optional_comma"]));
-    // optional_generics: (less && generics && greater)?
-    result.insert("optional_generics", create_and_rule(RuleRepeats::ZeroOrOne, vec!["less", "generics", "greater"]));
-    // user_type_or_generic: external_identifier && optional_generics
-    result.insert("user_type_or_generic", create_and_rule_once( vec!["external_identifier", "optional_generics"]));
-    // base_data_type: integer || float || boolean || character || user_type_or_generic
-    result.insert("base_data_type", create_or_rule_once( vec!["integer", "float", "boolean", "character", "user_type_or_generic"]));
-    // array_type: open_bracket && data_type && close_bracket
-    result.insert("array_type", create_and_rule_once( vec!["open_bracket", "data_type", "close_bracket"]));
-    // data_type: base_data_type || array_type
-    result.insert("data_type", create_or_rule_once( vec!["base_data_type", "array_type"]));
-    // optional_data_type: (colon && data_type)?
-    result.insert("optional_data_type", create_and_rule(RuleRepeats::ZeroOrOne, vec!["colon", "data_type"]));
-    // alias: _word
-    result.insert("alias", create_label_match(vec!["_word"]));
-    // double_colon: colon && colon
-    result.insert("double_colon", create_and_rule_once( vec!["colon", "colon"]));
-    // optional_comma: comma?
-    result.insert("optional_comma", create_and_rule(RuleRepeats::ZeroOrOne, vec!["comma"]));
-    // optional_semicolon: semicolon*
-    result.insert("optional_semicolon", create_and_rule(RuleRepeats::ZeroOrMore, vec!["semicolon"]));
-    // boolean_equals: equal && equal
-    result.insert("boolean_equals", create_and_rule_once( vec!["equal", "equal"]));
-    // boolean_less: less
-    result.insert("boolean_less", create_and_rule_once( vec!["less"]));
-    // boolean_greater: greater
-    result.insert("boolean_greater", create_and_rule_once( vec!["greater"]));
-    // boolean_not_equal: exclamation && equal
-    result.insert("boolean_not_equal", create_and_rule_once( vec!["exclamation", "equal"]));
-    // boolean_greater_or_equal: greater && equal
-    result.insert("boolean_greater_or_equal", create_and_rule_once( vec!["greater", "equal"]));
-    // boolean_less_or_equal: less && equal
-    result.insert("boolean_less_or_equal", create_and_rule_once( vec!["less", "equal"]));
-    // comparison: boolean_equals || boolean_less || boolean_greater || boolean_not_equal || boolean_greater_or_equal ||boolean_less_or_equal
-    result.insert("comparison", create_or_rule_once( vec!["boolean_equals", "boolean_less", "boolean_greater", "boolean_not_equal", "boolean_greater_or_equal", "boolean_less_or_equal"]));
-    // multiply: star
-    result.insert("multiply", create_and_rule_once( vec!["star"]));
-    // divide: slash
-    result.insert("divide", create_and_rule_once( vec!["slash"]));
-    // dereference_instance_member: period
-    result.insert("dereference_instance_member", create_and_rule_once( vec!["period"]));
-    // dereference_const_member: double_colon
-    result.insert("dereference_const_member", create_and_rule_once( vec!["double_colon"]));
-    // binary_operator: plus || minus || multiply || divide || dereference_instance_member || dereference_const_member || comparison
-    result.insert("binary_operator", create_or_rule_once( vec!["plus", "minus", "multiply", "divide", "dereference_instance_member", "dereference_const_member", "comparison"]));
-    // not_operator: exclamation
-    result.insert("not_operator", create_and_rule_once( vec!["exclamation"]));
-    // minus_operator: minus
-    result.insert("minus_operator", create_and_rule_once( vec!["minus"]));
-    // unary_operator: exclamation || minus
-    result.insert("unary_operator", create_or_rule_once( vec!["exclamation", "minus"]));
-    // log_decl: log && open_paren && string_literal && close_paren && optional_semicolon
-    result.insert("log_decl", create_and_rule_once( vec!["log", "open_paren", "string_literal", "close_paren", "optional_semicolon"]));
-    // attr_metadata: identifier && colon && literal
-    result.insert("attr_metadata", create_and_rule_once( vec!["identifier", "colon", "literal"]));
-    // optional_attr_metadata_next: (optional_comma && attr_metadata)*
-    result.insert("optional_attr_metadata_next", create_and_rule(RuleRepeats::ZeroOrMore, vec!["optional_comma", "attr_metadata"]));
-    // optional_attr_metadata: (attr_metadata && optional_attr_metadata_next)?
-    result.insert("optional_attr_metadata", create_and_rule(RuleRepeats::ZeroOrOne, vec!["attr_metadata", "optional_attr_metadata_next"]));
-    // optional_attr_metadata_group: (open_curly && optional_attr_metadata && close_curly)*
-    result.insert("optional_attr_metadata_group", create_and_rule(RuleRepeats::ZeroOrMore, vec!["open_curly", "optional_attr_metadata", "close_curly"]));
-    // attr_tag: hash && external_identifier && optional_attr_metadata_group
-    result.insert("attr_tag", create_and_rule_once( vec!["hash", "external_identifier", "optional_attr_metadata_group"]));
-    // optional_attr_tags: (attr_tag)*
-    result.insert("optional_attr_tags", create_and_rule(RuleRepeats::ZeroOrMore, vec!["attr_tag"]));
-    // enum_member: identifier
-    result.insert("enum_member", create_and_rule_once( vec!["identifier"]));
-    // enum_members: (enum_member && optional_comma)*
-    result.insert("enum_members", create_and_rule(RuleRepeats::ZeroOrMore, vec!["enum_member", "optional_comma"]));
-    // enum_decl: optional_attr_tags && enum && open_curly && enum_members && close_culry
-    result.insert("enum_decl", create_and_rule_once( vec!["optional_attr_tags", "enum", "open_curly", "enum_members", "close_culry"]));
-    // impl_statement: function_decl
-    result.insert("impl_statement", create_and_rule_once( vec!["function_decl"]));
-    // impl_body: (optional_const && impl_statement)*
-    result.insert("impl_body", create_and_rule(RuleRepeats::ZeroOrMore, vec!["optional_const", "impl_statement"]));
-    // on_optional_trait: (on && identifier && optional_generics)?
-    result.insert("on_optional_trait", create_and_rule(RuleRepeats::ZeroOrOne, vec!["on", "identifier", "optional_generics"]));
-    // impl_decl: optional_attr_tags && impl && identifier && on_optional_trait && open_curly && impl_body && close_curly
-    result.insert("impl_decl", create_and_rule_once( vec!["optional_attr_tags", "impl", "identifier", "on_optional_trait", "open_curly", "impl_body", "close_curly"]));
-    // optional_const: const?
-    result.insert("optional_const", create_and_rule(RuleRepeats::ZeroOrOne, vec!["const"]));
-    // trait_statement: function_signature_decl || function_decl
-    result.insert("trait_statement", create_or_rule_once( vec!["function_signature_decl", "function_decl"]));
-    // trait_body: (optional_const && trait_statement)*
-    result.insert("trait_body", create_and_rule(RuleRepeats::ZeroOrMore, vec!["optional_const", "trait_statement"]));
-    // trait_decl: optional_attr_tags && identifier && optional_generics && open_curly && trait_body && close_curly
-    result.insert("trait_decl", create_and_rule_once( vec!["optional_attr_tags", "identifier", "optional_generics", "open_curly", "trait_body", "close_curly"]));
-    // struct_member: identifier && optional_data_type
-    result.insert("struct_member", create_and_rule_once( vec!["identifier", "optional_data_type"]));
-    // struct_body: (struct_member && optional_semicolon)*
-    result.insert("struct_body", create_and_rule(RuleRepeats::ZeroOrMore, vec!["struct_member", "optional_semicolon"]));
-    // struct_decl: optional_attr_tags && struct && identifier && optional_generics && open_curly && struct_body && close_curly
-    result.insert("struct_decl", create_and_rule_once( vec!["optional_attr_tags", "struct", "identifier", "optional_generics", "open_curly", "struct_body", "close_curly"]));
-    // optional_param_qualifier: (identifier && colon)?
-    result.insert("optional_param_qualifier", create_and_rule(RuleRepeats::ZeroOrOne, vec!["identifier", "colon"]));
-    // params: (optional_param_qualifier && expression && optional_comma)*
-    result.insert("params", create_and_rule(RuleRepeats::ZeroOrMore, vec!["optional_param_qualifier", "expression", "optional_comma"]));
-    // function_invocation: qualified_identifier && open_paren && params && close_paren
-    result.insert("function_invocation", create_and_rule_once( vec!["qualified_identifier", "open_paren", "params", "close_paren"]));
-    // fail_invocation: fail && open_paren && params && close_paren
-    result.insert("fail_invocation", create_and_rule_once( vec!["fail", "open_paren", "params", "close_paren"]));
-    // variable_literal_invocation: function_invocation || literal_or_identifier
-    result.insert("variable_literal_invocation", create_or_rule_once( vec!["function_invocation", "literal_or_identifier"]));
-    // struct_constructor_list_entry: literal_or_identifier && optional_comma
-    result.insert("struct_constructor_list_entry", create_and_rule_once( vec!["literal_or_identifier", "optional_comma"]));
-    // struct_constructor_list_entries: struct_constructor_list_entry*
-    result.insert("struct_constructor_list_entries", create_and_rule(RuleRepeats::ZeroOrMore, vec!["struct_constructor_list_entry"]));
-    // struct_constructor_list: open_bracket && struct_constructor_list_entries && close_bracket
-    result.insert("struct_constructor_list", create_and_rule_once( vec!["open_bracket", "struct_constructor_list_entries", "close_bracket"]));
-    // struct_constructor_map_entry: identifier && colon && literal_or_identifier && optional_comma
-    result.insert("struct_constructor_map_entry", create_and_rule_once( vec!["identifier", "colon", "literal_or_identifier", "optional_comma"]));
-    // struct_constructor_map_entries: struct_constructor_map_entry*
-    result.insert("struct_constructor_map_entries", create_and_rule(RuleRepeats::ZeroOrMore, vec!["struct_constructor_map_entry"]));
-    // struct_constructor_map: open_curly && struct_constructor_map_entries && close_curly
-    result.insert("struct_constructor_map", create_and_rule_once( vec!["open_curly", "struct_constructor_map_entries", "close_curly"]));
-    // struct_constructor: identifier && struct_constructor_map
-    result.insert("struct_constructor", create_and_rule_once( vec!["identifier", "struct_constructor_map"]));
-    // optional_config_extention: (colon && identifier)?
-    result.insert("optional_config_extention", create_and_rule(RuleRepeats::ZeroOrOne, vec!["colon", "identifier"]));
-    // config_decl:  config && identifier && optional_config_extention && config_map
-    result.insert("config_decl", create_and_rule_once( vec!["config", "identifier", "optional_config_extention", "config_map"]));
-    // config_document: config_decl*
-    result.insert("config_document", create_and_rule(RuleRepeats::ZeroOrMore, vec!["config_decl"]));
-    // optional_range_inclusive: equal?
-    result.insert("optional_range_inclusive", create_and_rule(RuleRepeats::ZeroOrOne, vec!["equal"]));
-    // range_expression: open_bracket && literal_or_identifier && period && period && optional_range_inclusive && literal_or_identifier && close_bracket
-    result.insert("range_expression", create_and_rule_once( vec!["open_bracket", "literal_or_identifier", "period", "period", "optional_range_inclusive", "literal_or_identifier", "close_bracket"]));
-    // binary_operation: variable_literal_invocation && binary_operator && expression
-    result.insert("binary_operation", create_and_rule_once( vec!["variable_literal_invocation", "binary_operator", "expression"]));
-    // unary_operation: unary_operator && expression
-    result.insert("unary_operation", create_and_rule_once( vec!["unary_operator", "expression"]));
-    // cast_operation: variable_literal_invocation && as && data_type
-    result.insert("cast_operation", create_and_rule_once( vec!["variable_literal_invocation", "as", "data_type"]));
-    // expression_group: open_paren && expression && close_paren
-    result.insert("expression_group", create_and_rule_once( vec!["open_paren", "expression", "close_paren"]));
-    // expression_part: function_invocation || struct_constructor || expression_group || binary_operation || unary_operation || variable_literal_invocation || range_expression
-    result.insert("expression_part", create_or_rule_once( vec!["function_invocation", "struct_constructor", "expression_group", "binary_operation", "unary_operation", "variable_literal_invocation", "range_expression"]));
-    // trailing_binary_expression_part: (binary_operator && expression)*
-    result.insert("trailing_binary_expression_part", create_and_rule(RuleRepeats::ZeroOrMore, vec!["binary_operator", "expression"]));
-    // expression: expression_part && trailing_binary_expression_part
-    result.insert("expression", create_and_rule_once( vec!["expression_part", "trailing_binary_expression_part"]));
-    // optional_expression: expression?
-    result.insert("optional_expression", create_and_rule(RuleRepeats::ZeroOrOne, vec!["expression"]));
-    // variable_declaration: let && identifier && optional_data_type
-    result.insert("variable_declaration", create_and_rule_once( vec!["let", "identifier", "optional_data_type"]));
-    // variable_declaration_statement: variable_declaration && optional_semicolon
-    result.insert("variable_declaration_statement", create_and_rule_once( vec!["variable_declaration", "optional_semicolon"]));
-    // variable_or_variable_declaration: qualified_identifier || variable_declaration
-    result.insert("variable_or_variable_declaration", create_or_rule_once( vec!["qualified_identifier", "variable_declaration"]));
-    // assignment: variable_or_variable_declaration && equal && expression && optional_semicolon
-    result.insert("assignment", create_and_rule_once( vec!["variable_or_variable_declaration", "equal", "expression", "optional_semicolon"]));
-    // simple_statement: assignment || expression || variable_declaration_statement
-    result.insert("simple_statement", create_or_rule_once( vec!["assignment", "expression", "variable_declaration_statement"]));
-    // while_loop_statement: while && optional_expression && block
-    result.insert("while_loop_statement", create_and_rule_once( vec!["while", "optional_expression", "block"]));
-    // for_loop_statement: for && identifier && optional_data_type && in && expression && block
-    result.insert("for_loop_statement", create_and_rule_once( vec!["for", "identifier", "optional_data_type", "in", "expression", "block"]));
-    // return_statement: return && expression && optional_semicolon
-    result.insert("return_statement", create_and_rule_once( vec!["return", "expression", "optional_semicolon"]));
-    // if_statement: if && expression && block
-    result.insert("if_statement", create_and_rule_once( vec!["if", "expression", "block"]));
-    // otherwise_action: (block || expression || fail_invocation)
-    result.insert("otherwise_action", create_or_rule_once( vec!["block", "expression", "fail_invocation"]));
-    // optional_otherwise: (otherwise && otherwise_action)?
-    result.insert("optional_otherwise", create_and_rule(RuleRepeats::ZeroOrOne, vec!["otherwise", "otherwise_action"]));
-    // any_statement: block || return_statement || for_loop_statement || while_loop_statement || simple_statement || if_statement || fail_invocation
-    result.insert("any_statement", create_or_rule_once( vec!["block", "return_statement", "for_loop_statement", "while_loop_statement", "simple_statement", "if_statement", "fail_invocation"]));
-    // statements: (any_statement && optional_otherwise)*
-    result.insert("statements", create_and_rule(RuleRepeats::ZeroOrMore, vec!["any_statement", "optional_otherwise"]));
-    // block_no_otherwise: open_curly && statements && close_curly
-    result.insert("block_no_otherwise", create_and_rule_once( vec!["open_curly", "statements", "close_curly"]));
-    // block: block_no_otherwise && optional_otherwise
-    result.insert("block", create_and_rule_once( vec!["block_no_otherwise", "optional_otherwise"]));
-    // optional_param_value: (equal && literal)?
-    result.insert("optional_param_value", create_and_rule(RuleRepeats::ZeroOrOne, vec!["equal", "literal"]));
-    // function_params: (identifier && colon && data_type && optional_param_value && optional_comma)*
-    result.insert("function_params", create_and_rule(RuleRepeats::ZeroOrMore, vec!["identifier", "colon", "data_type", "optional_param_value", "optional_comma"]));
-    // function_params_group: open_paren && function_params && close_paren
-    result.insert("function_params_group", create_and_rule_once( vec!["open_paren", "function_params", "close_paren"]));
-    // optional_entry_point_decl: (app || test || lib || service || ui)?
-    result.insert("optional_entry_point_decl", create_or_rule(RuleRepeats::ZeroOrOne, vec!["app", "test", "lib", "service", "ui"]));
-    // function_name: identifier && optional_generics
-    result.insert("function_name", create_and_rule_once( vec!["identifier", "optional_generics"]));
-    // entry_or_function_decl: optional_attr_tags && optional_entry_point_decl && function && function_name && function_params_group && block_no_otherwise
-    result.insert("entry_or_function_decl", create_and_rule_once( vec!["optional_attr_tags", "optional_entry_point_decl", "function", "function_name", "function_params_group", "block_no_otherwise"]));
-    // function_signature_decl: optional_attr_tags && function && function_name && function_params_group
-    result.insert("function_signature_decl", create_and_rule_once( vec!["optional_attr_tags", "function", "function_name", "function_params_group"]));
-    // function_decl: function_signature_decl && block_no_otherwise
-    result.insert("function_decl", create_and_rule_once( vec!["function_signature_decl", "block_no_otherwise"]));
-    // attr_base_data_type: integer || float || boolean || character || identifier
-    result.insert("attr_base_data_type", create_or_rule_once( vec!["integer", "float", "boolean", "character", "identifier"]));
-    // attr_array_type: open_bracket && attr_base_data_type && close_bracket
-    result.insert("attr_array_type", create_and_rule_once( vec!["open_bracket", "attr_base_data_type", "close_bracket"]));
-    // attr_data_type: attr_base_data_type || attr_array_type
-    result.insert("attr_data_type", create_or_rule_once( vec!["attr_base_data_type", "attr_array_type"]));
-    // attr_body: (identifier && colon && attr_data_type && optional_semicolon)*
-    result.insert("attr_body", create_and_rule(RuleRepeats::ZeroOrMore, vec!["identifier", "colon", "attr_data_type", "optional_semicolon"]));
-    // attr_type: module || struct || impl || trait || function || enum || app || ui || service || lib
-    result.insert("attr_type", create_or_rule_once( vec!["module", "struct", "impl", "trait", "function", "enum", "app", "ui", "service", "lib"]));
-    // attr_types: (attr_type && optional_comma)+
-    result.insert("attr_types", create_and_rule(RuleRepeats::OneOrMore, vec!["attr_type", "optional_comma"]));
-    // optional_attr_generic_of_decl: (colon && attr_types)?
-    result.insert("optional_attr_generic_of_decl", create_and_rule(RuleRepeats::ZeroOrOne, vec!["colon", "attr_types"]));
-    // optional_attr_generic_decl: (less && identifier && optional_attr_generic_of_decl && greater)?
-    result.insert("optional_attr_generic_decl", create_and_rule(RuleRepeats::ZeroOrOne, vec!["less", "identifier", "optional_attr_generic_of_decl", "greater"]));
-    // attr_decl: attribute && identifier && optional_attr_generic_decl && use_when_config_matches_props && open_culry && attr_body && close_curly
-    result.insert("attr_decl", create_and_rule_once( vec!["attribute", "identifier", "optional_attr_generic_decl", "use_when_config_matches_props", "open_culry", "attr_body", "close_curly"]));
-    // mod_body_decls: (entry_or_function_decl || struct_decl || trait_decl || impl_decl || enum_decl || mod_decl || attr_decl)*
-    result.insert("mod_body_decls", create_or_rule(RuleRepeats::ZeroOrMore, vec!["entry_or_function_decl", "struct_decl", "trait_decl", "impl_decl", "enum_decl", "mod_decl", "attr_decl"]));
-    // optional_test: test?
-    result.insert("optional_test", create_and_rule(RuleRepeats::ZeroOrOne, vec!["test"]));
-    // mod_body: use_decls && mod_body_decls
-    result.insert("mod_body", create_and_rule_once( vec!["use_decls", "mod_body_decls"]));
-    // mod_decl: optional_attr_tags && optional_test && module && identifier && use_when_config_matches_props && open_curly && mod_body && close_curly
-    result.insert("mod_decl", create_and_rule_once( vec!["optional_attr_tags", "optional_test", "module", "identifier", "use_when_config_matches_props", "open_curly", "mod_body", "close_curly"]));
-    // use_group_part_alias: (as && alias)?
-    result.insert("use_group_part_alias", create_and_rule(RuleRepeats::ZeroOrOne, vec!["as", "alias"]));
-    // use_group_part_decl: (identifier && use_group_part_alias && optional_comma)+
-    result.insert("use_group_part_decl", create_and_rule(RuleRepeats::OneOrMore, vec!["identifier", "use_group_part_alias", "optional_comma"]));
-    // use_group_decl: double_colon && open_curly && use_group_part_decl && close_curly
-    result.insert("use_group_decl", create_and_rule_once( vec!["double_colon", "open_curly", "use_group_part_decl", "close_curly"]));
-    // use_decl_next_part: (double_colon && identifier)*
-    result.insert("use_decl_next_part", create_and_rule(RuleRepeats::ZeroOrMore, vec!["double_colon", "identifier"]));
-    // use_decl_form_2: use && identifier && use_decl_next_part && use_group_decl && optional_semicolon
-    result.insert("use_decl_form_2", create_and_rule_once( vec!["use", "identifier", "use_decl_next_part", "use_group_decl", "optional_semicolon"]));
-    // use_decl_form_1: use && identifier && use_decl_next_part && use_group_part_alias && optional_semicolon
-    result.insert("use_decl_form_1", create_and_rule_once( vec!["use", "identifier", "use_decl_next_part", "use_group_part_alias", "optional_semicolon"]));
-    // use_decls: (use_decl_form_1 || use_decl_form_2)*
-    result.insert("use_decls", create_or_rule(RuleRepeats::ZeroOrMore, vec!["use_decl_form_1", "use_decl_form_2"]));
-    // use_when_config_matches_prop: (identifier && colon && literal_or_identifier && optional_comma)+
-    result.insert("use_when_config_matches_prop", create_and_rule(RuleRepeats::OneOrMore, vec!["identifier", "colon", "literal_or_identifier", "optional_comma"]));
-    // use_when_config_matches_props: (open_bracket && use_when_config_matches_prop && close_bracket)?
-    result.insert("use_when_config_matches_props", create_and_rule(RuleRepeats::ZeroOrOne, vec!["open_bracket", "use_when_config_matches_prop", "close_bracket"]));
-    // mod_decl_next_part: (double_colon && identifier)*
-    result.insert("mod_decl_next_part", create_and_rule(RuleRepeats::ZeroOrMore, vec!["double_colon", "identifier"]));
-    // mod_name_decl: module && identifier && mod_decl_next_part && use_when_config_matches_props && optional_semicolon
-    result.insert("mod_name_decl", create_and_rule_once( vec!["module", "identifier", "mod_decl_next_part", "use_when_config_matches_props", "optional_semicolon"]));
-    // optional_mod_name_decl: mod_name_decl?
-    result.insert("optional_mod_name_decl", create_and_rule(RuleRepeats::ZeroOrOne, vec!["mod_name_decl"]));
-    // module_document: optional_mod_name_decl && mod_body
-    result.insert("module_document", create_and_rule_once( vec!["optional_mod_name_decl", "mod_body"]));
-    // config_value: literal || config_map || config_list
-    result.insert("config_value", create_or_rule_once( vec!["literal", "config_map", "config_list"]));
-    // config_list_entry: config_value && optional_comma
-    result.insert("config_list_entry", create_and_rule_once( vec!["config_value", "optional_comma"]));
-    // config_list_entries: config_list_entry*
-    result.insert("config_list_entries", create_and_rule(RuleRepeats::ZeroOrMore, vec!["config_list_entry"]));
-    // config_list: open_bracket && config_list_entries && close_bracket
-    result.insert("config_list", create_and_rule_once( vec!["open_bracket", "config_list_entries", "close_bracket"]));
-    // config_map_entry: identifier && colon && config_value && optional_comma
-    result.insert("config_map_entry", create_and_rule_once( vec!["identifier", "colon", "config_value", "optional_comma"]));
-    // config_map_entries: config_map_entry*
-    result.insert("config_map_entries", create_and_rule(RuleRepeats::ZeroOrMore, vec!["config_map_entry"]));
-    // config_map: open_curly && config_map_entries && close_curly
-    result.insert("config_map", create_and_rule_once( vec!["open_curly", "config_map_entries", "close_curly"]));
-    // optional_config_extention: (colon && identifier)?
-    result.insert("optional_config_extention", create_and_rule(RuleRepeats::ZeroOrOne, vec!["colon", "identifier"]));
-    // config_decl: config && identifier && optional_config_extention && config_map
-    result.insert("config_decl", create_and_rule_once( vec!["config", "identifier", "optional_config_extention", "config_map"]));
-    // config_document: config_decl+
-    result.insert("config_document", create_and_rule(RuleRepeats::OneOrMore, vec!["config_decl"]));
-    // document: config_document || module_document
-    result.insert("document", create_or_rule_once( vec!["config_document", "module_document"]));
-
-    return Some(result);
+// the same rules, for files that may only hold configuration, like config.dog
+pub fn build_config_rules() -> Grammar {
+    let mut result = build_rules();
+    result.start = "config_document";
+    return result;
 }
 
-fn create_and_rule_once(children: Vec<&'static str>) -> Box<RuleStruct> {
+#[allow(dead_code)]
+pub(super) fn create_and_rule_once(children: Vec<&'static str>) -> Box<RuleStruct> {
     Box::new(RuleStruct {
         rule_type: RuleType::And,
         repeat: RuleRepeats::Once,
@@ -679,7 +560,7 @@ fn create_and_rule_once(children: Vec<&'static str>) -> Box<RuleStruct> {
     })
 }
 
-fn create_and_rule(repeat: RuleRepeats, children: Vec<&'static str>) -> Box<RuleStruct> {
+pub(super) fn create_and_rule(repeat: RuleRepeats, children: Vec<&'static str>) -> Box<RuleStruct> {
     Box::new(RuleStruct {
         rule_type: RuleType::And,
         repeat,
@@ -688,8 +569,7 @@ fn create_and_rule(repeat: RuleRepeats, children: Vec<&'static str>) -> Box<Rule
     })
 }
 
-#[allow(dead_code)]
-fn create_or_rule(repeat: RuleRepeats, children: Vec<&'static str>) -> Box<RuleStruct> {
+pub(super) fn create_or_rule(repeat: RuleRepeats, children: Vec<&'static str>) -> Box<RuleStruct> {
     Box::new(
         RuleStruct {
             rule_type: RuleType::Or,
@@ -700,7 +580,8 @@ fn create_or_rule(repeat: RuleRepeats, children: Vec<&'static str>) -> Box<RuleS
     )
 }
 
-fn create_or_rule_once(children: Vec<&'static str>) -> Box<RuleStruct> {
+#[allow(dead_code)]
+pub(super) fn create_or_rule_once(children: Vec<&'static str>) -> Box<RuleStruct> {
     Box::new(
         RuleStruct {
             rule_type: RuleType::Or,
@@ -711,7 +592,19 @@ fn create_or_rule_once(children: Vec<&'static str>) -> Box<RuleStruct> {
     )
 }
 
-fn create_label_match(match_labels: Vec<&'static str>) -> Box<RuleStruct> {
+// the only child is the rule for the operands between the operators
+pub(super) fn create_precedence_rule(operand_rule: &'static str) -> Box<RuleStruct> {
+    Box::new(
+        RuleStruct {
+            rule_type: RuleType::Precedence,
+            repeat: RuleRepeats::Once,
+            children: vec![operand_rule],
+            match_labels: vec![],
+        }
+    )
+}
+
+pub(super) fn create_label_match(match_labels: Vec<&'static str>) -> Box<RuleStruct> {
     Box::new(
         RuleStruct {
             rule_type: RuleType::Match,
@@ -722,3 +615,124 @@ fn create_label_match(match_labels: Vec<&'static str>) -> Box<RuleStruct> {
     )
 }
 
+
+#[cfg(test)]
+mod parse_rules_tests {
+    use std::time::Instant;
+
+    use crate::lex::lex_code;
+    use crate::parse::parse_model::ParseModel;
+    use crate::parse::parse_rules::{build_config_rules, build_rules, create_and_rule, create_and_rule_once,
+                                    create_label_match, create_or_rule_once, Grammar, Parser, RuleRepeats};
+
+    // a module with many functions whose bodies are nested calls, followed by a function with a
+    // call that is never finished. every level of that call is a place for the parser to backtrack.
+    fn synthetic_module(functions: usize, broken_depth: usize) -> String {
+        let mut result = String::new();
+        for index in 0..functions {
+            let mut call = format!("{} + {}", index, index);
+            for level in 0..3 {
+                call = format!("f{}(x.y{}, {}, ({}) * 2)", level, level, call, level);
+            }
+            result += &format!("fn function_{}(a: integer) {{\n  {}\n  g(a)\n}}\n", index, call);
+        }
+        let mut call = "1 +".to_string();
+        for level in 0..broken_depth {
+            call = format!("f{}(x, {})", level, call);
+        }
+        result += &format!("fn broken() {{\n  {}\n}}\n", call);
+        return result;
+    }
+
+    // the dog rules with the expression rules from before precedence climbing. they nest binary
+    // operations to the right, and backtrack over a whole call whenever it isn't followed by an
+    // operator, which is what the memo has to make up for.
+    fn backtracking_grammar() -> Grammar {
+        let mut result = build_rules();
+        result.insert("comparison", create_or_rule_once(vec!["boolean_equals", "boolean_less", "boolean_greater", "boolean_not_equal", "boolean_greater_or_equal", "boolean_less_or_equal"]));
+        result.insert("binary_operator", create_or_rule_once(vec!["plus", "minus", "multiply", "divide", "dereference_instance_member", "dereference_const_member", "comparison"]));
+        result.insert("unary_operator", create_or_rule_once(vec!["exclamation", "minus"]));
+        result.insert("function_invocation", create_and_rule_once(vec!["qualified_identifier", "open_paren", "params", "close_paren"]));
+        result.insert("variable_literal_invocation", create_or_rule_once(vec!["function_invocation", "literal_or_identifier"]));
+        result.insert("binary_operation", create_and_rule_once(vec!["variable_literal_invocation", "binary_operator", "expression"]));
+        result.insert("unary_operation", create_and_rule_once(vec!["unary_operator", "expression"]));
+        result.insert("expression_part", create_or_rule_once(vec!["interpolated_string", "function_invocation", "struct_constructor", "expression_group", "binary_operation", "unary_operation", "variable_literal_invocation", "range_expression"]));
+        result.insert("trailing_binary_expression_part", create_and_rule(RuleRepeats::ZeroOrMore, vec!["binary_operator", "expression"]));
+        result.insert("expression", create_and_rule_once(vec!["expression_part", "trailing_binary_expression_part"]));
+        return result;
+    }
+
+    fn parse_synthetic(code: &str, memoize: bool) -> (Option<ParseModel>, usize) {
+        let grammar = backtracking_grammar();
+        let mut parser = if memoize { Parser::new(&grammar) } else { Parser::without_memo(&grammar) };
+        let mut token_stream = lex_code(code).unwrap();
+        let start = Instant::now();
+        let result = parser.match_document(&mut token_stream);
+        println!("memoize: {}, {} tokens, {} rule evaluations in {:?}",
+                 memoize, token_stream.size(), parser.rule_evaluations, start.elapsed());
+        return (result, parser.rule_evaluations);
+    }
+
+    #[test]
+    fn memoized_parse_benchmark() {
+        let code = synthetic_module(20, 6);
+        let (plain_result, plain_evaluations) = parse_synthetic(&code, false);
+        let (memoized_result, memoized_evaluations) = parse_synthetic(&code, true);
+        assert!(memoized_result.is_some());
+        assert_eq!(plain_result, memoized_result);
+        assert!(memoized_evaluations * 10 < plain_evaluations,
+                "{} evaluations with the memo, {} without", memoized_evaluations, plain_evaluations);
+
+        // with the memo, each extra level of nesting only costs what its tokens cost
+        let (_, deeper_evaluations) = parse_synthetic(&synthetic_module(20, 12), true);
+        assert!(deeper_evaluations < memoized_evaluations + 1000,
+                "{} evaluations at depth 12, {} at depth 6", deeper_evaluations, memoized_evaluations);
+    }
+
+    // a grammar for lists like (a, b, (c)), to show that parsing doesn't depend on the dog rules
+    fn list_grammar() -> Grammar {
+        let mut result = Grammar::new("list");
+        result.insert("open_paren", create_label_match(vec!["_open_paren"]));
+        result.insert("close_paren", create_label_match(vec!["_close_paren"]));
+        result.insert("comma", create_label_match(vec!["_comma"]));
+        result.insert("identifier", create_label_match(vec!["_word"]));
+        result.insert("optional_comma", create_and_rule(RuleRepeats::ZeroOrOne, vec!["comma"]));
+        result.insert("item", create_or_rule_once(vec!["identifier", "list"]));
+        result.insert("items", create_and_rule(RuleRepeats::ZeroOrMore, vec!["item", "optional_comma"]));
+        result.insert("list", create_and_rule_once(vec!["open_paren", "items", "close_paren"]));
+        return result;
+    }
+
+    #[test]
+    fn parse_with_custom_grammar() {
+        let grammar = list_grammar();
+        let mut token_stream = lex_code("(a, b, (c, ()))").unwrap();
+        let list = Parser::new(&grammar).match_document(&mut token_stream).unwrap();
+        assert!(!token_stream.has_next());
+        assert_eq!("list", list.label);
+        let items = &list.children[1];
+        assert_eq!(3, items.children.len());
+        assert_eq!("b", items.children[1].children[0].tokens[0].value);
+        assert_eq!("list", items.children[2].children[0].label);
+
+        let mut token_stream = lex_code("(a, b").unwrap();
+        assert!(Parser::new(&grammar).match_document(&mut token_stream).is_none());
+    }
+
+    #[test]
+    fn parse_on_several_threads() {
+        let grammar = build_config_rules();
+        let code = "config Base { name: \"base\", sizes: [1, 2, 3] }\nconfig Release : Base { optimize: true }";
+        let expected = Parser::new(&grammar).match_document(&mut lex_code(code).unwrap()).unwrap();
+        assert_eq!("config_document", expected.label);
+        std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..4).map(|_| scope.spawn(|| {
+                let mut token_stream = lex_code(code).unwrap();
+                return Parser::new(&grammar).match_document(&mut token_stream);
+            })).collect();
+            for thread in threads {
+                assert_eq!(Some(&expected), thread.join().unwrap().as_ref());
+            }
+        });
+    }
+}
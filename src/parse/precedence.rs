@@ -0,0 +1,238 @@
+// Expressions are matched with precedence climbing (a Pratt parser) instead of and/or rules. The
+// rules can only nest to the right, so `a - b - c` came out as `a - (b - c)` and `a * b + c` as
+// `a * (b + c)`. Here each operator has a binding power, and an operator only takes the operand
+// to its right if nothing that binds tighter wants it first.
+//
+// The operators, from the tightest binding to the loosest:
+//
+//   power  operators                 kind     associativity
+//   7      a.b  a::b  a(b, c)        postfix  left to right
+//   6      !a  -a                    prefix
+//   5      a as T                    postfix  left to right
+//   4      a * b  a / b              binary   left
+//   3      a + b  a - b              binary   left
+//   2      == != < > <= >=           binary   left
+//
+// So `-a.b(c) as float * 2 + 1 < x` is `((((-(a.b)(c)) as float) * 2) + 1) < x`.
+//
+// The operands between the operators are matched by a normal rule, so literals, names, groups
+// and the rest of the grammar stay in the grammar. The nodes built here are:
+//
+//   binary_operation: left, operator, right
+//   unary_operation: operator, operand
+//   cast_operation: operand, as, data_type
+//   member_access: object, dereference_instance_member or dereference_const_member, identifier
+//   function_invocation: function, open_paren, params, close_paren
+//
+// A syntax error that could have been fixed by an operator says "operator" rather than listing
+// every one of them, and one that needed an operand says "expression".
+
+use std::sync::Arc;
+
+use crate::lex::token_stream::TokenStream;
+use crate::parse::parse_model::ParseModel;
+use crate::parse::parse_rules::Parser;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OperatorKind {
+    Prefix,
+    Binary,
+    Cast,
+    Member,
+    Call,
+}
+
+struct Operator {
+    // the grammar rule that matches the operator
+    rule: &'static str,
+    kind: OperatorKind,
+    power: u8,
+}
+
+// operators that start the same way are listed longest first, so that <= isn't read as <
+const OPERATORS: [Operator; 16] = [
+    Operator { rule: "dereference_instance_member", kind: OperatorKind::Member, power: 7 },
+    Operator { rule: "dereference_const_member", kind: OperatorKind::Member, power: 7 },
+    Operator { rule: "open_paren", kind: OperatorKind::Call, power: 7 },
+    Operator { rule: "not_operator", kind: OperatorKind::Prefix, power: 6 },
+    Operator { rule: "minus_operator", kind: OperatorKind::Prefix, power: 6 },
+    Operator { rule: "as", kind: OperatorKind::Cast, power: 5 },
+    Operator { rule: "multiply", kind: OperatorKind::Binary, power: 4 },
+    Operator { rule: "divide", kind: OperatorKind::Binary, power: 4 },
+    Operator { rule: "plus", kind: OperatorKind::Binary, power: 3 },
+    Operator { rule: "minus", kind: OperatorKind::Binary, power: 3 },
+    Operator { rule: "boolean_equals", kind: OperatorKind::Binary, power: 2 },
+    Operator { rule: "boolean_not_equal", kind: OperatorKind::Binary, power: 2 },
+    Operator { rule: "boolean_less_or_equal", kind: OperatorKind::Binary, power: 2 },
+    Operator { rule: "boolean_greater_or_equal", kind: OperatorKind::Binary, power: 2 },
+    Operator { rule: "boolean_less", kind: OperatorKind::Binary, power: 2 },
+    Operator { rule: "boolean_greater", kind: OperatorKind::Binary, power: 2 },
+];
+
+fn node(label: &str, children: Vec<Arc<ParseModel>>) -> Arc<ParseModel> {
+    return Arc::new(ParseModel {
+        label: label.to_string(),
+        tokens: vec![],
+        children,
+        trivia: vec![],
+    });
+}
+
+// the rules that an expression may start with besides its operand, and every rule it may use
+pub(super) fn prefix_rules() -> Vec<&'static str> {
+    return OPERATORS.iter().filter(|operator| operator.kind == OperatorKind::Prefix).map(|operator| operator.rule).collect();
+}
+
+pub(super) fn referenced_rules() -> Vec<&'static str> {
+    let mut result: Vec<&'static str> = OPERATORS.iter().map(|operator| operator.rule).collect();
+    result.extend(["data_type", "identifier", "params", "close_paren"]);
+    return result;
+}
+
+impl<'a> Parser<'a> {
+    pub(super) fn precedence_match_with(&mut self, label: &str, operand_rule: &'static str, token_stream: &mut TokenStream) -> Option<Arc<ParseModel>> {
+        if !token_stream.has_next() {
+            return None;
+        }
+        let expression = self.match_expression(operand_rule, 0, token_stream)?;
+        return Some(node(label, vec![expression]));
+    }
+
+    // matches an expression whose operators all bind at least as tightly as min_power
+    fn match_expression(&mut self, operand_rule: &'static str, min_power: u8, token_stream: &mut TokenStream) -> Option<Arc<ParseModel>> {
+        let position = token_stream.position();
+        let mark = self.failure_mark();
+        let mut left = None;
+        for operator in OPERATORS.iter().filter(|operator| operator.kind == OperatorKind::Prefix) {
+            if let Some(operator_model) = self.match_rule_by_name(operator.rule, token_stream) {
+                if let Some(operand) = self.match_expression(operand_rule, operator.power, token_stream) {
+                    left = Some(node("unary_operation", vec![operator_model, operand]));
+                    break;
+                }
+                token_stream.restore(position);
+            }
+        }
+        if left.is_none() {
+            left = self.match_rule_by_name(operand_rule, token_stream);
+        }
+        if left.is_none() {
+            let offset = token_stream.next_offset().unwrap_or(token_stream.matches.len());
+            self.expect_instead(mark, offset, "expression");
+        }
+        let mut left = left?;
+
+        loop {
+            match self.match_operation(operand_rule, min_power, left, token_stream) {
+                Ok(operation) => left = operation,
+                Err(unchanged) => return Some(unchanged),
+            }
+        }
+    }
+
+    // matches the first operator after left that binds tightly enough, with whatever it needs on
+    // its right. gives left back and leaves the token stream alone if there isn't one.
+    fn match_operation(&mut self, operand_rule: &'static str, min_power: u8, left: Arc<ParseModel>, token_stream: &mut TokenStream) -> Result<Arc<ParseModel>, Arc<ParseModel>> {
+        let position = token_stream.position();
+        for operator in OPERATORS.iter().filter(|operator| operator.kind != OperatorKind::Prefix && operator.power >= min_power) {
+            let mark = self.failure_mark();
+            let operator_model = match self.match_rule_by_name(operator.rule, token_stream) {
+                Some(operator_model) => operator_model,
+                None => {
+                    if operator.kind != OperatorKind::Call {
+                        let offset = token_stream.next_offset().unwrap_or(token_stream.matches.len());
+                        self.expect_instead(mark, offset, "operator");
+                    }
+                    continue;
+                }
+            };
+            let rest = match operator.kind {
+                // left associative, so the right side may only hold operators that bind tighter
+                OperatorKind::Binary => self.match_expression(operand_rule, operator.power + 1, token_stream)
+                    .map(|right| ("binary_operation", vec![right])),
+                OperatorKind::Cast => self.match_rule_by_name("data_type", token_stream)
+                    .map(|data_type| ("cast_operation", vec![data_type])),
+                OperatorKind::Member => self.match_rule_by_name("identifier", token_stream)
+                    .map(|member| ("member_access", vec![member])),
+                OperatorKind::Call => self.match_call_arguments(token_stream)
+                    .map(|(params, close_paren)| ("function_invocation", vec![params, close_paren])),
+                OperatorKind::Prefix => None,
+            };
+            if let Some((label, rest)) = rest {
+                let mut children = vec![left, operator_model];
+                children.extend(rest);
+                return Ok(node(label, children));
+            }
+            token_stream.restore(position);
+        }
+        return Err(left);
+    }
+
+    fn match_call_arguments(&mut self, token_stream: &mut TokenStream) -> Option<(Arc<ParseModel>, Arc<ParseModel>)> {
+        let params = self.match_rule_by_name("params", token_stream)?;
+        let close_paren = self.match_rule_by_name("close_paren", token_stream)?;
+        return Some((params, close_paren));
+    }
+}
+
+#[cfg(test)]
+mod precedence_tests {
+    use crate::lex::lex_code;
+    use crate::parse::parse_model::ParseModel;
+    use crate::parse::parse_rules::{build_rules, Parser};
+
+    // writes the expression with a pair of parentheses around every operation
+    fn render(model: &ParseModel) -> String {
+        let child = |index: usize| render(&model.children[index]);
+        return match model.label.as_str() {
+            "expression" => child(0),
+            "binary_operation" => format!("({} {} {})", child(0), child(1), child(2)),
+            "unary_operation" => format!("({}{})", child(0), child(1)),
+            "cast_operation" => format!("({} as {})", child(0), child(2)),
+            "member_access" => format!("({}{}{})", child(0), child(1), child(2)),
+            "function_invocation" => format!("{}({})", child(0), child(2)),
+            "expression_group" => child(1),
+            // each parameter is a qualifier, the expression and a comma
+            "params" => {
+                let params: Vec<String> = model.children.iter().map(|param| render(&param.children[1])).collect();
+                params.join(", ")
+            }
+            _ => {
+                let mut text: Vec<String> = model.tokens.iter().map(|token| token.value.clone()).collect();
+                text.extend(model.children.iter().map(|child| render(child)));
+                text.join("")
+            }
+        };
+    }
+
+    fn parse_expression(code: &str) -> String {
+        let mut grammar = build_rules();
+        grammar.start = "expression";
+        let mut token_stream = lex_code(code).unwrap();
+        let expression = Parser::new(&grammar).match_document(&mut token_stream).unwrap();
+        assert!(!token_stream.has_next(), "{} wasn't parsed to the end", code);
+        return render(&expression);
+    }
+
+    #[test]
+    fn binary_precedence_and_associativity() {
+        assert_eq!("((a - b) - c)", parse_expression("a - b - c"));
+        assert_eq!("(a + (b * c))", parse_expression("a + b * c"));
+        assert_eq!("((a * b) + c)", parse_expression("a * b + c"));
+        assert_eq!("((a / b) * c)", parse_expression("a / b * c"));
+        assert_eq!("((a + b) * c)", parse_expression("(a + b) * c"));
+        assert_eq!("((a + 1) <= (b * 2))", parse_expression("a + 1 <= b * 2"));
+        assert_eq!("((a == b) != c)", parse_expression("a == b != c"));
+        assert_eq!("(a > b)", parse_expression("a > b"));
+    }
+
+    #[test]
+    fn unary_cast_member_and_call() {
+        assert_eq!("((-a) - (-b))", parse_expression("-a - -b"));
+        assert_eq!("((!a) == b)", parse_expression("!a == b"));
+        assert_eq!("(((-a) as float) * 2)", parse_expression("-a as float * 2"));
+        assert_eq!("(-((a.b).c))", parse_expression("-a.b.c"));
+        assert_eq!("((std::math)::max)(a, (b + 1))", parse_expression("std::math::max(a, b + 1)"));
+        assert_eq!("(f(x).y)(z)()", parse_expression("f(x).y(z)()"));
+        assert_eq!("(((((-(a.b)(c)) as float) * 2) + 1) < x)", parse_expression("-a.b(c) as float * 2 + 1 < x"));
+    }
+}
@@ -0,0 +1,159 @@
+use crate::lex::pattern_init::WORD;
+use crate::lex::token_stream::{Match, TokenStream};
+use crate::lex::token_text;
+use crate::source::{FileId, SourceMap};
+
+// stands in for a token label when the parser expected the file to end
+pub const END_OF_FILE: &str = "end of file";
+
+// The furthest the parser got into a file before no rule could go on, with the tokens that would
+// have let it go on, like: expected `}` or identifier, found `let` at main.dog:12:5. The file's
+// name is looked up in the source map when the error is rendered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    // what could have come next, in the words used in the message
+    pub expected: Vec<String>,
+    // the token that was there instead, none at the end of the file
+    pub found: Option<Box<Match>>,
+    pub file_id: FileId,
+    // byte offsets into the source of the token that was found
+    pub start: usize,
+    pub end: usize,
+    // both start at 1
+    pub line_number: usize,
+    pub line_offset: usize,
+}
+
+impl SyntaxError {
+    // expected_labels are token labels, found_offset is where the token that was found is in the
+    // token stream. an offset past the last token is the end of the file.
+    pub fn new(expected_labels: &[&str], found_offset: usize, token_stream: &TokenStream, file_id: FileId) -> SyntaxError {
+        let mut expected: Vec<String> = vec![];
+        for label in expected_labels {
+            let description = describe_label(label);
+            if !expected.contains(&description) {
+                expected.push(description);
+            }
+        }
+
+        let found = token_stream.peek(found_offset).cloned().map(Box::new);
+        let (start, end, line_number, line_offset) = if let Some(found) = &found {
+            (found.start_byte_offset, found.end_byte_offset, found.line_number, found.line_offset)
+        } else if let Some(last) = token_stream.matches.iter().rev().find(|token| !token.skip) {
+            // the file ended too soon, which is just after the last thing in it
+            (last.end_byte_offset, last.end_byte_offset, last.end_line_number, last.end_line_offset)
+        } else {
+            (0, 0, 1, 1)
+        };
+        return SyntaxError {
+            expected,
+            found,
+            file_id,
+            start,
+            end,
+            line_number,
+            line_offset,
+        };
+    }
+
+    // the error without where it happened: expected `}` or identifier, found `let`
+    pub fn message(&self) -> String {
+        let found = match &self.found {
+            Some(found) => format!("`{}`", found.value.trim()),
+            None => END_OF_FILE.to_string(),
+        };
+        return match self.expected.split_last() {
+            None => format!("unexpected {}", found),
+            Some((last, [])) => format!("expected {}, found {}", last, found),
+            Some((last, rest)) => format!("expected {} or {}, found {}", rest.join(", "), last, found),
+        };
+    }
+
+    // the message and where it happened
+    pub fn render(&self, source_map: &SourceMap) -> String {
+        return format!("{} at {}:{}:{}", self.message(), source_map.get(self.file_id).name, self.line_number, self.line_offset);
+    }
+}
+
+// keywords and punctuation are shown as they are written, other tokens by what they are
+fn describe_label(label: &str) -> String {
+    if label == WORD {
+        return "identifier".to_string();
+    }
+    if label == END_OF_FILE || !label.starts_with('_') {
+        // not a token label, but something the parser describes itself, like "operator"
+        return label.to_string();
+    }
+    if let Some(text) = token_text(label) {
+        return format!("`{}`", text);
+    }
+    return label.trim_start_matches('_').replace('_', " ");
+}
+
+#[cfg(test)]
+mod syntax_error_tests {
+    use crate::lex::lex;
+    use crate::parse::{parse, parse_with_recovery};
+    use crate::parse::parse_model::ParseModel;
+    use crate::source::SourceMap;
+
+    fn check(code: &str) -> String {
+        let mut source_map = SourceMap::new();
+        let file_id = source_map.add_file(Some("main.dog"), Some("src/main.dog"), code.to_string());
+        let token_stream = lex(&source_map, file_id).unwrap();
+        return match parse(token_stream, file_id) {
+            Ok(_) => "ok".to_string(),
+            Err(error) => error.render(&source_map),
+        };
+    }
+
+    #[test]
+    fn report_furthest_failure() {
+        assert_eq!("ok", check("fn main() {\n  print(\"hi\")\n}\n"));
+        assert_eq!("expected operator, `(`, `,`, identifier, expression or `)`, found `}` at main.dog:3:1", check("fn main() {\n  print(\"hi\"\n}\n"));
+        assert_eq!("expected `(`, operator or `}`, found end of file at main.dog:2:14", check("fn main() {\n  print(\"hi\")\n"));
+        assert_eq!("unexpected end of file at main.dog:1:1", check(""));
+    }
+
+    fn error_nodes(model: &ParseModel) -> Vec<String> {
+        let mut result = vec![];
+        if model.label == "error" {
+            let text: Vec<&str> = model.tokens.iter().map(|token| token.value.as_str()).collect();
+            result.push(text.join(" "));
+        }
+        for child in &model.children {
+            result.extend(error_nodes(child));
+        }
+        return result;
+    }
+
+    #[test]
+    fn recover_from_every_error() {
+        let code = "fn main() {\n  f(a +)\n  g(let)\n  h(1 +\n}\n\nfn broken(a: integer {\n  h(1)\n}\n\nfn fine() {\n  h(2)\n}\n";
+        let mut source_map = SourceMap::new();
+        let file_id = source_map.add_file(Some("main.dog"), Some("src/main.dog"), code.to_string());
+        let token_stream = lex(&source_map, file_id).unwrap();
+        let (model, errors) = parse_with_recovery(token_stream, file_id);
+
+        let errors: Vec<String> = errors.iter().map(|error| error.render(&source_map)).collect();
+        assert_eq!(vec![
+            "expected expression, found `)` at main.dog:2:8",
+            "expected expression, found `let` at main.dog:3:5",
+            "expected expression, found `}` at main.dog:5:1",
+            "expected `:`, `<`, `=`, `,`, identifier or `)`, found `{` at main.dog:7:22",
+        ], errors);
+        // `f` on its own is a statement, so the error starts after it
+        assert_eq!(vec!["( a + )", "( let )", "( 1 +", "fn broken ( a : integer { h ( 1 ) }"],
+                   error_nodes(&model.unwrap()));
+    }
+
+    #[test]
+    fn recover_nothing_from_a_good_file() {
+        let mut source_map = SourceMap::new();
+        let file_id = source_map.add_file(Some("main.dog"), Some("src/main.dog"), "fn main() {\n  f(a)\n}\n".to_string());
+        let token_stream = lex(&source_map, file_id).unwrap();
+        let (model, errors) = parse_with_recovery(token_stream, file_id);
+        assert!(errors.is_empty());
+        assert!(error_nodes(&model.unwrap()).is_empty());
+    }
+}
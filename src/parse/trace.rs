@@ -0,0 +1,186 @@
+// A trace of what the parser tried, for working on the grammar: each rule as the parser enters
+// it, where in the file, and whether it matched. It is off unless asked for with --trace-parse,
+// since even a small file tries thousands of rules.
+//
+// As text, each rule is a line going in and a line coming out, indented by how deep it is:
+//
+//   > function_decl at 3:1
+//     > function_signature_decl at 3:1
+//     < function_signature_decl matched, next at 3:18
+//   < function_decl missed
+//
+// As json, each line is an object: {"event":"enter","rule":"function_decl","depth":0,"line":3,"column":1}
+// and {"event":"exit",...,"matched":false,"remembered":false}. The position is null at the end of
+// the file. A rule that was matched at the same place before is remembered rather than tried
+// again, so nothing is traced inside of it.
+
+use std::io;
+use std::io::Write;
+use std::sync::OnceLock;
+
+use crate::lex::token_stream::TokenStream;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TraceFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceOptions {
+    pub format: TraceFormat,
+    // only these rules, and the rules they try, are traced. every rule is when it's empty.
+    pub rules: Vec<String>,
+}
+
+static TRACE_OPTIONS: OnceLock<TraceOptions> = OnceLock::new();
+
+// traces every parser made from now on to stderr
+pub fn enable_trace(options: TraceOptions) {
+    let _ = TRACE_OPTIONS.set(options);
+}
+
+pub(super) fn default_tracer() -> Option<Tracer> {
+    return TRACE_OPTIONS.get().map(|options| Tracer::new(options.clone(), Box::new(io::stderr())));
+}
+
+pub struct Tracer {
+    options: TraceOptions,
+    output: Box<dyn Write>,
+    // whether each of the rules that the parser is in is traced
+    open_rules: Vec<bool>,
+}
+
+impl Tracer {
+    pub fn new(options: TraceOptions, output: Box<dyn Write>) -> Tracer {
+        return Tracer {
+            options,
+            output,
+            open_rules: vec![],
+        };
+    }
+
+    pub(super) fn enter(&mut self, rule: &str, token_stream: &TokenStream) {
+        let traced = self.options.rules.is_empty()
+            || self.open_rules.last().copied().unwrap_or(false)
+            || self.options.rules.iter().any(|name| name == rule);
+        if traced {
+            let depth = self.depth();
+            let line = match self.options.format {
+                TraceFormat::Text => format!("{}> {} at {}", "  ".repeat(depth), rule, text_position(token_stream)),
+                TraceFormat::Json => format!("{{\"event\":\"enter\",\"rule\":\"{}\",\"depth\":{},{}}}",
+                                             rule, depth, json_position(token_stream)),
+            };
+            let _ = writeln!(self.output, "{}", line);
+        }
+        self.open_rules.push(traced);
+    }
+
+    pub(super) fn exit(&mut self, rule: &str, matched: bool, remembered: bool, token_stream: &TokenStream) {
+        if !self.open_rules.pop().unwrap_or(false) {
+            return;
+        }
+        let depth = self.depth();
+        let line = match self.options.format {
+            TraceFormat::Text => {
+                let outcome = if matched { format!("matched, next at {}", text_position(token_stream)) } else { "missed".to_string() };
+                format!("{}< {} {}{}", "  ".repeat(depth), rule, outcome, if remembered { " (remembered)" } else { "" })
+            }
+            TraceFormat::Json => format!("{{\"event\":\"exit\",\"rule\":\"{}\",\"depth\":{},\"matched\":{},\"remembered\":{},{}}}",
+                                         rule, depth, matched, remembered, json_position(token_stream)),
+        };
+        let _ = writeln!(self.output, "{}", line);
+    }
+
+    // how many traced rules the parser is in
+    fn depth(&self) -> usize {
+        return self.open_rules.iter().filter(|traced| **traced).count();
+    }
+}
+
+// where the next token is, as line:offset
+fn text_position(token_stream: &TokenStream) -> String {
+    return match token_stream.next_offset().and_then(|offset| token_stream.peek(offset)) {
+        Some(token) => format!("{}:{}", token.line_number, token.line_offset),
+        None => "end of file".to_string(),
+    };
+}
+
+fn json_position(token_stream: &TokenStream) -> String {
+    return match token_stream.next_offset().and_then(|offset| token_stream.peek(offset)) {
+        Some(token) => format!("\"line\":{},\"column\":{}", token.line_number, token.line_offset),
+        None => "\"line\":null,\"column\":null".to_string(),
+    };
+}
+
+#[cfg(test)]
+mod trace_tests {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    use crate::lex::lex_code;
+    use crate::parse::parse_rules::{build_rules, Parser};
+    use crate::parse::trace::{TraceFormat, TraceOptions, Tracer};
+
+    // keeps what the tracer writes where the test can still read it
+    #[derive(Clone)]
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            return Ok(buf.len());
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            return Ok(());
+        }
+    }
+
+    fn trace(code: &str, format: TraceFormat, rules: Vec<&str>) -> Vec<String> {
+        let output = SharedOutput(Arc::new(Mutex::new(vec![])));
+        let options = TraceOptions {
+            format,
+            rules: rules.iter().map(|rule| rule.to_string()).collect(),
+        };
+        let grammar = build_rules();
+        let mut parser = Parser::with_tracer(&grammar, Tracer::new(options, Box::new(output.clone())));
+        let mut token_stream = lex_code(code).unwrap();
+        assert!(parser.match_document(&mut token_stream).is_some());
+        let text = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        return text.lines().map(|line| line.to_string()).collect();
+    }
+
+    #[test]
+    fn trace_rules_by_name() {
+        let lines = trace("fn main() {\n  f(1)\n}\n", TraceFormat::Text, vec!["function_params_group"]);
+        assert_eq!(vec![
+            "> function_params_group at 1:8",
+            "  > open_paren at 1:8",
+            "  < open_paren matched, next at 1:9",
+            "  > function_params at 1:9",
+            "    > identifier at 1:9",
+            "    < identifier missed",
+            "  < function_params matched, next at 1:9",
+            "  > close_paren at 1:9",
+            "  < close_paren matched, next at 1:11",
+            "< function_params_group matched, next at 1:11",
+        ], lines);
+
+        // everything when there's no filter, in and out the same number of times
+        let lines = trace("fn main() {\n  f(1)\n}\n", TraceFormat::Text, vec![]);
+        assert_eq!("> document at 1:1", lines[0]);
+        assert_eq!("< document matched, next at end of file", lines[lines.len() - 1]);
+        assert_eq!(lines.iter().filter(|line| line.trim_start().starts_with('>')).count(),
+                   lines.iter().filter(|line| line.trim_start().starts_with('<')).count());
+    }
+
+    #[test]
+    fn trace_as_json() {
+        let lines = trace("fn main() {}", TraceFormat::Json, vec!["close_paren"]);
+        assert_eq!(vec![
+            "{\"event\":\"enter\",\"rule\":\"close_paren\",\"depth\":0,\"line\":1,\"column\":9}",
+            "{\"event\":\"exit\",\"rule\":\"close_paren\",\"depth\":0,\"matched\":true,\"remembered\":false,\"line\":1,\"column\":11}",
+        ], lines);
+    }
+}
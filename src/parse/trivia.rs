@@ -0,0 +1,111 @@
+// Gives each token of a parse model the whitespace, line ends and comments around it, from a
+// token stream that kept them (see lex_lossless). Every token of the stream is then somewhere in
+// the model, so the file can be written back out byte for byte, which is what a formatter or a
+// refactoring needs: it can move or change a node and keep the comments that go with it.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::lex::token_stream::{Match, TokenStream};
+use crate::parse::parse_model::{ParseModel, Trivia};
+
+pub(super) fn attach_trivia(model: &mut ParseModel, token_stream: &TokenStream) {
+    // the trivia of each token that isn't skipped, by where the token starts
+    let mut trivia_by_start: HashMap<usize, Trivia> = HashMap::new();
+    let mut leading: Vec<Box<Match>> = vec![];
+    let mut last_start = None;
+    let mut index = 0;
+    while index < token_stream.matches.len() {
+        let token = &token_stream.matches[index];
+        index += 1;
+        if token.skip {
+            leading.push(token.clone());
+            continue;
+        }
+        // trailing trivia ends with the line
+        let mut trailing = vec![];
+        while index < token_stream.matches.len() && token_stream.matches[index].skip {
+            let trivia = token_stream.matches[index].clone();
+            index += 1;
+            let ends_line = trivia.value.contains('\n');
+            trailing.push(trivia);
+            if ends_line {
+                break;
+            }
+        }
+        trivia_by_start.insert(token.start_byte_offset, Trivia {
+            leading: std::mem::take(&mut leading),
+            trailing,
+        });
+        last_start = Some(token.start_byte_offset);
+    }
+    // the end of the file
+    if let Some(last) = last_start.and_then(|last_start| trivia_by_start.get_mut(&last_start)) {
+        last.trailing.append(&mut leading);
+    }
+
+    set_trivia(model, &mut trivia_by_start);
+}
+
+fn set_trivia(model: &mut ParseModel, trivia_by_start: &mut HashMap<usize, Trivia>) {
+    model.trivia = model.tokens.iter()
+        .map(|token| trivia_by_start.remove(&token.start_byte_offset).unwrap_or_default())
+        .collect();
+    for child in &mut model.children {
+        set_trivia(Arc::make_mut(child), trivia_by_start);
+    }
+}
+
+#[cfg(test)]
+mod trivia_tests {
+    use crate::lex::lex_lossless;
+    use crate::parse::parse_lossless;
+    use crate::parse::parse_model::ParseModel;
+    use crate::source::SourceMap;
+
+    fn first_token<'a>(model: &'a ParseModel, value: &str) -> Option<&'a ParseModel> {
+        if model.tokens.first().is_some_and(|token| token.value == value) {
+            return Some(model);
+        }
+        return model.children.iter().find_map(|child| first_token(child, value));
+    }
+
+    #[test]
+    fn round_trip_a_file() {
+        let code = "\
+// a file with every kind of trivia
+mod tools    /* inline */
+
+/// Says hi.
+fn   greet(name: String) {   // trailing
+\tprint(\"hi {  name  }!\")\r
+  let Ǆ = \"\"\"raw\n  text\"\"\"
+
+}
+   // the end, without a newline";
+        let mut source_map = SourceMap::new();
+        let file_id = source_map.add_file(Some("main.dog"), Some("src/main.dog"), code.to_string());
+        let token_stream = lex_lossless(&source_map, file_id).unwrap();
+        let model = parse_lossless(token_stream, file_id).unwrap();
+        assert_eq!(code, model.source_text(code));
+
+        // what's on the line after a token trails it, the lines before a token lead it
+        let tools = first_token(&model, "tools").unwrap();
+        let trailing: Vec<&str> = tools.trivia[0].trailing.iter().map(|trivia| trivia.value.as_str()).collect();
+        assert_eq!(vec!["    ", "/* inline */", "\n"], trailing);
+        let doc_comment = first_token(&model, "/// Says hi.\n").unwrap();
+        let leading: Vec<&str> = doc_comment.trivia[0].leading.iter().map(|trivia| trivia.value.as_str()).collect();
+        assert_eq!(vec!["\n"], leading);
+        let function = first_token(&model, "fn").unwrap();
+        let trailing: Vec<&str> = function.trivia[0].trailing.iter().map(|trivia| trivia.value.as_str()).collect();
+        assert_eq!(vec!["   "], trailing);
+        let open_curly = first_token(&model, "{").unwrap();
+        let trailing: Vec<&str> = open_curly.trivia[0].trailing.iter().map(|trivia| trivia.label.as_str()).collect();
+        assert_eq!(vec!["_whitespace", "_comment"], trailing);
+
+        // the comment after the last token belongs to it
+        let close_curly = first_token(&model, "}").unwrap();
+        let trailing: Vec<&str> = close_curly.trivia[0].trailing.iter().map(|trivia| trivia.value.as_str()).collect();
+        assert_eq!(vec!["\n", "   ", "// the end, without a newline"], trailing);
+    }
+}
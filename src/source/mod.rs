@@ -0,0 +1,140 @@
+// Every file the compiler reads is registered here exactly once. Everything downstream of the
+// lexer refers to a file by its small FileId rather than carrying around copies of the file's
+// name and path, and looks the details up here when it needs them (error messages, debug info).
+
+use crate::lex::token_stream::Match;
+use crate::UNKNOWN;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId(u32);
+
+// A stretch of a file, from the start of one token to the end of another. The syntax tree keeps
+// one on every node, so that whatever finds a problem later on can say where it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub file_id: Option<FileId>,
+    // byte offsets into the source, the end is exclusive
+    pub start: usize,
+    pub end: usize,
+    // where the span starts. both start at 1.
+    pub line_number: usize,
+    pub line_offset: usize,
+}
+
+impl Span {
+    // from the start of first to the end of last
+    pub fn between(first: &Match, last: &Match) -> Span {
+        return Span {
+            file_id: first.file_id,
+            start: first.start_byte_offset,
+            end: last.end_byte_offset,
+            line_number: first.line_number,
+            line_offset: first.line_offset,
+        };
+    }
+
+    // from the start of this span to the end of other
+    pub fn to(&self, other: &Span) -> Span {
+        return Span {
+            end: other.end,
+            ..*self
+        };
+    }
+}
+
+#[derive(Debug)]
+pub struct SourceFile {
+    pub name: String,
+    pub path: String,
+    pub contents: String,
+}
+
+#[derive(Debug)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        return SourceMap {
+            files: vec![],
+        };
+    }
+
+    // Registers a file and returns its id. Registering a path that is already known replaces its
+    // contents and keeps the original id.
+    pub fn add_file(&mut self, file_name: Option<&str>, file_path: Option<&str>, contents: String) -> FileId {
+        if let Some(file_path) = file_path {
+            if let Some(index) = self.files.iter().position(|file| file.path == file_path) {
+                self.files[index].contents = contents;
+                return FileId(index as u32);
+            }
+        }
+        self.files.push(SourceFile {
+            name: String::from(file_name.unwrap_or(UNKNOWN)),
+            path: String::from(file_path.unwrap_or(UNKNOWN)),
+            contents,
+        });
+        return FileId((self.files.len() - 1) as u32);
+    }
+
+    // for edits made to a file that is already registered, like those from an editor
+    pub fn replace_contents(&mut self, file_id: FileId, contents: String) {
+        self.files[file_id.0 as usize].contents = contents;
+    }
+
+    pub fn get(&self, file_id: FileId) -> &SourceFile {
+        return &self.files[file_id.0 as usize];
+    }
+
+    #[allow(dead_code)]
+    pub fn name(&self, file_id: Option<FileId>) -> &str {
+        if let Some(file_id) = file_id {
+            return &self.get(file_id).name;
+        }
+        return UNKNOWN;
+    }
+
+    #[allow(dead_code)]
+    pub fn path(&self, file_id: Option<FileId>) -> &str {
+        if let Some(file_id) = file_id {
+            return &self.get(file_id).path;
+        }
+        return UNKNOWN;
+    }
+
+    // file_name:line:offset, the form most editors understand
+    #[allow(dead_code)]
+    pub fn location(&self, token: &Match) -> String {
+        return format!("{}:{}:{}", self.name(token.file_id), token.line_number, token.line_offset);
+    }
+
+    #[allow(dead_code)]
+    pub fn size(&self) -> usize {
+        return self.files.len();
+    }
+}
+
+#[cfg(test)]
+mod source_tests {
+    use crate::lex::lex;
+    use crate::source::SourceMap;
+
+    #[test]
+    fn tokens_resolve_through_the_map() {
+        let mut source_map = SourceMap::new();
+        let first = source_map.add_file(Some("main.dog"), Some("src/main.dog"), "app fn main() {}".to_string());
+        let second = source_map.add_file(Some("other.dog"), Some("src/other.dog"), "\n  x".to_string());
+        assert_ne!(first, second);
+        assert_eq!(first, source_map.add_file(Some("main.dog"), Some("src/main.dog"), "app".to_string()));
+        assert_eq!(2, source_map.size());
+        assert_eq!("app", source_map.get(first).contents);
+
+        let token_stream = lex(&source_map, second).unwrap();
+        let x = token_stream.peek(0).unwrap();
+        assert_eq!(Some(second), x.file_id);
+        assert_eq!("other.dog", source_map.name(x.file_id));
+        assert_eq!("src/other.dog", source_map.path(x.file_id));
+        assert_eq!("other.dog:2:3", source_map.location(x));
+    }
+}
@@ -0,0 +1,247 @@
+// build a project from its build plan, config.dog unless another file is named
+//
+// The folder the build plan is in is the project. Every .dog file under it is part of the
+// project, except for the build plan and what the compiler wrote to the output folder. Each config
+// is built into its own folder under the output folder, and when no config is named, all of them
+// are built. See target/output.rs for where everything goes.
+
+use std::env::consts::EXE_SUFFIX;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::config::{Config, resolve};
+use crate::llvm::{build_executable, CodegenOptions, default_target_triple};
+use crate::source::{SourceMap, Span};
+use crate::target::{BuildData, collect_entry_points, entry_point_keyword, output_name, read_document, Target};
+use crate::target::clean::clean_builds;
+use crate::target::output::{OUTPUT_FOLDER, OutputFolder, Profile};
+use crate::transform::ast::{Document, EntryPoint, Module, Name};
+
+pub const DEFAULT_BUILD_PLAN: &str = "config.dog";
+
+pub struct Build {
+    pub build_data: BuildData,
+}
+
+impl Target for Build {
+    fn execute(&self) {
+        build(&self.build_data, false);
+    }
+}
+
+// builds the project's files for every config that was asked for, and exits when that fails.
+// from scratch, what the last build of those configs left is cleaned first.
+pub(crate) fn build(build_data: &BuildData, from_scratch: bool) {
+    let mut source_map = SourceMap::new();
+    let configs = match read_configs(&mut source_map, &build_data.build_plan, build_data.configuration.as_deref()) {
+        Some(configs) => configs,
+        None => process::exit(1),
+    };
+
+    let modules = read_modules(&mut source_map, build_data);
+    let mut entry_points = vec![];
+    for module in &modules {
+        collect_entry_points(module, &[], &mut entry_points);
+    }
+    let module_refs: Vec<&Module> = modules.iter().collect();
+    if entry_points.is_empty() {
+        println!("Nothing to build: there are no app, lib, service or ui functions in {}.", build_data.project_folder.display());
+        process::exit(1);
+    }
+
+    let options = match build_data.profile {
+        Profile::Debug => CodegenOptions::debug(),
+        Profile::Release => CodegenOptions::release(),
+    };
+    if from_scratch {
+        clean_builds(build_data, &configs);
+    }
+    let output_folder = OutputFolder::new(&build_data.project_folder);
+    for config in &configs {
+        let target_triple = config.target_triple.clone().unwrap_or_else(default_target_triple);
+        let intermediates = output_folder.intermediates(build_data.profile, &config.name, &target_triple);
+        let artifacts = output_folder.artifacts(build_data.profile, &config.name, &target_triple);
+        for folder in [&intermediates, &artifacts] {
+            if let Err(error) = fs::create_dir_all(folder) {
+                println!("Unable to create {}: {}", folder.display(), error);
+                process::exit(1);
+            }
+        }
+        for (name, entry_point) in &entry_points {
+            if entry_point.entry_point != Some(EntryPoint::App) {
+                println!("Skipped {} fn {}: only app fns can be built so far.", entry_point_keyword(entry_point), name);
+                continue;
+            }
+            let object = intermediates.join(format!("{}.o", output_name(name)));
+            let executable = artifacts.join(format!("{}{}", output_name(name), EXE_SUFFIX));
+            if let Err(error) = output_folder.record(&[&object, &executable]) {
+                println!("Unable to record the outputs in {}: {}", build_data.project_folder.join(OUTPUT_FOLDER).display(), error);
+                process::exit(1);
+            }
+            if let Err(codegen_error) = build_executable(&source_map, config, options, &module_refs, entry_point, &object, &executable) {
+                println!("{}", codegen_error);
+                println!("Failed to build {} for config {}.", name, config.name);
+                process::exit(1);
+            }
+            println!("Built {}", executable.display());
+        }
+    }
+}
+
+// the project's files, exits when any of them has errors
+pub(crate) fn read_modules(source_map: &mut SourceMap, build_data: &BuildData) -> Vec<Module> {
+    let mut modules = vec![];
+    let mut failed = 0;
+    for file in &build_data.files {
+        match read_document(source_map, file, false) {
+            Some((_, Document::Module(mut module))) => {
+                // a file that doesn't name its module is in the module of its folder
+                if module.name.is_empty() {
+                    module.name = folder_module(&build_data.project_folder, file, module.span);
+                }
+                modules.push(module);
+            }
+            // another build plan
+            Some((_, Document::Config(_))) => {}
+            None => failed += 1,
+        }
+    }
+    if failed > 0 {
+        println!("Failed to build: {} of {} file(s) have errors.", failed, build_data.files.len());
+        process::exit(1);
+    }
+    return modules;
+}
+
+// the folders between the project and a file, tools/text/words.dog is in tools::text
+fn folder_module(project_folder: &Path, file: &Path, span: Span) -> Vec<Name> {
+    let folder = match file.parent().and_then(|folder| folder.strip_prefix(project_folder).ok()) {
+        Some(folder) => folder,
+        None => return vec![],
+    };
+    return folder.components()
+        .map(|component| Name { text: component.as_os_str().to_string_lossy().into_owned(), span })
+        .collect();
+}
+
+// the configs that were asked for from the build plan, all of them when none was
+pub(crate) fn read_configs(source_map: &mut SourceMap, build_plan: &Path, configuration: Option<&str>) -> Option<Vec<Config>> {
+    let (file_id, decls) = match read_document(source_map, build_plan, true)? {
+        (file_id, Document::Config(decls)) => (file_id, decls),
+        // the config grammar only matches configs
+        (_, Document::Module(_)) => return None,
+    };
+    let file_name = source_map.get(file_id).name.clone();
+    let names: Vec<String> = match configuration {
+        Some(configuration) => vec![configuration.to_string()],
+        None => decls.iter().map(|decl| decl.name.text.clone()).collect(),
+    };
+    let mut result = vec![];
+    for name in &names {
+        match resolve(&decls, name, &file_name) {
+            Ok(config) => result.push(config),
+            Err(config_error) => {
+                println!("{}", config_error);
+                println!("Failed to read the build plan.");
+                return None;
+            }
+        }
+    }
+    return Some(result);
+}
+
+// every .dog file in the project, in a stable order. the build plan, the output folder and
+// hidden folders like .git aren't part of it.
+pub(crate) fn find_source_files(project_folder: &Path, build_plan: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut result = vec![];
+    let mut folders = vec![project_folder.to_path_buf()];
+    while let Some(folder) = folders.pop() {
+        for entry in fs::read_dir(&folder)? {
+            let path = entry?.path();
+            let hidden = path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with('.'));
+            if hidden {
+                continue;
+            }
+            if path.is_dir() {
+                if path != project_folder.join(OUTPUT_FOLDER) {
+                    folders.push(path);
+                }
+            } else if path.extension().is_some_and(|extension| extension == "dog") && !same_file(&path, build_plan) {
+                result.push(path);
+            }
+        }
+    }
+    result.sort();
+    return Ok(result);
+}
+
+fn same_file(first: &Path, second: &Path) -> bool {
+    return match (first.canonicalize(), second.canonicalize()) {
+        (Ok(first), Ok(second)) => first == second,
+        _ => first == second,
+    };
+}
+
+#[cfg(test)]
+mod build_tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::config::Config;
+    use crate::llvm::codegen::{Codegen, collect_functions};
+    use crate::source::SourceMap;
+    use crate::target::BuildData;
+    use crate::target::build::{find_source_files, read_modules};
+    use crate::target::output::Profile;
+    use crate::transform::ast::Module;
+
+    #[test]
+    fn find_project_files() {
+        let project_folder = std::env::temp_dir().join(format!("dog_build_tests_{}", std::process::id()));
+        for folder in ["tools/text", "out/debug", ".git"] {
+            fs::create_dir_all(project_folder.join(folder)).unwrap();
+        }
+        for file in ["config.dog", "main.dog", "tools/text/words.dog", "tools/notes.txt", "out/debug/old.dog", ".git/hooks.dog"] {
+            fs::write(project_folder.join(file), "").unwrap();
+        }
+
+        let files = find_source_files(&project_folder, &project_folder.join("config.dog")).unwrap();
+        let expected: Vec<PathBuf> = vec![project_folder.join("main.dog"), project_folder.join("tools/text/words.dog")];
+        fs::remove_dir_all(&project_folder).unwrap();
+        assert_eq!(expected, files);
+    }
+
+    #[test]
+    fn modules_by_folder() {
+        let project_folder = std::env::temp_dir().join(format!("dog_build_folders_{}", std::process::id()));
+        fs::create_dir_all(project_folder.join("tools/text")).unwrap();
+        fs::write(project_folder.join("a.dog"), "fn helper() {\n  return 1\n}\napp fn main() {\n  return helper() + tools::helper()\n}\n").unwrap();
+        fs::write(project_folder.join("tools/b.dog"), "fn helper() {\n  return 2\n}\n").unwrap();
+        fs::write(project_folder.join("tools/text/c.dog"), "mod words\nfn helper() {\n  return 3\n}\n").unwrap();
+        let build_data = BuildData {
+            files: find_source_files(&project_folder, &project_folder.join("config.dog")).unwrap(),
+            build_plan: project_folder.join("config.dog"),
+            project_folder: project_folder.clone(),
+            configuration: None,
+            output: None,
+            profile: Profile::Debug,
+            test_filters: vec![],
+            test_json: None,
+        };
+        let mut source_map = SourceMap::new();
+        let modules = read_modules(&mut source_map, &build_data);
+        fs::remove_dir_all(&project_folder).unwrap();
+
+        // a mod line names the module instead of the folder
+        let names: Vec<Vec<String>> = modules.iter().map(|module| module.name.iter().map(|name| name.text.clone()).collect()).collect();
+        assert_eq!(vec![vec![], vec!["tools".to_string()], vec!["words".to_string()]], names);
+        let module_refs: Vec<&Module> = modules.iter().collect();
+        let mut functions = vec![];
+        collect_functions(&modules[0], &[], false, &mut functions);
+        let config = Config::default();
+        let mut codegen = Codegen::new(&source_map, &config, "main", true);
+        assert!(codegen.lower(&module_refs, functions[1].1).is_ok());
+    }
+}
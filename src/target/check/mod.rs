@@ -0,0 +1,20 @@
+// syntax check a single file without generating any output
+
+use std::process;
+
+use crate::source::SourceMap;
+use crate::target::{BuildData, read_document, Target};
+
+pub struct Check {
+    pub build_data: BuildData,
+}
+
+impl Target for Check {
+    fn execute(&self) {
+        let file = self.build_data.files.first().expect("No file to check.");
+        let mut source_map = SourceMap::new();
+        if read_document(&mut source_map, file, false).is_none() {
+            process::exit(1);
+        }
+    }
+}
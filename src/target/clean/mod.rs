@@ -0,0 +1,44 @@
+// clean the output folder of a project, what the compiler didn't write there stays
+
+use std::process;
+
+use crate::config::Config;
+use crate::target::{BuildData, Target};
+use crate::target::output::{OUTPUT_FOLDER, OutputFolder};
+
+pub struct Clean {
+    pub build_data: BuildData,
+}
+
+impl Target for Clean {
+    fn execute(&self) {
+        clean(&self.build_data);
+    }
+}
+
+// exits when a file can't be removed
+pub(crate) fn clean(build_data: &BuildData) {
+    let output_folder = build_data.project_folder.join(OUTPUT_FOLDER);
+    match OutputFolder::new(&build_data.project_folder).clean() {
+        Ok(0) => println!("Nothing to clean in {}.", output_folder.display()),
+        Ok(removed) => println!("Removed {} file(s) from {}.", removed, output_folder.display()),
+        Err(error) => {
+            println!("Unable to clean {}: {}", output_folder.display(), error);
+            process::exit(1);
+        }
+    }
+}
+
+// the same, but only for what the build data's profile made for these configs
+pub(crate) fn clean_builds(build_data: &BuildData, configs: &[Config]) {
+    let output_folder = build_data.project_folder.join(OUTPUT_FOLDER);
+    let names: Vec<&str> = configs.iter().map(|config| config.name.as_str()).collect();
+    match OutputFolder::new(&build_data.project_folder).clean_builds(build_data.profile, &names) {
+        Ok(0) => {}
+        Ok(removed) => println!("Removed {} file(s) of the last {} build from {}.", removed, build_data.profile.name(), output_folder.display()),
+        Err(error) => {
+            println!("Unable to clean {}: {}", output_folder.display(), error);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+// compile a single file into an executable
+//
+// Each app fn in the file is an executable of its own, named after the function. With -o, the
+// one executable is written there, or when there are several app fns, they're written to that
+// folder. Only app fns are compiled so far: lib, service and ui fns are skipped. The object file
+// is written next to the executable and removed once it's linked, compile doesn't have an output
+// folder to keep it in.
+
+use std::env::consts::EXE_SUFFIX;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::config::Config;
+use crate::llvm::{build_executable, CodegenOptions};
+use crate::source::SourceMap;
+use crate::target::{BuildData, collect_entry_points, entry_point_keyword, output_name, read_document, Target};
+use crate::transform::ast::{Document, EntryPoint};
+
+pub struct Compile {
+    pub build_data: BuildData,
+}
+
+impl Target for Compile {
+    fn execute(&self) {
+        let file = self.build_data.files.first().expect("No file to compile.");
+        let mut source_map = SourceMap::new();
+        let module = match read_document(&mut source_map, file, false) {
+            Some((_, Document::Module(module))) => module,
+            Some((_, Document::Config(_))) => {
+                println!("{} holds configs, which are built with `build`.", file.display());
+                process::exit(1);
+            }
+            None => process::exit(1),
+        };
+
+        let mut entry_points = vec![];
+        collect_entry_points(&module, &[], &mut entry_points);
+        let (apps, others): (Vec<_>, Vec<_>) = entry_points.into_iter().partition(|(_, entry_point)| entry_point.entry_point == Some(EntryPoint::App));
+        for (name, other) in &others {
+            println!("Skipped {} fn {}: only app fns can be compiled so far.", entry_point_keyword(other), name);
+        }
+        if apps.is_empty() {
+            println!("Nothing to compile: there is no app fn in {}.", file.display());
+            process::exit(1);
+        }
+
+        for (name, app) in &apps {
+            let executable = match (&self.build_data.output, apps.len()) {
+                (Some(output), 1) => PathBuf::from(output),
+                (Some(output), _) => Path::new(output).join(format!("{}{}", output_name(name), EXE_SUFFIX)),
+                (None, _) => PathBuf::from(format!("{}{}", output_name(name), EXE_SUFFIX)),
+            };
+            if let Some(folder) = executable.parent().filter(|folder| !folder.as_os_str().is_empty()) {
+                if let Err(error) = fs::create_dir_all(folder) {
+                    println!("Unable to create {}: {}", folder.display(), error);
+                    process::exit(1);
+                }
+            }
+            // app.o is built into app.o.o, so that the object is never the executable itself
+            let mut object = executable.clone().into_os_string();
+            object.push(".o");
+            let object = PathBuf::from(object);
+            let result = build_executable(&source_map, &Config::default(), CodegenOptions::debug(), &[&module], app, &object, &executable);
+            let _ = fs::remove_file(&object);
+            if let Err(codegen_error) = result {
+                println!("{}", codegen_error);
+                println!("Failed to compile {}.", name);
+                process::exit(1);
+            }
+            println!("Compiled {}", executable.display());
+        }
+    }
+}
@@ -0,0 +1,214 @@
+// A target is the thing the user asked the compiler to do: check a file, compile a file,
+// build a project, and so on. Each command maps to exactly one target.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::goal::{Command, UserGoal};
+use crate::lex::lex_with_recovery;
+use crate::parse::{parse_config_with_recovery, parse_with_recovery};
+use crate::parse::trace::{enable_trace, TraceFormat, TraceOptions};
+use crate::source::{FileId, SourceMap};
+use crate::target::build::{Build, DEFAULT_BUILD_PLAN, find_source_files};
+use crate::target::check::Check;
+use crate::target::clean::Clean;
+use crate::target::compile::Compile;
+use crate::target::output::Profile;
+use crate::target::rebuild::Rebuild;
+use crate::target::release::Release;
+use crate::target::test::Test;
+use crate::transform::ast::{Document, EntryPoint, FnDecl, Item, Module};
+use crate::transform::transform;
+
+mod build;
+mod check;
+mod clean;
+mod compile;
+mod output;
+mod rebuild;
+mod release;
+mod test;
+
+pub trait Target {
+    fn execute(&self);
+}
+
+pub struct BuildData {
+    // the files the target works on: the one that was named for check and compile, and every
+    // source file of the project for the targets that build it
+    pub files: Vec<PathBuf>,
+    // config.dog unless another build plan was named, the project is the folder it's in
+    pub build_plan: PathBuf,
+    pub project_folder: PathBuf,
+    // the config to build, all of them when there's none
+    pub configuration: Option<String>,
+    // where compile writes the executable
+    pub output: Option<String>,
+    pub profile: Profile,
+    // the tests to run by part of their name, all of them when there are none
+    pub test_filters: Vec<String>,
+    // where test writes its json report
+    pub test_json: Option<String>,
+}
+
+pub fn create_target(user_goal: Box<UserGoal>) -> Box<dyn Target> {
+    if user_goal.arg_trace_parse {
+        enable_trace(TraceOptions {
+            format: if user_goal.arg_trace_format.as_deref() == Some("json") { TraceFormat::Json } else { TraceFormat::Text },
+            rules: user_goal.arg_trace_rules.clone(),
+        });
+    }
+    let build_plan = PathBuf::from(user_goal.arg_build_plan.as_deref().unwrap_or(DEFAULT_BUILD_PLAN));
+    let project_folder = match build_plan.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let files = match (&user_goal.arg_file, &user_goal.arg_command) {
+        (Some(file), _) => vec![PathBuf::from(file)],
+        (None, Command::Build) | (None, Command::Rebuild) | (None, Command::Release) | (None, Command::Test) => {
+            match find_source_files(&project_folder, &build_plan) {
+                Ok(files) => files,
+                Err(error) => {
+                    println!("Unable to read the project folder {}: {}", project_folder.display(), error);
+                    process::exit(1);
+                }
+            }
+        }
+        _ => vec![],
+    };
+    let build_data = BuildData {
+        files,
+        build_plan,
+        project_folder,
+        configuration: user_goal.arg_configuration.clone(),
+        output: user_goal.arg_output.clone(),
+        profile: if matches!(user_goal.arg_command, Command::Release) { Profile::Release } else { Profile::Debug },
+        test_filters: user_goal.arg_test_filters.clone(),
+        test_json: user_goal.arg_test_json.clone(),
+    };
+    return match user_goal.arg_command {
+        Command::Check => Box::new(Check { build_data }),
+        Command::Build => Box::new(Build { build_data }),
+        Command::Compile => Box::new(Compile { build_data }),
+        Command::Clean => Box::new(Clean { build_data }),
+        Command::Rebuild => Box::new(Rebuild { build_data }),
+        Command::Release => Box::new(Release { build_data }),
+        Command::Test => Box::new(Test { build_data }),
+        _ => panic!("command not implemented")
+    };
+}
+
+// Reads a file into the source map, then lexes, parses and transforms it. When that fails, what's
+// wrong with the file is printed and there's no document. A build plan may only hold configs.
+pub(crate) fn read_document(source_map: &mut SourceMap, path: &Path, build_plan: bool) -> Option<(FileId, Document)> {
+    let file_name = path.file_name().and_then(|name| name.to_str());
+    let code = match fs::read_to_string(path) {
+        Ok(code) => code,
+        Err(error) => {
+            println!("Unable to read {}: {}", path.display(), error);
+            return None;
+        }
+    };
+    let file_id = source_map.add_file(file_name, path.to_str(), code);
+
+    let (token_stream, lex_errors) = lex_with_recovery(source_map, file_id);
+    if !lex_errors.is_empty() {
+        for lex_error in &lex_errors {
+            println!("{}", lex_error.render(source_map));
+        }
+        println!("Failed to lex: found {} error(s).", lex_errors.len());
+        return None;
+    }
+    let (model, syntax_errors) = if build_plan {
+        parse_config_with_recovery(token_stream, file_id)
+    } else {
+        parse_with_recovery(token_stream, file_id)
+    };
+    if !syntax_errors.is_empty() {
+        for syntax_error in &syntax_errors {
+            println!("{}", syntax_error.render(source_map));
+        }
+        println!("Failed to parse: found {} error(s).", syntax_errors.len());
+        return None;
+    }
+    // there's always a model when there are no syntax errors
+    return match transform(&model.unwrap(), source_map, file_id) {
+        Ok(document) => Some((file_id, document)),
+        Err(transform_error) => {
+            println!("{}", transform_error);
+            println!("Failed to transform.");
+            None
+        }
+    };
+}
+
+// tests and test mods are run by `dog test`, not built. each is named by its module path, so
+// that main in mod one and main in mod two are one::main and two::main
+pub(crate) fn collect_entry_points<'a>(module: &'a Module, path: &[String], result: &mut Vec<(String, &'a FnDecl)>) {
+    let mut path = path.to_vec();
+    path.extend(module.name.iter().map(|name| name.text.clone()));
+    for item in &module.items {
+        match item {
+            Item::Function(function) if function.entry_point.as_ref().is_some_and(|entry_point| *entry_point != EntryPoint::Test) => {
+                let mut name = path.clone();
+                name.push(function.name.text.clone());
+                result.push((name.join("::"), function));
+            }
+            Item::Module(module) if !module.test => collect_entry_points(module, &path, result),
+            _ => {}
+        }
+    }
+}
+
+// the name of what an entry point is built into, one::main is built into one.main
+pub(crate) fn output_name(entry_point_name: &str) -> String {
+    return entry_point_name.replace("::", ".");
+}
+
+pub(crate) fn entry_point_keyword(function: &FnDecl) -> &'static str {
+    return match function.entry_point {
+        Some(EntryPoint::App) => "app",
+        Some(EntryPoint::Test) => "test",
+        Some(EntryPoint::Lib) => "lib",
+        Some(EntryPoint::Service) => "service",
+        Some(EntryPoint::Ui) => "ui",
+        None => "",
+    };
+}
+
+#[cfg(test)]
+mod target_tests {
+    use crate::source::SourceMap;
+    use crate::target::{collect_entry_points, output_name};
+    use crate::transform::transform_module;
+
+    #[test]
+    fn entry_points_outside_of_test_mods() {
+        let mut source_map = SourceMap::new();
+        let module = transform_module(&mut source_map, "\
+app fn main() {
+}
+test fn adds() {
+}
+mod tools {
+  app fn words() {
+  }
+}
+test mod tools_tests {
+  app fn fixture() {
+  }
+  mod more {
+    app fn nested_fixture() {
+    }
+  }
+}
+");
+
+        let mut entry_points = vec![];
+        collect_entry_points(&module, &[], &mut entry_points);
+        let names: Vec<&str> = entry_points.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(vec!["main", "tools::words"], names);
+        assert_eq!("tools.words", output_name(names[1]));
+    }
+}
@@ -0,0 +1,207 @@
+// Where a build writes what it makes, and how clean finds it again.
+//
+//     out/
+//         .dog_outputs                           every file the compiler wrote, one per line
+//         debug/<config>/<target triple>/
+//             intermediates/<name>.o
+//             artifacts/<name>
+//         release/<config>/<target triple>/
+//             ...
+//
+// The artifacts are what a build is for, the intermediates are only kept so there's something
+// to look at when the linker complains. out/ is the project's folder as much as the compiler's,
+// so clean only removes the files that are listed in .dog_outputs, and then the folders that
+// are left empty. Rebuild and release only clean the builds they're about to make again, the
+// other profile and configs keep what they had.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const OUTPUT_FOLDER: &str = "out";
+const OUTPUTS_FILE: &str = ".dog_outputs";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    Debug,
+    Release,
+}
+
+impl Profile {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Profile::Debug => "debug",
+            Profile::Release => "release",
+        };
+    }
+}
+
+pub struct OutputFolder {
+    folder: PathBuf,
+}
+
+impl OutputFolder {
+    pub fn new(project_folder: &Path) -> OutputFolder {
+        return OutputFolder { folder: project_folder.join(OUTPUT_FOLDER) };
+    }
+
+    fn build_folder(&self, profile: Profile, config: &str, target_triple: &str) -> PathBuf {
+        return self.folder.join(profile.name()).join(config).join(target_triple);
+    }
+
+    pub fn intermediates(&self, profile: Profile, config: &str, target_triple: &str) -> PathBuf {
+        return self.build_folder(profile, config, target_triple).join("intermediates");
+    }
+
+    pub fn artifacts(&self, profile: Profile, config: &str, target_triple: &str) -> PathBuf {
+        return self.build_folder(profile, config, target_triple).join("artifacts");
+    }
+
+    // the files that were recorded, relative to out/
+    fn read_outputs(&self) -> io::Result<BTreeSet<PathBuf>> {
+        return match fs::read_to_string(self.folder.join(OUTPUTS_FILE)) {
+            Ok(outputs) => Ok(outputs.lines().filter(|line| !line.is_empty()).map(PathBuf::from).collect()),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(BTreeSet::new()),
+            Err(error) => Err(error),
+        };
+    }
+
+    // the manifest is removed once there's nothing in it
+    fn write_outputs(&self, outputs: &BTreeSet<PathBuf>) -> io::Result<()> {
+        let file = self.folder.join(OUTPUTS_FILE);
+        if outputs.is_empty() {
+            return match fs::remove_file(file) {
+                Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+                _ => Ok(()),
+            };
+        }
+        fs::create_dir_all(&self.folder)?;
+        let mut contents = String::new();
+        for output in outputs {
+            contents.push_str(&output.to_string_lossy());
+            contents.push('\n');
+        }
+        return fs::write(file, contents);
+    }
+
+    // Records files as the compiler's before they're written, so that clean finds them even when
+    // the build fails half way. Files outside of out/ aren't the output folder's to clean.
+    pub fn record(&self, files: &[&Path]) -> io::Result<()> {
+        let mut outputs = self.read_outputs()?;
+        for file in files {
+            if let Ok(relative) = file.strip_prefix(&self.folder) {
+                outputs.insert(relative.to_path_buf());
+            }
+        }
+        return self.write_outputs(&outputs);
+    }
+
+    // Removes the recorded files and the folders they leave empty, out/ too when nothing else is
+    // in it. Returns how many files there were.
+    pub fn clean(&self) -> io::Result<usize> {
+        return self.clean_where(|_| true);
+    }
+
+    // The same as clean, but only for the files of the profile's builds of these configs, for
+    // every target triple. The rest stay recorded.
+    pub fn clean_builds(&self, profile: Profile, configs: &[&str]) -> io::Result<usize> {
+        let builds: Vec<PathBuf> = configs.iter().map(|config| Path::new(profile.name()).join(config)).collect();
+        return self.clean_where(|output| builds.iter().any(|build| output.starts_with(build)));
+    }
+
+    fn clean_where(&self, cleaned: impl Fn(&Path) -> bool) -> io::Result<usize> {
+        let (outputs, kept): (BTreeSet<PathBuf>, BTreeSet<PathBuf>) = self.read_outputs()?.into_iter().partition(|output| cleaned(output));
+        let mut removed = 0;
+        let mut folders = BTreeSet::new();
+        for output in &outputs {
+            // a recorded path can't point out of out/
+            if output.is_absolute() || output.components().any(|component| component.as_os_str() == "..") {
+                continue;
+            }
+            let file = self.folder.join(output);
+            match fs::remove_file(&file) {
+                Ok(()) => removed += 1,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+                Err(error) => return Err(error),
+            }
+            let mut folder = file.parent();
+            while let Some(parent) = folder.filter(|parent| parent.starts_with(&self.folder) && *parent != self.folder) {
+                folders.insert(parent.to_path_buf());
+                folder = parent.parent();
+            }
+        }
+        self.write_outputs(&kept)?;
+        // the deepest folders first, a folder that isn't empty is someone else's and stays
+        for folder in folders.iter().rev().chain(std::iter::once(&self.folder)) {
+            let _ = fs::remove_dir(folder);
+        }
+        return Ok(removed);
+    }
+}
+
+#[cfg(test)]
+mod output_tests {
+    use std::fs;
+
+    use crate::target::output::{OutputFolder, Profile};
+
+    #[test]
+    fn clean_only_what_was_recorded() {
+        let project_folder = std::env::temp_dir().join(format!("dog_output_tests_{}", std::process::id()));
+        let output_folder = OutputFolder::new(&project_folder);
+        let intermediates = output_folder.intermediates(Profile::Debug, "default", "x86_64-pc-linux-gnu");
+        let artifacts = output_folder.artifacts(Profile::Debug, "default", "x86_64-pc-linux-gnu");
+        assert_eq!(project_folder.join("out/debug/default/x86_64-pc-linux-gnu/artifacts"), artifacts);
+
+        fs::create_dir_all(&intermediates).unwrap();
+        fs::create_dir_all(&artifacts).unwrap();
+        let object = intermediates.join("main.o");
+        let executable = artifacts.join("main");
+        let notes = project_folder.join("out/notes.txt");
+        output_folder.record(&[&object, &executable]).unwrap();
+        // recorded twice, and recorded but never written
+        output_folder.record(&[&executable, &artifacts.join("other")]).unwrap();
+        for file in [&object, &executable, &notes] {
+            fs::write(file, "").unwrap();
+        }
+
+        assert_eq!(2, output_folder.clean().unwrap());
+        let notes_left = notes.exists();
+        let out_left: Vec<_> = fs::read_dir(project_folder.join("out")).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        // nothing left to clean
+        let cleaned_again = output_folder.clean().unwrap();
+        fs::remove_dir_all(&project_folder).unwrap();
+        assert!(notes_left);
+        assert_eq!(vec!["notes.txt"], out_left);
+        assert_eq!(0, cleaned_again);
+    }
+
+    #[test]
+    fn clean_only_the_builds_asked_for() {
+        let project_folder = std::env::temp_dir().join(format!("dog_output_builds_tests_{}", std::process::id()));
+        let output_folder = OutputFolder::new(&project_folder);
+        let mut executables = vec![];
+        for (profile, config) in [(Profile::Release, "dev"), (Profile::Release, "prod"), (Profile::Debug, "dev"), (Profile::Release, "dev2")] {
+            let artifacts = output_folder.artifacts(profile, config, "x86_64-pc-linux-gnu");
+            fs::create_dir_all(&artifacts).unwrap();
+            let executable = artifacts.join("main");
+            fs::write(&executable, "").unwrap();
+            output_folder.record(&[&executable]).unwrap();
+            executables.push(executable);
+        }
+
+        let removed = output_folder.clean_builds(Profile::Release, &["dev"]).unwrap();
+        let left: Vec<bool> = executables.iter().map(|executable| executable.exists()).collect();
+        let release_left: Vec<_> = fs::read_dir(project_folder.join("out/release")).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        // the others are still recorded
+        let cleaned_rest = output_folder.clean().unwrap();
+        let out_left = project_folder.join("out").exists();
+        fs::remove_dir_all(&project_folder).unwrap();
+        assert_eq!(1, removed);
+        assert_eq!(vec![false, true, true, true], left);
+        assert_eq!(2, release_left.len());
+        assert_eq!(3, cleaned_rest);
+        assert!(!out_left);
+    }
+}
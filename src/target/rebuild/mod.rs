@@ -0,0 +1,15 @@
+// build a project again from scratch: what the last debug build of its configs left is cleaned
+// first, the rest of the output folder stays
+
+use crate::target::{BuildData, Target};
+use crate::target::build::build;
+
+pub struct Rebuild {
+    pub build_data: BuildData,
+}
+
+impl Target for Rebuild {
+    fn execute(&self) {
+        build(&self.build_data, true);
+    }
+}
@@ -0,0 +1,18 @@
+// build a project for release: optimized, and stripped of its symbols and debug info
+//
+// A release is always built from scratch, so what the last release of its configs left in the
+// output folder is cleaned first. The executables go to out/release/ instead of out/debug/, see
+// target/output.rs.
+
+use crate::target::{BuildData, Target};
+use crate::target::build::build;
+
+pub struct Release {
+    pub build_data: BuildData,
+}
+
+impl Target for Release {
+    fn execute(&self) {
+        build(&self.build_data, true);
+    }
+}
@@ -0,0 +1,251 @@
+// run the tests of a project
+//
+// A test is a `test fn`, in a `test mod` or anywhere else, and it's named by the modules it's in,
+// like math::adds. Test mods hold what only the tests need, they aren't part of what `build`
+// builds. All of the tests are built into one harness executable, see
+// llvm::build_test_harness, and each test runs in a process of its own, so a test that fails
+// with `fail(...)` or crashes doesn't take the others with it.
+//
+// The tests are built with the config that was named, or the first one in the build plan.
+
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::process::Command;
+use std::time::Instant;
+
+use crate::llvm::{build_test_harness, CodegenOptions, default_target_triple};
+use crate::source::SourceMap;
+use crate::target::{BuildData, Target};
+use crate::target::build::{read_configs, read_modules};
+use crate::target::output::{OUTPUT_FOLDER, OutputFolder};
+use crate::transform::ast::{EntryPoint, FnDecl, Item, Module};
+
+const HARNESS: &str = "test_harness";
+
+pub struct Test {
+    pub build_data: BuildData,
+}
+
+struct TestResult {
+    name: String,
+    passed: bool,
+    // none when the test was stopped by a signal
+    exit_code: Option<i32>,
+    duration_ms: u128,
+    stdout: String,
+    stderr: String,
+}
+
+impl Target for Test {
+    fn execute(&self) {
+        let mut source_map = SourceMap::new();
+        let config = match read_configs(&mut source_map, &self.build_data.build_plan, self.build_data.configuration.as_deref()) {
+            Some(mut configs) if !configs.is_empty() => configs.remove(0),
+            Some(_) => {
+                println!("Nothing to test with: {} has no configs.", self.build_data.build_plan.display());
+                process::exit(1);
+            }
+            None => process::exit(1),
+        };
+        let modules = read_modules(&mut source_map, &self.build_data);
+        let mut tests = vec![];
+        for module in &modules {
+            collect_tests(module, &[], &mut tests);
+        }
+        let total = tests.len();
+        tests.retain(|(name, _)| matches_filters(name, &self.build_data.test_filters));
+        if tests.is_empty() {
+            println!("No tests to run, {} filtered out.", total);
+            return;
+        }
+
+        // the harness has to run here
+        let target_triple = default_target_triple();
+        if config.target_triple.as_ref().is_some_and(|config_triple| *config_triple != target_triple) {
+            println!("Unable to run tests built for {} on {}.", config.target_triple.as_deref().unwrap_or_default(), target_triple);
+            process::exit(1);
+        }
+        let output_folder = OutputFolder::new(&self.build_data.project_folder);
+        let intermediates = output_folder.intermediates(self.build_data.profile, &config.name, &target_triple);
+        let artifacts = output_folder.artifacts(self.build_data.profile, &config.name, &target_triple);
+        for folder in [&intermediates, &artifacts] {
+            if let Err(error) = fs::create_dir_all(folder) {
+                println!("Unable to create {}: {}", folder.display(), error);
+                process::exit(1);
+            }
+        }
+        let object = intermediates.join(format!("{}.o", HARNESS));
+        let harness = artifacts.join(format!("{}{}", HARNESS, std::env::consts::EXE_SUFFIX));
+        if let Err(error) = output_folder.record(&[&object, &harness]) {
+            println!("Unable to record the outputs in {}: {}", self.build_data.project_folder.join(OUTPUT_FOLDER).display(), error);
+            process::exit(1);
+        }
+        let module_refs: Vec<&Module> = modules.iter().collect();
+        if let Err(codegen_error) = build_test_harness(&source_map, &config, CodegenOptions::debug(), &module_refs, &tests, &object, &harness) {
+            println!("{}", codegen_error);
+            println!("Failed to build the tests for config {}.", config.name);
+            process::exit(1);
+        }
+
+        println!("Running {} test(s) for config {}", tests.len(), config.name);
+        let mut results = vec![];
+        for (name, _) in &tests {
+            let result = run_test(&harness, name);
+            println!("test {} ... {}", result.name, if result.passed { "ok" } else { "FAILED" });
+            results.push(result);
+        }
+
+        let failures: Vec<&TestResult> = results.iter().filter(|result| !result.passed).collect();
+        for failure in &failures {
+            println!();
+            println!("---- {} ----", failure.name);
+            print!("{}{}", failure.stdout, failure.stderr);
+            if failure.exit_code.is_none() {
+                println!("stopped without an exit code");
+            }
+        }
+        println!();
+        println!("Test result: {} passed, {} failed, {} filtered out.", results.len() - failures.len(), failures.len(), total - tests.len());
+
+        if let Some(json) = &self.build_data.test_json {
+            if let Err(error) = fs::write(json, json_report(&config.name, &results, total - tests.len())) {
+                println!("Unable to write {}: {}", json, error);
+                process::exit(1);
+            }
+        }
+        if !failures.is_empty() {
+            process::exit(1);
+        }
+    }
+}
+
+// the test fns of the module and the modules in it, test mods or not
+fn collect_tests<'a>(module: &'a Module, path: &[String], result: &mut Vec<(String, &'a FnDecl)>) {
+    let mut path = path.to_vec();
+    path.extend(module.name.iter().map(|name| name.text.clone()));
+    for item in &module.items {
+        match item {
+            Item::Function(function) if function.entry_point == Some(EntryPoint::Test) => {
+                let mut name = path.clone();
+                name.push(function.name.text.clone());
+                result.push((name.join("::"), function));
+            }
+            Item::Module(module) => collect_tests(module, &path, result),
+            _ => {}
+        }
+    }
+}
+
+fn matches_filters(name: &str, filters: &[String]) -> bool {
+    return filters.is_empty() || filters.iter().any(|filter| name.contains(filter.as_str()));
+}
+
+fn run_test(harness: &Path, name: &str) -> TestResult {
+    let start = Instant::now();
+    let output = Command::new(harness).arg(name).output();
+    let duration_ms = start.elapsed().as_millis();
+    return match output {
+        Ok(output) => TestResult {
+            name: name.to_string(),
+            passed: output.status.success(),
+            exit_code: output.status.code(),
+            duration_ms,
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        },
+        Err(error) => TestResult {
+            name: name.to_string(),
+            passed: false,
+            exit_code: None,
+            duration_ms,
+            stdout: String::new(),
+            stderr: format!("Unable to run {}: {}\n", harness.display(), error),
+        },
+    };
+}
+
+fn json_string(text: &str) -> String {
+    let mut result = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    return result;
+}
+
+// {"config":"debug","passed":1,"failed":1,"filtered_out":0,"tests":[{"name":"math::adds","passed":true,...}]}
+fn json_report(config: &str, results: &[TestResult], filtered_out: usize) -> String {
+    let passed = results.iter().filter(|result| result.passed).count();
+    let tests: Vec<String> = results.iter().map(|result| {
+        format!("{{\"name\":{},\"passed\":{},\"exit_code\":{},\"duration_ms\":{},\"stdout\":{},\"stderr\":{}}}",
+                json_string(&result.name), result.passed,
+                result.exit_code.map(|code| code.to_string()).unwrap_or_else(|| "null".to_string()),
+                result.duration_ms, json_string(&result.stdout), json_string(&result.stderr))
+    }).collect();
+    return format!("{{\"config\":{},\"passed\":{},\"failed\":{},\"filtered_out\":{},\"tests\":[{}]}}\n",
+                   json_string(config), passed, results.len() - passed, filtered_out, tests.join(","));
+}
+
+#[cfg(test)]
+mod test_tests {
+    use crate::source::SourceMap;
+    use crate::target::test::{collect_tests, json_report, matches_filters, TestResult};
+    use crate::transform::transform_module;
+
+    #[test]
+    fn find_tests() {
+        let mut source_map = SourceMap::new();
+        let module = transform_module(&mut source_map, "\
+fn helper(n: int) {
+}
+test fn adds() {
+}
+test mod math {
+  test fn multiplies() {
+  }
+  fn check(n: int) {
+  }
+  mod more {
+    test fn divides() {
+    }
+  }
+}
+mod other {
+  fn not_a_test() {
+  }
+  test fn subtracts() {
+  }
+}
+");
+
+        let mut tests = vec![];
+        collect_tests(&module, &[], &mut tests);
+        let names: Vec<&str> = tests.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(vec!["adds", "math::multiplies", "math::more::divides", "other::subtracts"], names);
+
+        assert!(matches_filters("math::more::divides", &[]));
+        assert!(matches_filters("math::more::divides", &["add".to_string(), "more".to_string()]));
+        assert!(!matches_filters("adds", &["math".to_string()]));
+    }
+
+    #[test]
+    fn report_as_json() {
+        let results = vec![
+            TestResult { name: "adds".to_string(), passed: true, exit_code: Some(0), duration_ms: 3, stdout: "2\n".to_string(), stderr: String::new() },
+            TestResult { name: "math::divides".to_string(), passed: false, exit_code: Some(1), duration_ms: 1, stdout: String::new(), stderr: "fail: \"zero\"\n".to_string() },
+        ];
+        assert_eq!("{\"config\":\"debug\",\"passed\":1,\"failed\":1,\"filtered_out\":2,\"tests\":[\
+{\"name\":\"adds\",\"passed\":true,\"exit_code\":0,\"duration_ms\":3,\"stdout\":\"2\\n\",\"stderr\":\"\"},\
+{\"name\":\"math::divides\",\"passed\":false,\"exit_code\":1,\"duration_ms\":1,\"stdout\":\"\",\"stderr\":\"fail: \\\"zero\\\"\\n\"}]}\n",
+                   json_report("debug", &results, 2));
+    }
+}
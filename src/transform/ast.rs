@@ -0,0 +1,373 @@
+// The syntax tree of a Dog file, built from the parse model by the transform pass. The parse
+// model has a node for every rule the parser matched, including the punctuation, and finds its
+// children by position. Here there is a type for each thing that the language has, holding only
+// what it means, and a span on each of them that says where in the file it came from.
+
+use crate::lex::number_literal::NumberLiteral;
+use crate::source::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Document {
+    Module(Module),
+    Config(Vec<ConfigDecl>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Name {
+    pub text: String,
+    pub span: Span,
+}
+
+// a file, or a `module` declared inside of one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    pub doc_comments: Vec<String>,
+    pub attributes: Vec<AttrTag>,
+    pub test: bool,
+    // a::b::c, a file doesn't have to name its module
+    pub name: Vec<Name>,
+    // the module is only built when the config matches all of these
+    pub when: Vec<ConfigCondition>,
+    pub uses: Vec<UseDecl>,
+    pub items: Vec<Item>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigCondition {
+    pub name: Name,
+    pub value: Expr,
+    pub span: Span,
+}
+
+// `use a::b::c as d` imports c from a::b, `use a::b::{c, d}` imports c and d
+#[derive(Debug, Clone, PartialEq)]
+pub struct UseDecl {
+    pub path: Vec<Name>,
+    pub imports: Vec<UseImport>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UseImport {
+    pub name: Name,
+    pub alias: Option<Name>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Function(FnDecl),
+    Struct(StructDecl),
+    Enum(EnumDecl),
+    Trait(TraitDecl),
+    Impl(ImplDecl),
+    Attribute(AttrDecl),
+    Module(Module),
+}
+
+// #name{key: value} in front of a declaration
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttrTag {
+    pub name: Vec<Name>,
+    pub metadata: Vec<AttrMetadata>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttrMetadata {
+    pub name: Name,
+    // always a literal
+    pub value: Expr,
+    pub span: Span,
+}
+
+// a generic parameter like T: Display, or a generic argument like String
+#[derive(Debug, Clone, PartialEq)]
+pub struct Generic {
+    pub name: Vec<Name>,
+    pub bound: Option<Type>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntryPoint {
+    App,
+    Test,
+    Lib,
+    Service,
+    Ui,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FnDecl {
+    pub doc_comments: Vec<String>,
+    pub attributes: Vec<AttrTag>,
+    pub entry_point: Option<EntryPoint>,
+    pub is_const: bool,
+    pub name: Name,
+    pub generics: Vec<Generic>,
+    pub params: Vec<Param>,
+    // none for a function that a trait only declares
+    pub body: Option<Block>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: Name,
+    pub data_type: Type,
+    // always a literal
+    pub default: Option<Expr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructDecl {
+    pub doc_comments: Vec<String>,
+    pub attributes: Vec<AttrTag>,
+    pub name: Name,
+    pub generics: Vec<Generic>,
+    pub members: Vec<StructMember>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructMember {
+    pub doc_comments: Vec<String>,
+    pub name: Name,
+    pub data_type: Option<Type>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDecl {
+    pub doc_comments: Vec<String>,
+    pub attributes: Vec<AttrTag>,
+    pub name: Name,
+    pub members: Vec<EnumMember>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumMember {
+    pub doc_comments: Vec<String>,
+    pub name: Name,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraitDecl {
+    pub doc_comments: Vec<String>,
+    pub attributes: Vec<AttrTag>,
+    pub name: Name,
+    pub generics: Vec<Generic>,
+    pub functions: Vec<FnDecl>,
+    pub span: Span,
+}
+
+// impl Name on Trait<T> { ... }
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImplDecl {
+    pub doc_comments: Vec<String>,
+    pub attributes: Vec<AttrTag>,
+    pub name: Name,
+    pub on: Option<Name>,
+    pub on_generics: Vec<Generic>,
+    pub functions: Vec<FnDecl>,
+    pub span: Span,
+}
+
+// attribute name<T: struct, enum> { field: type }
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttrDecl {
+    pub doc_comments: Vec<String>,
+    pub name: Name,
+    pub generic: Option<Name>,
+    // what the attribute can be put on, anything if there's no list
+    pub targets: Vec<AttrTarget>,
+    pub when: Vec<ConfigCondition>,
+    pub fields: Vec<AttrField>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttrTarget {
+    Module,
+    Struct,
+    Impl,
+    Trait,
+    Function,
+    Enum,
+    App,
+    Ui,
+    Service,
+    Lib,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttrField {
+    pub name: Name,
+    pub data_type: Type,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Type {
+    pub kind: TypeKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeKind {
+    Integer,
+    Float,
+    Boolean,
+    Character,
+    Named { path: Vec<Name>, generics: Vec<Generic> },
+    Array(Box<Type>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub statements: Vec<Stmt>,
+    pub otherwise: Option<Box<Otherwise>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub otherwise: Option<Otherwise>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind {
+    Block(Block),
+    Return(Expr),
+    For { variable: Name, data_type: Option<Type>, iterable: Expr, body: Block },
+    // a loop without a condition runs until something breaks out of it
+    While { condition: Option<Expr>, body: Block },
+    If { condition: Expr, body: Block },
+    Let { name: Name, data_type: Option<Type>, value: Option<Expr> },
+    Assign { target: Expr, value: Expr },
+    Expr(Expr),
+    Fail(Fail),
+}
+
+// what to do when the statement before `otherwise` fails
+#[derive(Debug, Clone, PartialEq)]
+pub enum Otherwise {
+    Block(Block),
+    Expr(Expr),
+    Fail(Fail),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fail {
+    pub arguments: Vec<Argument>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Argument {
+    // name: value
+    pub name: Option<Name>,
+    pub value: Expr,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    // a or a::b
+    Identifier(Vec<Name>),
+    // the `config` keyword, the config the program was built with
+    Config,
+    Literal(Literal),
+    Binary { operator: BinaryOperator, left: Box<Expr>, right: Box<Expr> },
+    Unary { operator: UnaryOperator, operand: Box<Expr> },
+    Cast { value: Box<Expr>, data_type: Type },
+    // a.b, or a::b when constant
+    Member { object: Box<Expr>, member: Name, constant: bool },
+    Call { function: Box<Expr>, arguments: Vec<Argument> },
+    StructConstructor { name: Name, fields: Vec<FieldValue> },
+    Range { start: Box<Expr>, end: Box<Expr>, inclusive: bool },
+    InterpolatedString(Vec<StringPart>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    // the text that the literal stands for, with its escapes decoded
+    String(String),
+    Number(NumberLiteral),
+    Boolean(bool),
+    Null,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessOrEqual,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOperator {
+    Not,
+    Negate,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldValue {
+    pub name: Name,
+    pub value: Expr,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    Text(String),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigDecl {
+    pub doc_comments: Vec<String>,
+    pub name: Name,
+    pub extends: Option<Name>,
+    pub entries: Vec<ConfigEntry>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigEntry {
+    pub name: Name,
+    pub value: ConfigValue,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigValue {
+    pub kind: ConfigValueKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigValueKind {
+    Literal(Literal),
+    Map(Vec<ConfigEntry>),
+    List(Vec<ConfigValue>),
+}
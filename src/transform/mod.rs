@@ -1,4 +1,1131 @@
 // We need to transform the parser model into the dog language structure
+//
+// The parse model is what the grammar matched: a node for every rule, with its children in the
+// order the rule lists them, punctuation and all. The transform walks it rule by rule and builds
+// the syntax tree in ast.rs. A rule with repeats has a node for each repeat, labeled with the
+// rule, holding that repeat's children. An `||` rule leaves the node of the alternative that
+// matched in its place, so the label says which one it was.
+//
+// The parser only builds models that the grammar allows, so a model of another shape means that
+// the grammar and this file disagree. That is still reported as an error rather than a panic,
+// along with the `error` nodes that recovery leaves behind, at the tokens where it happened.
 
-// takes output from parser and translates it to an internal, execution representation
-// this representation will be sent to the analyzer
+pub(crate) mod ast;
+pub(crate) mod transform_error;
+
+use crate::lex::number_literal::parse_number_literal;
+use crate::lex::string_literal::decode_string_token;
+use crate::parse::parse_model::ParseModel;
+use crate::source::{FileId, SourceMap, Span};
+use crate::transform::ast::*;
+use crate::transform::transform_error::TransformError;
+
+pub fn transform(model: &ParseModel, source_map: &SourceMap, file_id: FileId) -> Result<Document, TransformError> {
+    let transformer = Transformer {
+        file_id,
+        file_name: source_map.get(file_id).name.clone(),
+    };
+    return transformer.document(model);
+}
+
+// the module of code that has to lex, parse and transform without errors, as main.dog
+#[cfg(test)]
+pub fn transform_module(source_map: &mut SourceMap, code: &str) -> Module {
+    return match transform_test_file(source_map, "main.dog", code, false) {
+        Document::Module(module) => module,
+        Document::Config(_) => panic!("not a module"),
+    };
+}
+
+// the configs of a build plan that has to lex, parse and transform without errors, as config.dog
+#[cfg(test)]
+pub fn transform_config(source_map: &mut SourceMap, code: &str) -> Vec<ConfigDecl> {
+    return match transform_test_file(source_map, "config.dog", code, true) {
+        Document::Config(decls) => decls,
+        Document::Module(_) => panic!("not a config document"),
+    };
+}
+
+#[cfg(test)]
+fn transform_test_file(source_map: &mut SourceMap, file_name: &str, code: &str, build_plan: bool) -> Document {
+    use crate::lex::lex_with_recovery;
+    use crate::parse::{parse_config_with_recovery, parse_with_recovery};
+
+    let file_id = source_map.add_file(Some(file_name), Some(file_name), code.to_string());
+    let (token_stream, lex_errors) = lex_with_recovery(source_map, file_id);
+    assert!(lex_errors.is_empty(), "{:?}", lex_errors);
+    let (model, syntax_errors) = if build_plan {
+        parse_config_with_recovery(token_stream, file_id)
+    } else {
+        parse_with_recovery(token_stream, file_id)
+    };
+    assert!(syntax_errors.is_empty(), "{:?}", syntax_errors);
+    return transform(&model.unwrap(), source_map, file_id).unwrap();
+}
+
+struct Transformer {
+    file_id: FileId,
+    file_name: String,
+}
+
+impl Transformer {
+    // from the first token of the model to its last. a model without tokens, like an empty
+    // repeat, is at the start of the file.
+    fn span(&self, model: &ParseModel) -> Span {
+        let mut tokens = vec![];
+        model.collect_tokens(&mut tokens);
+        if let (Some(first), Some(last)) = (tokens.first(), tokens.last()) {
+            return Span::between(first, last);
+        }
+        return Span {
+            file_id: Some(self.file_id),
+            start: 0,
+            end: 0,
+            line_number: 1,
+            line_offset: 1,
+        };
+    }
+
+    fn error(&self, model: &ParseModel, message: String) -> TransformError {
+        return TransformError {
+            message,
+            file_name: self.file_name.clone(),
+            span: self.span(model),
+        };
+    }
+
+    fn unexpected(&self, model: &ParseModel, expected: &str) -> TransformError {
+        if model.label == "error" {
+            return self.error(model, format!("expected {}, found a syntax error", describe(expected)));
+        }
+        return self.error(model, format!("expected {}, found {}", describe(expected), describe(&model.label)));
+    }
+
+    // the child at index, which the grammar says is a label
+    fn child<'m>(&self, model: &'m ParseModel, index: usize, label: &str) -> Result<&'m ParseModel, TransformError> {
+        return match model.children.get(index) {
+            Some(child) if child.label == label => Ok(child),
+            Some(child) => Err(self.unexpected(child, label)),
+            None => Err(self.error(model, format!("{} is missing {}", describe(&model.label), describe(label)))),
+        };
+    }
+
+    // the child at index, which is one of the alternatives of an `||` rule
+    fn alternative<'m>(&self, model: &'m ParseModel, index: usize) -> Result<&'m ParseModel, TransformError> {
+        return match model.children.get(index) {
+            Some(child) if child.label == "error" => Err(self.unexpected(child, &model.label)),
+            Some(child) => Ok(child),
+            None => Err(self.error(model, format!("{} is missing a part", describe(&model.label)))),
+        };
+    }
+
+    // the repeats of a repeated rule
+    fn items<'m>(&self, model: &'m ParseModel) -> Result<Vec<&'m ParseModel>, TransformError> {
+        let mut result = vec![];
+        for item in &model.children {
+            if item.label != model.label {
+                return Err(self.unexpected(item, &model.label));
+            }
+            result.push(item.as_ref());
+        }
+        return Ok(result);
+    }
+
+    // the one repeat of a rule that matches at most once
+    fn optional<'m>(&self, model: &'m ParseModel) -> Result<Option<&'m ParseModel>, TransformError> {
+        let items = self.items(model)?;
+        if items.len() > 1 {
+            return Err(self.error(items[1], format!("expected one {}, found {}", describe(&model.label), items.len())));
+        }
+        return Ok(items.first().copied());
+    }
+
+    fn name(&self, model: &ParseModel) -> Result<Name, TransformError> {
+        if model.tokens.len() != 1 || !model.children.is_empty() {
+            return Err(self.error(model, format!("expected {} to be one word", describe(&model.label))));
+        }
+        return Ok(Name {
+            text: model.tokens[0].value.clone(),
+            span: self.span(model),
+        });
+    }
+
+    fn name_at(&self, model: &ParseModel, index: usize) -> Result<Name, TransformError> {
+        return self.name(self.child(model, index, "identifier")?);
+    }
+
+    // first, then the identifier of each `::identifier` in tail
+    fn path(&self, first: &ParseModel, tail: &ParseModel) -> Result<Vec<Name>, TransformError> {
+        let mut result = vec![self.name(first)?];
+        for item in self.items(tail)? {
+            result.push(self.name_at(item, 1)?);
+        }
+        return Ok(result);
+    }
+
+    fn external_identifier(&self, model: &ParseModel) -> Result<Vec<Name>, TransformError> {
+        return self.path(self.child(model, 0, "identifier")?, self.child(model, 1, "external_identifier_tail")?);
+    }
+
+    fn doc_comments(&self, model: &ParseModel) -> Result<Vec<String>, TransformError> {
+        self.child(model, 0, "optional_doc_comments")?;
+        return Ok(model.doc_comments());
+    }
+
+    fn document(&self, model: &ParseModel) -> Result<Document, TransformError> {
+        return match model.label.as_str() {
+            "module_document" => Ok(Document::Module(self.module_document(model)?)),
+            "config_document" => {
+                let mut result = vec![];
+                for item in self.items(model)? {
+                    result.push(self.config_decl(self.child(item, 0, "config_decl")?)?);
+                }
+                Ok(Document::Config(result))
+            }
+            _ => Err(self.unexpected(model, "document")),
+        };
+    }
+
+    fn module_document(&self, model: &ParseModel) -> Result<Module, TransformError> {
+        let mut result = self.mod_body(self.child(model, 1, "mod_body")?, self.span(model))?;
+        if let Some(item) = self.optional(self.child(model, 0, "optional_mod_name_decl")?)? {
+            let mod_name_decl = self.child(item, 0, "mod_name_decl")?;
+            result.doc_comments = self.doc_comments(mod_name_decl)?;
+            result.name = self.path(self.child(mod_name_decl, 2, "identifier")?, self.child(mod_name_decl, 3, "mod_decl_next_part")?)?;
+            result.when = self.config_conditions(self.child(mod_name_decl, 4, "use_when_config_matches_props")?)?;
+        }
+        return Ok(result);
+    }
+
+    fn mod_decl(&self, model: &ParseModel) -> Result<Module, TransformError> {
+        let mut result = self.mod_body(self.child(model, 7, "mod_body")?, self.span(model))?;
+        result.doc_comments = self.doc_comments(model)?;
+        result.attributes = self.attributes(self.child(model, 1, "optional_attr_tags")?)?;
+        result.test = self.optional(self.child(model, 2, "optional_test")?)?.is_some();
+        result.name = vec![self.name_at(model, 4)?];
+        result.when = self.config_conditions(self.child(model, 5, "use_when_config_matches_props")?)?;
+        return Ok(result);
+    }
+
+    fn mod_body(&self, model: &ParseModel, span: Span) -> Result<Module, TransformError> {
+        let use_decls = self.child(model, 0, "use_decls")?;
+        let mut uses = vec![];
+        for index in 0..use_decls.children.len() {
+            uses.push(self.use_decl(self.alternative(use_decls, index)?)?);
+        }
+
+        let mod_body_decls = self.child(model, 1, "mod_body_decls")?;
+        let mut items = vec![];
+        for index in 0..mod_body_decls.children.len() {
+            let decl = self.alternative(mod_body_decls, index)?;
+            items.push(match decl.label.as_str() {
+                "entry_or_function_decl" => Item::Function(self.function(decl)?),
+                "struct_decl" => Item::Struct(self.struct_decl(decl)?),
+                "trait_decl" => Item::Trait(self.trait_decl(decl)?),
+                "impl_decl" => Item::Impl(self.impl_decl(decl)?),
+                "enum_decl" => Item::Enum(self.enum_decl(decl)?),
+                "mod_decl" => Item::Module(self.mod_decl(decl)?),
+                "attr_decl" => Item::Attribute(self.attr_decl(decl)?),
+                _ => return Err(self.unexpected(decl, "declaration")),
+            });
+        }
+
+        return Ok(Module {
+            doc_comments: vec![],
+            attributes: vec![],
+            test: false,
+            name: vec![],
+            when: vec![],
+            uses,
+            items,
+            span,
+        });
+    }
+
+    // a doc comment over a use is only a comment, there's nothing for it to document
+    fn use_decl(&self, model: &ParseModel) -> Result<UseDecl, TransformError> {
+        let mut path = self.path(self.child(model, 2, "identifier")?, self.child(model, 3, "use_decl_next_part")?)?;
+        let mut imports = vec![];
+        match model.label.as_str() {
+            "use_decl_form_1" => {
+                // path can't be empty, it starts with the identifier
+                let name = path.pop().unwrap();
+                imports.push(UseImport {
+                    name,
+                    alias: self.alias(self.child(model, 4, "use_group_part_alias")?)?,
+                });
+            }
+            "use_decl_form_2" => {
+                let use_group_decl = self.child(model, 4, "use_group_decl")?;
+                for item in self.items(self.child(use_group_decl, 2, "use_group_part_decl")?)? {
+                    imports.push(UseImport {
+                        name: self.name_at(item, 0)?,
+                        alias: self.alias(self.child(item, 1, "use_group_part_alias")?)?,
+                    });
+                }
+            }
+            _ => return Err(self.unexpected(model, "use_decl")),
+        }
+        return Ok(UseDecl {
+            path,
+            imports,
+            span: self.span(model),
+        });
+    }
+
+    fn alias(&self, model: &ParseModel) -> Result<Option<Name>, TransformError> {
+        return match self.optional(model)? {
+            Some(item) => Ok(Some(self.name(self.child(item, 1, "alias")?)?)),
+            None => Ok(None),
+        };
+    }
+
+    // the [name: value, ...] that limits a module or attribute to some configs
+    fn config_conditions(&self, model: &ParseModel) -> Result<Vec<ConfigCondition>, TransformError> {
+        let mut result = vec![];
+        if let Some(item) = self.optional(model)? {
+            for condition in self.items(self.child(item, 1, "use_when_config_matches_prop")?)? {
+                result.push(ConfigCondition {
+                    name: self.name_at(condition, 0)?,
+                    value: self.literal_or_identifier(self.alternative(condition, 2)?)?,
+                    span: self.span(condition),
+                });
+            }
+        }
+        return Ok(result);
+    }
+
+    fn attributes(&self, model: &ParseModel) -> Result<Vec<AttrTag>, TransformError> {
+        let mut result = vec![];
+        for item in self.items(model)? {
+            let attr_tag = self.child(item, 0, "attr_tag")?;
+            let mut metadata = vec![];
+            for group in self.items(self.child(attr_tag, 2, "optional_attr_metadata_group")?)? {
+                if let Some(first) = self.optional(self.child(group, 1, "optional_attr_metadata")?)? {
+                    metadata.push(self.attr_metadata(self.child(first, 0, "attr_metadata")?)?);
+                    for next in self.items(self.child(first, 1, "optional_attr_metadata_next")?)? {
+                        metadata.push(self.attr_metadata(self.child(next, 1, "attr_metadata")?)?);
+                    }
+                }
+            }
+            result.push(AttrTag {
+                name: self.external_identifier(self.child(attr_tag, 1, "external_identifier")?)?,
+                metadata,
+                span: self.span(attr_tag),
+            });
+        }
+        return Ok(result);
+    }
+
+    fn attr_metadata(&self, model: &ParseModel) -> Result<AttrMetadata, TransformError> {
+        return Ok(AttrMetadata {
+            name: self.name_at(model, 0)?,
+            value: self.literal(self.alternative(model, 2)?)?,
+            span: self.span(model),
+        });
+    }
+
+    fn generics(&self, model: &ParseModel) -> Result<Vec<Generic>, TransformError> {
+        let mut result = vec![];
+        if let Some(item) = self.optional(model)? {
+            for generic in self.items(self.child(item, 1, "generics")?)? {
+                result.push(Generic {
+                    name: self.external_identifier(self.child(generic, 0, "external_identifier")?)?,
+                    bound: self.optional_data_type(self.child(generic, 1, "optional_generic_of_decl")?)?,
+                    span: self.span(generic),
+                });
+            }
+        }
+        return Ok(result);
+    }
+
+    // `: data_type`, if it's there
+    fn optional_data_type(&self, model: &ParseModel) -> Result<Option<Type>, TransformError> {
+        return match self.optional(model)? {
+            Some(item) => Ok(Some(self.data_type(self.alternative(item, 1)?)?)),
+            None => Ok(None),
+        };
+    }
+
+    fn data_type(&self, model: &ParseModel) -> Result<Type, TransformError> {
+        let kind = match model.label.as_str() {
+            "integer" => TypeKind::Integer,
+            "float" => TypeKind::Float,
+            "boolean" => TypeKind::Boolean,
+            "character" => TypeKind::Character,
+            "user_type_or_generic" => TypeKind::Named {
+                path: self.external_identifier(self.child(model, 0, "external_identifier")?)?,
+                generics: self.generics(self.child(model, 1, "optional_generics")?)?,
+            },
+            // attributes only have plain names
+            "identifier" => TypeKind::Named {
+                path: vec![self.name(model)?],
+                generics: vec![],
+            },
+            "array_type" | "attr_array_type" => TypeKind::Array(Box::new(self.data_type(self.alternative(model, 1)?)?)),
+            _ => return Err(self.unexpected(model, "data_type")),
+        };
+        return Ok(Type {
+            kind,
+            span: self.span(model),
+        });
+    }
+
+    fn function(&self, model: &ParseModel) -> Result<FnDecl, TransformError> {
+        let entry_point = match self.child(model, 2, "optional_entry_point_decl")?.children.first() {
+            None => None,
+            Some(entry_point) => Some(match entry_point.label.as_str() {
+                "app" => EntryPoint::App,
+                "test" => EntryPoint::Test,
+                "lib" => EntryPoint::Lib,
+                "service" => EntryPoint::Service,
+                "ui" => EntryPoint::Ui,
+                _ => return Err(self.unexpected(entry_point, "optional_entry_point_decl")),
+            }),
+        };
+        let mut result = self.signature(model, 4)?;
+        result.entry_point = entry_point;
+        result.body = Some(self.block_no_otherwise(self.child(model, 6, "block_no_otherwise")?)?);
+        return Ok(result);
+    }
+
+    // the doc comments, attributes, name and parameters of a function, whose function_name is
+    // at name_index
+    fn signature(&self, model: &ParseModel, name_index: usize) -> Result<FnDecl, TransformError> {
+        let function_name = self.child(model, name_index, "function_name")?;
+        let function_params_group = self.child(model, name_index + 1, "function_params_group")?;
+        let mut params = vec![];
+        for param in self.items(self.child(function_params_group, 1, "function_params")?)? {
+            let default = match self.optional(self.child(param, 3, "optional_param_value")?)? {
+                Some(value) => Some(self.literal(self.alternative(value, 1)?)?),
+                None => None,
+            };
+            params.push(Param {
+                name: self.name_at(param, 0)?,
+                data_type: self.data_type(self.alternative(param, 2)?)?,
+                default,
+                span: self.span(param),
+            });
+        }
+        return Ok(FnDecl {
+            doc_comments: self.doc_comments(model)?,
+            attributes: self.attributes(self.child(model, 1, "optional_attr_tags")?)?,
+            entry_point: None,
+            is_const: false,
+            name: self.name_at(function_name, 0)?,
+            generics: self.generics(self.child(function_name, 1, "optional_generics")?)?,
+            params,
+            body: None,
+            span: self.span(model),
+        });
+    }
+
+    // a function_signature_decl or a function_decl, in a trait or an impl
+    fn member_function(&self, is_const: &ParseModel, model: &ParseModel) -> Result<FnDecl, TransformError> {
+        let mut result = match model.label.as_str() {
+            "function_signature_decl" => self.signature(model, 3)?,
+            "function_decl" => {
+                let mut result = self.signature(self.child(model, 0, "function_signature_decl")?, 3)?;
+                result.body = Some(self.block_no_otherwise(self.child(model, 1, "block_no_otherwise")?)?);
+                result.span = self.span(model);
+                result
+            }
+            _ => return Err(self.unexpected(model, "function_decl")),
+        };
+        result.is_const = self.optional(is_const)?.is_some();
+        return Ok(result);
+    }
+
+    fn struct_decl(&self, model: &ParseModel) -> Result<StructDecl, TransformError> {
+        let mut members = vec![];
+        for item in self.items(self.child(model, 6, "struct_body")?)? {
+            let member = self.child(item, 0, "struct_member")?;
+            members.push(StructMember {
+                doc_comments: self.doc_comments(member)?,
+                name: self.name_at(member, 1)?,
+                data_type: self.optional_data_type(self.child(member, 2, "optional_data_type")?)?,
+                span: self.span(member),
+            });
+        }
+        return Ok(StructDecl {
+            doc_comments: self.doc_comments(model)?,
+            attributes: self.attributes(self.child(model, 1, "optional_attr_tags")?)?,
+            name: self.name_at(model, 3)?,
+            generics: self.generics(self.child(model, 4, "optional_generics")?)?,
+            members,
+            span: self.span(model),
+        });
+    }
+
+    fn enum_decl(&self, model: &ParseModel) -> Result<EnumDecl, TransformError> {
+        let mut members = vec![];
+        for item in self.items(self.child(model, 5, "enum_members")?)? {
+            let member = self.child(item, 0, "enum_member")?;
+            members.push(EnumMember {
+                doc_comments: self.doc_comments(member)?,
+                name: self.name_at(member, 1)?,
+                span: self.span(member),
+            });
+        }
+        return Ok(EnumDecl {
+            doc_comments: self.doc_comments(model)?,
+            attributes: self.attributes(self.child(model, 1, "optional_attr_tags")?)?,
+            name: self.name_at(model, 3)?,
+            members,
+            span: self.span(model),
+        });
+    }
+
+    fn trait_decl(&self, model: &ParseModel) -> Result<TraitDecl, TransformError> {
+        let mut functions = vec![];
+        for item in self.items(self.child(model, 6, "trait_body")?)? {
+            functions.push(self.member_function(self.child(item, 0, "optional_const")?, self.alternative(item, 1)?)?);
+        }
+        return Ok(TraitDecl {
+            doc_comments: self.doc_comments(model)?,
+            attributes: self.attributes(self.child(model, 1, "optional_attr_tags")?)?,
+            name: self.name_at(model, 3)?,
+            generics: self.generics(self.child(model, 4, "optional_generics")?)?,
+            functions,
+            span: self.span(model),
+        });
+    }
+
+    fn impl_decl(&self, model: &ParseModel) -> Result<ImplDecl, TransformError> {
+        let (on, on_generics) = match self.optional(self.child(model, 4, "on_optional_trait")?)? {
+            Some(on) => (Some(self.name_at(on, 1)?), self.generics(self.child(on, 2, "optional_generics")?)?),
+            None => (None, vec![]),
+        };
+        let mut functions = vec![];
+        for item in self.items(self.child(model, 6, "impl_body")?)? {
+            let impl_statement = self.child(item, 1, "impl_statement")?;
+            functions.push(self.member_function(self.child(item, 0, "optional_const")?, self.child(impl_statement, 0, "function_decl")?)?);
+        }
+        return Ok(ImplDecl {
+            doc_comments: self.doc_comments(model)?,
+            attributes: self.attributes(self.child(model, 1, "optional_attr_tags")?)?,
+            name: self.name_at(model, 3)?,
+            on,
+            on_generics,
+            functions,
+            span: self.span(model),
+        });
+    }
+
+    fn attr_decl(&self, model: &ParseModel) -> Result<AttrDecl, TransformError> {
+        let mut generic = None;
+        let mut targets = vec![];
+        if let Some(generic_decl) = self.optional(self.child(model, 3, "optional_attr_generic_decl")?)? {
+            generic = Some(self.name_at(generic_decl, 1)?);
+            if let Some(of_decl) = self.optional(self.child(generic_decl, 2, "optional_attr_generic_of_decl")?)? {
+                for item in self.items(self.child(of_decl, 1, "attr_types")?)? {
+                    let attr_type = self.alternative(item, 0)?;
+                    targets.push(match attr_type.label.as_str() {
+                        "module" => AttrTarget::Module,
+                        "struct" => AttrTarget::Struct,
+                        "impl" => AttrTarget::Impl,
+                        "trait" => AttrTarget::Trait,
+                        "function" => AttrTarget::Function,
+                        "enum" => AttrTarget::Enum,
+                        "app" => AttrTarget::App,
+                        "ui" => AttrTarget::Ui,
+                        "service" => AttrTarget::Service,
+                        "lib" => AttrTarget::Lib,
+                        _ => return Err(self.unexpected(attr_type, "attr_type")),
+                    });
+                }
+            }
+        }
+        let mut fields = vec![];
+        for item in self.items(self.child(model, 6, "attr_body")?)? {
+            fields.push(AttrField {
+                name: self.name_at(item, 0)?,
+                data_type: self.data_type(self.alternative(item, 2)?)?,
+                span: self.span(item),
+            });
+        }
+        return Ok(AttrDecl {
+            doc_comments: self.doc_comments(model)?,
+            name: self.name_at(model, 2)?,
+            generic,
+            targets,
+            when: self.config_conditions(self.child(model, 4, "use_when_config_matches_props")?)?,
+            fields,
+            span: self.span(model),
+        });
+    }
+
+    fn block_no_otherwise(&self, model: &ParseModel) -> Result<Block, TransformError> {
+        let mut statements = vec![];
+        for item in self.items(self.child(model, 1, "statements")?)? {
+            // a doc comment between statements is only a comment
+            let statement = self.alternative(item, 0)?;
+            if statement.label == "doc_comment" {
+                continue;
+            }
+            statements.push(Stmt {
+                kind: self.statement(statement)?,
+                otherwise: self.otherwise(self.child(item, 1, "optional_otherwise")?)?,
+                span: self.span(item),
+            });
+        }
+        return Ok(Block {
+            statements,
+            otherwise: None,
+            span: self.span(model),
+        });
+    }
+
+    fn block(&self, model: &ParseModel) -> Result<Block, TransformError> {
+        let mut result = self.block_no_otherwise(self.child(model, 0, "block_no_otherwise")?)?;
+        result.otherwise = self.otherwise(self.child(model, 1, "optional_otherwise")?)?.map(Box::new);
+        result.span = self.span(model);
+        return Ok(result);
+    }
+
+    fn otherwise(&self, model: &ParseModel) -> Result<Option<Otherwise>, TransformError> {
+        let item = match self.optional(model)? {
+            Some(item) => item,
+            None => return Ok(None),
+        };
+        let action = self.alternative(item, 1)?;
+        return Ok(Some(match action.label.as_str() {
+            "block" => Otherwise::Block(self.block(action)?),
+            "expression" => Otherwise::Expr(self.expression(action)?),
+            "fail_invocation" => Otherwise::Fail(self.fail(action)?),
+            _ => return Err(self.unexpected(action, "otherwise_action")),
+        }));
+    }
+
+    fn statement(&self, model: &ParseModel) -> Result<StmtKind, TransformError> {
+        return Ok(match model.label.as_str() {
+            "block" => StmtKind::Block(self.block(model)?),
+            "return_statement" => StmtKind::Return(self.expression(self.child(model, 1, "expression")?)?),
+            "for_loop_statement" => StmtKind::For {
+                variable: self.name_at(model, 1)?,
+                data_type: self.optional_data_type(self.child(model, 2, "optional_data_type")?)?,
+                iterable: self.expression(self.child(model, 4, "expression")?)?,
+                body: self.block(self.child(model, 5, "block")?)?,
+            },
+            "while_loop_statement" => {
+                let condition = match self.optional(self.child(model, 1, "optional_expression")?)? {
+                    Some(condition) => Some(self.expression(self.child(condition, 0, "expression")?)?),
+                    None => None,
+                };
+                StmtKind::While {
+                    condition,
+                    body: self.block(self.child(model, 2, "block")?)?,
+                }
+            }
+            "if_statement" => StmtKind::If {
+                condition: self.expression(self.child(model, 1, "expression")?)?,
+                body: self.block(self.child(model, 2, "block")?)?,
+            },
+            "assignment" => {
+                let value = self.expression(self.child(model, 2, "expression")?)?;
+                let target = self.alternative(model, 0)?;
+                match target.label.as_str() {
+                    "variable_declaration" => self.variable_declaration(target, Some(value))?,
+                    "qualified_identifier" => StmtKind::Assign {
+                        target: self.qualified_identifier(target)?,
+                        value,
+                    },
+                    _ => return Err(self.unexpected(target, "variable_or_variable_declaration")),
+                }
+            }
+            "variable_declaration_statement" => self.variable_declaration(self.child(model, 0, "variable_declaration")?, None)?,
+            "expression" => StmtKind::Expr(self.expression(model)?),
+            "fail_invocation" => StmtKind::Fail(self.fail(model)?),
+            _ => return Err(self.unexpected(model, "any_statement")),
+        });
+    }
+
+    fn variable_declaration(&self, model: &ParseModel, value: Option<Expr>) -> Result<StmtKind, TransformError> {
+        return Ok(StmtKind::Let {
+            name: self.name_at(model, 1)?,
+            data_type: self.optional_data_type(self.child(model, 2, "optional_data_type")?)?,
+            value,
+        });
+    }
+
+    fn fail(&self, model: &ParseModel) -> Result<Fail, TransformError> {
+        return Ok(Fail {
+            arguments: self.arguments(self.child(model, 2, "params")?)?,
+            span: self.span(model),
+        });
+    }
+
+    fn arguments(&self, model: &ParseModel) -> Result<Vec<Argument>, TransformError> {
+        let mut result = vec![];
+        for param in self.items(model)? {
+            let name = match self.optional(self.child(param, 0, "optional_param_qualifier")?)? {
+                Some(qualifier) => Some(self.name_at(qualifier, 0)?),
+                None => None,
+            };
+            result.push(Argument {
+                name,
+                value: self.expression(self.child(param, 1, "expression")?)?,
+                span: self.span(param),
+            });
+        }
+        return Ok(result);
+    }
+
+    fn boxed_expression(&self, model: &ParseModel, index: usize) -> Result<Box<Expr>, TransformError> {
+        return Ok(Box::new(self.expression(self.alternative(model, index)?)?));
+    }
+
+    // the nodes of precedence.rs, or an expression_operand
+    fn expression(&self, model: &ParseModel) -> Result<Expr, TransformError> {
+        let kind = match model.label.as_str() {
+            "expression" => self.expression(self.alternative(model, 0)?)?.kind,
+            // the parentheses are only there for the parser
+            "expression_group" => self.expression(self.child(model, 1, "expression")?)?.kind,
+            "binary_operation" => {
+                let operator = self.alternative(model, 1)?;
+                let operator = match operator.label.as_str() {
+                    "plus" => BinaryOperator::Add,
+                    "minus" => BinaryOperator::Subtract,
+                    "multiply" => BinaryOperator::Multiply,
+                    "divide" => BinaryOperator::Divide,
+                    "boolean_equals" => BinaryOperator::Equal,
+                    "boolean_not_equal" => BinaryOperator::NotEqual,
+                    "boolean_less" => BinaryOperator::Less,
+                    "boolean_greater" => BinaryOperator::Greater,
+                    "boolean_less_or_equal" => BinaryOperator::LessOrEqual,
+                    "boolean_greater_or_equal" => BinaryOperator::GreaterOrEqual,
+                    _ => return Err(self.unexpected(operator, "operator")),
+                };
+                ExprKind::Binary {
+                    operator,
+                    left: self.boxed_expression(model, 0)?,
+                    right: self.boxed_expression(model, 2)?,
+                }
+            }
+            "unary_operation" => {
+                let operator = self.alternative(model, 0)?;
+                let operator = match operator.label.as_str() {
+                    "not_operator" => UnaryOperator::Not,
+                    "minus_operator" => UnaryOperator::Negate,
+                    _ => return Err(self.unexpected(operator, "operator")),
+                };
+                ExprKind::Unary {
+                    operator,
+                    operand: self.boxed_expression(model, 1)?,
+                }
+            }
+            "cast_operation" => ExprKind::Cast {
+                value: self.boxed_expression(model, 0)?,
+                data_type: self.data_type(self.alternative(model, 2)?)?,
+            },
+            "member_access" => ExprKind::Member {
+                object: self.boxed_expression(model, 0)?,
+                member: self.name_at(model, 2)?,
+                constant: self.alternative(model, 1)?.label == "dereference_const_member",
+            },
+            "function_invocation" => ExprKind::Call {
+                function: self.boxed_expression(model, 0)?,
+                arguments: self.arguments(self.child(model, 2, "params")?)?,
+            },
+            "struct_constructor" => {
+                let struct_constructor_map = self.child(model, 1, "struct_constructor_map")?;
+                let mut fields = vec![];
+                for item in self.items(self.child(struct_constructor_map, 1, "struct_constructor_map_entries")?)? {
+                    let entry = self.child(item, 0, "struct_constructor_map_entry")?;
+                    fields.push(FieldValue {
+                        name: self.name_at(entry, 0)?,
+                        value: self.literal_or_identifier(self.alternative(entry, 2)?)?,
+                        span: self.span(entry),
+                    });
+                }
+                ExprKind::StructConstructor {
+                    name: self.name_at(model, 0)?,
+                    fields,
+                }
+            }
+            "range_expression" => ExprKind::Range {
+                start: Box::new(self.literal_or_identifier(self.alternative(model, 1)?)?),
+                end: Box::new(self.literal_or_identifier(self.alternative(model, 5)?)?),
+                inclusive: self.optional(self.child(model, 4, "optional_range_inclusive")?)?.is_some(),
+            },
+            "interpolated_string" => {
+                let mut parts = vec![];
+                self.string_part(self.child(model, 0, "interpolated_string_start")?, &mut parts)?;
+                parts.push(StringPart::Expr(self.expression(self.child(model, 1, "expression")?)?));
+                for item in self.items(self.child(model, 2, "interpolated_string_next_part")?)? {
+                    self.string_part(self.child(item, 0, "interpolated_string_middle")?, &mut parts)?;
+                    parts.push(StringPart::Expr(self.expression(self.child(item, 1, "expression")?)?));
+                }
+                self.string_part(self.child(model, 3, "interpolated_string_end")?, &mut parts)?;
+                ExprKind::InterpolatedString(parts)
+            }
+            "identifier" => ExprKind::Identifier(vec![self.name(model)?]),
+            "config" => ExprKind::Config,
+            "string_literal" | "number_literal" | "true" | "false" | "null" => return self.literal(model),
+            _ => return Err(self.unexpected(model, "expression")),
+        };
+        return Ok(Expr {
+            kind,
+            span: self.span(model),
+        });
+    }
+
+    // the text between the expressions of an interpolated string, empty text is left out
+    fn string_part(&self, model: &ParseModel, parts: &mut Vec<StringPart>) -> Result<(), TransformError> {
+        let token = model.tokens.first().ok_or_else(|| self.error(model, format!("{} has no text", describe(&model.label))))?;
+        let text = decode_string_token(&token.label, &token.value).map_err(|error| self.error(model, error.message))?;
+        if !text.is_empty() {
+            parts.push(StringPart::Text(text));
+        }
+        return Ok(());
+    }
+
+    fn literal(&self, model: &ParseModel) -> Result<Expr, TransformError> {
+        let literal = match model.label.as_str() {
+            "true" => Literal::Boolean(true),
+            "false" => Literal::Boolean(false),
+            "null" => Literal::Null,
+            "string_literal" | "number_literal" => {
+                let token = model.tokens.first().ok_or_else(|| self.error(model, format!("{} has no text", describe(&model.label))))?;
+                if model.label == "string_literal" {
+                    Literal::String(decode_string_token(&token.label, &token.value).map_err(|error| self.error(model, error.message))?)
+                } else {
+                    Literal::Number(parse_number_literal(&token.value).map_err(|error| self.error(model, error.message))?)
+                }
+            }
+            _ => return Err(self.unexpected(model, "literal")),
+        };
+        return Ok(Expr {
+            kind: ExprKind::Literal(literal),
+            span: self.span(model),
+        });
+    }
+
+    fn literal_or_identifier(&self, model: &ParseModel) -> Result<Expr, TransformError> {
+        if model.label == "qualified_identifier" {
+            return self.qualified_identifier(model);
+        }
+        return self.literal(model);
+    }
+
+    // a, a::b, config.a or "a".b, each `.b` being a member of what comes before it
+    fn qualified_identifier(&self, model: &ParseModel) -> Result<Expr, TransformError> {
+        let identifier_part = self.alternative(model, 0)?;
+        let mut result = match identifier_part.label.as_str() {
+            "external_identifier" => Expr {
+                kind: ExprKind::Identifier(self.external_identifier(identifier_part)?),
+                span: self.span(identifier_part),
+            },
+            "config" => Expr {
+                kind: ExprKind::Config,
+                span: self.span(identifier_part),
+            },
+            _ => self.literal(identifier_part)?,
+        };
+        for item in self.items(self.child(model, 1, "additional_identifier_part")?)? {
+            let member = self.name_at(item, 1)?;
+            let span = result.span.to(&member.span);
+            result = Expr {
+                kind: ExprKind::Member {
+                    object: Box::new(result),
+                    member,
+                    constant: false,
+                },
+                span,
+            };
+        }
+        return Ok(result);
+    }
+
+    fn config_decl(&self, model: &ParseModel) -> Result<ConfigDecl, TransformError> {
+        let extends = match self.optional(self.child(model, 3, "optional_config_extention")?)? {
+            Some(extension) => Some(self.name_at(extension, 1)?),
+            None => None,
+        };
+        return Ok(ConfigDecl {
+            doc_comments: self.doc_comments(model)?,
+            name: self.name_at(model, 2)?,
+            extends,
+            entries: self.config_map(self.child(model, 4, "config_map")?)?,
+            span: self.span(model),
+        });
+    }
+
+    fn config_map(&self, model: &ParseModel) -> Result<Vec<ConfigEntry>, TransformError> {
+        let mut result = vec![];
+        for item in self.items(self.child(model, 1, "config_map_entries")?)? {
+            let entry = self.child(item, 0, "config_map_entry")?;
+            result.push(ConfigEntry {
+                name: self.name_at(entry, 0)?,
+                value: self.config_value(self.alternative(entry, 2)?)?,
+                span: self.span(entry),
+            });
+        }
+        return Ok(result);
+    }
+
+    fn config_value(&self, model: &ParseModel) -> Result<ConfigValue, TransformError> {
+        let kind = match model.label.as_str() {
+            "config_map" => ConfigValueKind::Map(self.config_map(model)?),
+            "config_list" => {
+                let mut values = vec![];
+                for item in self.items(self.child(model, 1, "config_list_entries")?)? {
+                    let entry = self.child(item, 0, "config_list_entry")?;
+                    values.push(self.config_value(self.alternative(entry, 0)?)?);
+                }
+                ConfigValueKind::List(values)
+            }
+            _ => match self.literal(model) {
+                Ok(Expr { kind: ExprKind::Literal(literal), .. }) => ConfigValueKind::Literal(literal),
+                _ => return Err(self.unexpected(model, "config_value")),
+            },
+        };
+        return Ok(ConfigValue {
+            kind,
+            span: self.span(model),
+        });
+    }
+}
+
+// a rule name the way it's written in an error
+fn describe(label: &str) -> String {
+    return label.replace('_', " ");
+}
+
+#[cfg(test)]
+mod transform_tests {
+    use std::sync::Arc;
+
+    use crate::lex::lex;
+    use crate::lex::number_literal::NumberValue;
+    use crate::parse::parse_model::ParseModel;
+    use crate::parse::parse_with_recovery;
+    use crate::source::SourceMap;
+    use crate::transform::ast::*;
+    use crate::transform::transform;
+
+    fn transform_code(code: &str) -> Result<Document, String> {
+        let mut source_map = SourceMap::new();
+        let file_id = source_map.add_file(Some("main.dog"), Some("src/main.dog"), code.to_string());
+        let token_stream = lex(&source_map, file_id).unwrap();
+        let (model, _) = parse_with_recovery(token_stream, file_id);
+        return transform(&model.unwrap(), &source_map, file_id).map_err(|error| error.to_string());
+    }
+
+    fn names(path: &[Name]) -> Vec<&str> {
+        return path.iter().map(|name| name.text.as_str()).collect();
+    }
+
+    // writes the expression with a pair of parentheses around every operation
+    fn render(expr: &Expr) -> String {
+        return match &expr.kind {
+            ExprKind::Identifier(path) => names(path).join("::"),
+            ExprKind::Config => "config".to_string(),
+            ExprKind::Literal(Literal::String(text)) => format!("{:?}", text),
+            ExprKind::Literal(Literal::Number(number)) => match number.value {
+                NumberValue::Integer(value) => value.to_string(),
+                NumberValue::Float(value) => value.to_string(),
+            },
+            ExprKind::Literal(Literal::Boolean(value)) => value.to_string(),
+            ExprKind::Literal(Literal::Null) => "null".to_string(),
+            ExprKind::Binary { operator, left, right } => format!("({} {:?} {})", render(left), operator, render(right)),
+            ExprKind::Unary { operator, operand } => format!("({:?} {})", operator, render(operand)),
+            ExprKind::Cast { value, data_type } => format!("({} as {:?})", render(value), data_type.kind),
+            ExprKind::Member { object, member, constant } => format!("{}{}{}", render(object), if *constant { "::" } else { "." }, member.text),
+            ExprKind::Call { function, arguments } => {
+                let arguments: Vec<String> = arguments.iter().map(|argument| match &argument.name {
+                    Some(name) => format!("{}: {}", name.text, render(&argument.value)),
+                    None => render(&argument.value),
+                }).collect();
+                format!("{}({})", render(function), arguments.join(", "))
+            }
+            ExprKind::StructConstructor { name, fields } => {
+                let fields: Vec<String> = fields.iter().map(|field| format!("{}: {}", field.name.text, render(&field.value))).collect();
+                format!("{} {{{}}}", name.text, fields.join(", "))
+            }
+            ExprKind::Range { start, end, inclusive } => format!("[{}..{}{}]", render(start), if *inclusive { "=" } else { "" }, render(end)),
+            ExprKind::InterpolatedString(parts) => {
+                let parts: Vec<String> = parts.iter().map(|part| match part {
+                    StringPart::Text(text) => format!("{:?}", text),
+                    StringPart::Expr(expr) => format!("{{{}}}", render(expr)),
+                }).collect();
+                parts.join(" ")
+            }
+        };
+    }
+
+    #[test]
+    fn doc_comments_that_document_nothing() {
+        let code = "\
+/// The helpers.
+use std::io
+fn main() {
+    let x = 1
+    /// explain x
+    x = 2
+    /// nothing after this
+}
+";
+        let module = match transform_code(code).unwrap() {
+            Document::Module(module) => module,
+            document => panic!("not a module: {:?}", document),
+        };
+        assert_eq!(vec!["std"], names(&module.uses[0].path));
+        let Item::Function(main) = &module.items[0] else { panic!("not a function") };
+        assert_eq!(2, main.body.as_ref().unwrap().statements.len());
+    }
+
+    #[test]
+    fn transform_a_module() {
+        let code = "\
+/// The tools.
+mod tools::text [os: \"linux\"]
+use std::io::print as out
+use std::{list, map as dictionary}
+
+#check{name: \"words\"}
+struct Words<T: Display> {
+  /// How many.
+  count: int;
+  names: [std::String]
+}
+
+enum Color { Red, Green }
+
+trait Shape {
+  fn area(scale: float)
+  fn name() { return \"shape\" }
+}
+
+impl Square on Shape {
+  const fn area(scale: float = 1.5) {
+    return -side * side as float + 1
+  }
+}
+
+app fn main(x: int) {
+  let a: int = f(x, size: 2).count
+  total = a + b - c
+  let done
+  for item in [1..=10] {
+    print(\"item {item}!\")
+  }
+  while { g() } otherwise fail(\"no\")
+  if !done == true { return null }
+}
+";
+        let module = match transform_code(code).unwrap() {
+            Document::Module(module) => module,
+            document => panic!("not a module: {:?}", document),
+        };
+        assert_eq!(vec!["tools", "text"], names(&module.name));
+        assert_eq!(vec!["The tools."], module.doc_comments);
+        assert_eq!("os", module.when[0].name.text);
+        assert_eq!("\"linux\"", render(&module.when[0].value));
+
+        assert_eq!(vec!["std", "io"], names(&module.uses[0].path));
+        assert_eq!("print", module.uses[0].imports[0].name.text);
+        assert_eq!("out", module.uses[0].imports[0].alias.as_ref().unwrap().text);
+        assert_eq!(vec!["std"], names(&module.uses[1].path));
+        assert_eq!("dictionary", module.uses[1].imports[1].alias.as_ref().unwrap().text);
+        assert_eq!(5, module.items.len());
+
+        let Item::Struct(words) = &module.items[0] else { panic!("not a struct") };
+        assert_eq!("Words", words.name.text);
+        assert_eq!(vec!["check"], names(&words.attributes[0].name));
+        assert_eq!("\"words\"", render(&words.attributes[0].metadata[0].value));
+        assert_eq!(vec!["T"], names(&words.generics[0].name));
+        assert_eq!(vec!["How many."], words.members[0].doc_comments);
+        assert_eq!(Some(TypeKind::Integer), words.members[0].data_type.as_ref().map(|data_type| data_type.kind.clone()));
+        let Some(TypeKind::Array(element)) = words.members[1].data_type.as_ref().map(|data_type| &data_type.kind) else { panic!("not an array") };
+        let TypeKind::Named { path, .. } = &element.kind else { panic!("not a named type") };
+        assert_eq!(vec!["std", "String"], names(path));
+
+        let Item::Enum(color) = &module.items[1] else { panic!("not an enum") };
+        let members: Vec<&str> = color.members.iter().map(|member| member.name.text.as_str()).collect();
+        assert_eq!(vec!["Red", "Green"], members);
+
+        let Item::Trait(shape) = &module.items[2] else { panic!("not a trait") };
+        assert!(shape.functions[0].body.is_none());
+        assert!(shape.functions[1].body.is_some());
+
+        let Item::Impl(square) = &module.items[3] else { panic!("not an impl") };
+        assert_eq!("Shape", square.on.as_ref().unwrap().text);
+        let area = &square.functions[0];
+        assert!(area.is_const);
+        assert_eq!("1.5", render(area.params[0].default.as_ref().unwrap()));
+        let StmtKind::Return(value) = &area.body.as_ref().unwrap().statements[0].kind else { panic!("not a return") };
+        assert_eq!("(((Negate side) Multiply (side as Float)) Add 1)", render(value));
+
+        let Item::Function(main) = &module.items[4] else { panic!("not a function") };
+        assert_eq!(Some(EntryPoint::App), main.entry_point);
+        let statements = &main.body.as_ref().unwrap().statements;
+        let StmtKind::Let { name, value: Some(value), .. } = &statements[0].kind else { panic!("not a let") };
+        assert_eq!("a", name.text);
+        assert_eq!("f(x, size: 2).count", render(value));
+        let StmtKind::Assign { target, value } = &statements[1].kind else { panic!("not an assignment") };
+        assert_eq!("total", render(target));
+        assert_eq!("((a Add b) Subtract c)", render(value));
+        assert!(matches!(&statements[2].kind, StmtKind::Let { value: None, .. }));
+        let StmtKind::For { variable, iterable, body, .. } = &statements[3].kind else { panic!("not a for") };
+        assert_eq!("item", variable.text);
+        assert_eq!("[1..=10]", render(iterable));
+        let StmtKind::Expr(print) = &body.statements[0].kind else { panic!("not an expression") };
+        assert_eq!("print(\"item \" {item} \"!\")", render(print));
+        let StmtKind::While { condition: None, body } = &statements[4].kind else { panic!("not a while") };
+        assert!(matches!(body.otherwise.as_deref(), Some(Otherwise::Fail(_))));
+        let StmtKind::If { condition, .. } = &statements[5].kind else { panic!("not an if") };
+        assert_eq!("((Not done) Equal true)", render(condition));
+
+        // the spans are where the declarations are in the code
+        assert_eq!((6, 1), (words.span.line_number, words.span.line_offset));
+        assert_eq!(&code[main.span.start..main.span.end], &code[code.find("app fn").unwrap()..code.len() - 1]);
+    }
+
+    #[test]
+    fn transform_a_config() {
+        let code = "/// Everything.\nconfig Base { name: \"dog\", tags: [\"a\", 2], nested: { debug: true } }\nconfig Release : Base { level: 3 }\n";
+        let Document::Config(decls) = transform_code(code).unwrap() else { panic!("not a config") };
+        assert_eq!(2, decls.len());
+        assert_eq!(vec!["Everything."], decls[0].doc_comments);
+        assert_eq!("Base", decls[1].extends.as_ref().unwrap().text);
+        assert_eq!(ConfigValueKind::Literal(Literal::String("dog".to_string())), decls[0].entries[0].value.kind);
+        let ConfigValueKind::List(tags) = &decls[0].entries[1].value.kind else { panic!("not a list") };
+        assert_eq!(2, tags.len());
+        let ConfigValueKind::Map(nested) = &decls[0].entries[2].value.kind else { panic!("not a map") };
+        assert_eq!(ConfigValueKind::Literal(Literal::Boolean(true)), nested[0].value.kind);
+        assert_eq!((3, 1), (decls[1].span.line_number, decls[1].span.line_offset));
+    }
+
+    #[test]
+    fn reject_malformed_models() {
+        // recovery leaves the part that didn't parse in the model
+        assert_eq!(Err("expected statements, found a syntax error at main.dog:2:4".to_string()),
+                   transform_code("fn main() {\n  f(let)\n}\n").map(|_| ()));
+
+        // a model that the grammar wouldn't make
+        let mut source_map = SourceMap::new();
+        let file_id = source_map.add_file(Some("main.dog"), Some("src/main.dog"), "x".to_string());
+        let token_stream = lex(&source_map, file_id).unwrap();
+        let identifier = ParseModel {
+            label: "identifier".to_string(),
+            tokens: token_stream.matches.clone(),
+            children: vec![],
+            trivia: vec![],
+        };
+        let no_name = ParseModel {
+            label: "optional_mod_name_decl".to_string(),
+            tokens: vec![],
+            children: vec![],
+            trivia: vec![],
+        };
+        let model = ParseModel {
+            label: "module_document".to_string(),
+            tokens: vec![],
+            children: vec![Arc::new(no_name), Arc::new(identifier)],
+            trivia: vec![],
+        };
+        assert_eq!("expected mod body, found identifier at main.dog:1:1",
+                   transform(&model, &source_map, file_id).unwrap_err().to_string());
+    }
+}
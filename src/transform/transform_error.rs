@@ -0,0 +1,18 @@
+use std::fmt;
+
+use crate::source::Span;
+
+// A part of the parse model that isn't shaped the way the grammar says it would be, or that
+// holds something the parser couldn't read, like: expected block, found expression at main.dog:3:5
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransformError {
+    pub message: String,
+    pub file_name: String,
+    pub span: Span,
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}:{}:{}", self.message, self.file_name, self.span.line_number, self.span.line_offset)
+    }
+}